@@ -0,0 +1,166 @@
+use crate::Reference;
+use crate::mst::{Descent, MST, TreeStore};
+use crate::store::{Page, Store};
+use crate::utils::{KeyComparable, Merge, MstHasher};
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// A page store whose operations complete asynchronously, such as one backed by
+/// object storage
+///
+/// This is the async counterpart of `PageStore`. Pages are returned by value,
+/// since a remote backend has nothing to lend a borrow from. The returned futures
+/// are `Send`, so a tree over such a store can be driven from a multithreaded
+/// executor.
+pub trait AsyncPageStore<Key, Value> {
+    /// Retrieves a page by its key
+    fn get(&self, key: Key) -> impl Future<Output = Option<Value>> + Send;
+
+    /// Stores a page under its key, returning the key
+    fn put(&mut self, key: Key, value: Value) -> impl Future<Output = Key> + Send;
+
+    /// Checks whether a page is stored under the key
+    fn has(&self, key: Key) -> impl Future<Output = bool> + Send;
+}
+
+/// The in-memory store, whose futures are ready as soon as they are created
+impl<Key, Value> AsyncPageStore<Key, Value> for Store<Key, Value>
+where
+    Key: AsRef<[u8]> + Eq + Hash + Copy + Send,
+    Value: Reference<Key = Key> + Clone + Send,
+{
+    fn get(&self, key: Key) -> impl Future<Output = Option<Value>> + Send {
+        let page = Store::get(self, key).cloned();
+        async move { page }
+    }
+
+    fn put(&mut self, key: Key, value: Value) -> impl Future<Output = Key> + Send {
+        let key = Store::put(self, key, value);
+        async move { key }
+    }
+
+    fn has(&self, key: Key) -> impl Future<Output = bool> + Send {
+        let present = Store::has(self, key);
+        async move { present }
+    }
+}
+
+/// A Merkle Search Tree over an `AsyncPageStore`
+///
+/// There is no page cache: every walk through the tree becomes a sequence of
+/// awaited page fetches, one per page visited, each issued only after the page
+/// before it has arrived. A lookup therefore costs one round trip per level.
+/// An insert fetches the pages on the key's path together with their direct
+/// children, builds the new pages in memory exactly as `MST::insert` would, and
+/// then writes only the pages that did not exist before.
+pub struct MstAsync<Value, H = Sha256, S = TreeStore<Value, H>>
+where
+    Value: Hash,
+    H: MstHasher,
+{
+    /// The hash key of the root node, or None for an empty tree
+    pub root: Option<H::Key>,
+    /// Asynchronous storage mapping hash keys to pages
+    pub store: S,
+    marker: PhantomData<fn() -> (H, Value)>,
+}
+
+impl<Value, H, S> MstAsync<Value, H, S>
+where
+    Value: AsRef<[u8]>
+        + Hash
+        + Reference<Key = H::Key>
+        + Clone
+        + std::fmt::Debug
+        + Merge
+        + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: AsyncPageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// Creates a tree over `store` with the given root, None for an empty tree
+    pub fn with_store(root: Option<H::Key>, store: S) -> Self {
+        MstAsync {
+            root,
+            store,
+            marker: PhantomData,
+        }
+    }
+
+    /// Retrieves the value for a key, fetching one page per level on its path
+    ///
+    /// Tombstones read as absent, like in `MST::get_value`.
+    pub async fn get_value(&self, search_key: H::Key) -> Option<Value> {
+        let mut descent = Descent::new(self.root);
+
+        while let Some(current) = descent.next_key() {
+            let page = self.store.get(current).await?;
+            if let Ok(index) = descent.step(&page, &search_key)? {
+                let value = &page.list[index].value;
+                return (!value.is_tombstone()).then(|| value.clone());
+            }
+        }
+
+        None
+    }
+
+    /// Inserts a key-value pair, merging the value into an existing one like
+    /// `MST::insert`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Value::compare_keys` is not a consistent total order, like `MST::insert`.
+    ///
+    /// # Returns
+    ///
+    /// The new root key
+    pub async fn insert(&mut self, item_key: H::Key, item_value: Value) -> H::Key {
+        let fetched = self.fetch_insert_path(item_key).await;
+        let known: HashSet<H::Key> = fetched.iter().map(|(key, _)| *key).collect();
+
+        let mut local = MST::<Value, H>::with_store_and_hasher(self.root, fetched);
+        let root = local.insert(item_key, item_value);
+
+        for key in local.store.reachable_set(root) {
+            if !known.contains(&key)
+                && let Some(page) = local.store.remove(key)
+            {
+                self.store.put(key, page).await;
+            }
+        }
+        self.root = Some(root);
+        root
+    }
+
+    /// Fetches the pages an insert of `key` reads: those on its search path, and
+    /// their children, whose subtree sizes go into the rebuilt pages
+    async fn fetch_insert_path(&self, key: H::Key) -> TreeStore<Value, H> {
+        let mut pages = Store::new();
+        let mut descent = Descent::new(self.root);
+
+        while let Some(current) = descent.next_key() {
+            let Some(page) = self.store.get(current).await else {
+                break;
+            };
+
+            // A page holding the key is the last
+            if descent.step(&page, &key).is_none() {
+                break;
+            }
+            let child = descent.next_key();
+            for sibling in page.refs() {
+                if Some(sibling) != child
+                    && let Some(sibling_page) = self.store.get(sibling).await
+                {
+                    pages.put(sibling, sibling_page);
+                }
+            }
+
+            pages.put(current, page);
+        }
+
+        pages
+    }
+}
@@ -0,0 +1,112 @@
+use crate::error::MstError;
+use sha2::{Digest, Sha256};
+
+/// A Bloom filter over the keys of a tree, built by `MST::bloom_summary`
+///
+/// `maybe_contains` never returns false for a key that was added, but may return
+/// true for one that was not. With `n` keys, `m` bits and `k` hash functions the
+/// false-positive rate is about `(1 - e^(-k * n / m))^k`: more bits per key make
+/// it smaller at the cost of a larger filter, and `k` near `0.69 * m / n` is the
+/// best choice for a given size. For example 10 bits per key with 7 hashes gives
+/// roughly 1% false positives.
+///
+/// Bit positions are derived from the SHA-256 of the key bytes, whatever hasher
+/// the tree uses, so a filter can be checked by a client that only has the keys.
+/// `to_bytes` and `from_bytes` let it be shipped alongside the root hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    len: usize,
+    hashes: u8,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter of `bits` bits probed by `hashes` hash functions
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` or `hashes` is zero.
+    pub fn new(bits: usize, hashes: u8) -> Self {
+        assert!(bits > 0, "a Bloom filter needs at least one bit");
+        assert!(
+            hashes > 0,
+            "a Bloom filter needs at least one hash function"
+        );
+        BloomFilter {
+            bits: vec![0; bits.div_ceil(8)],
+            len: bits,
+            hashes,
+        }
+    }
+
+    /// Returns the number of bits in the filter
+    pub fn bits(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of hash functions probed per key
+    pub fn hashes(&self) -> u8 {
+        self.hashes
+    }
+
+    /// Adds a key to the filter
+    pub fn insert<K: AsRef<[u8]>>(&mut self, key: K) {
+        for index in self.positions(key.as_ref()) {
+            self.bits[index / 8] |= 1 << (index % 8);
+        }
+    }
+
+    /// Returns false if the key was definitely not added, true if it may have been
+    pub fn maybe_contains<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        self.positions(key.as_ref())
+            .all(|index| self.bits[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// Encodes the filter as bytes for `from_bytes`
+    ///
+    /// The layout is the bit count as a big-endian `u64`, the hash count as one
+    /// byte, then the bits packed eight to a byte, least significant bit first.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9 + self.bits.len());
+        bytes.extend_from_slice(&(self.len as u64).to_be_bytes());
+        bytes.push(self.hashes);
+        bytes.extend_from_slice(&self.bits);
+        bytes
+    }
+
+    /// Decodes a filter produced by `to_bytes`
+    ///
+    /// # Returns
+    /// The filter, or `MstError::InvalidBloomEncoding` if the bytes are truncated,
+    /// have trailing data, or describe an empty filter
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MstError> {
+        let (header, bits) = bytes
+            .split_first_chunk::<9>()
+            .ok_or(MstError::InvalidBloomEncoding)?;
+        let len = u64::from_be_bytes(header[..8].try_into().unwrap());
+        let len = usize::try_from(len).map_err(|_| MstError::InvalidBloomEncoding)?;
+        let hashes = header[8];
+
+        if len == 0 || hashes == 0 || bits.len() != len.div_ceil(8) {
+            return Err(MstError::InvalidBloomEncoding);
+        }
+
+        Ok(BloomFilter {
+            bits: bits.to_vec(),
+            len,
+            hashes,
+        })
+    }
+
+    /// The bit positions probed for a key, by double hashing one SHA-256 digest
+    fn positions(&self, key: &[u8]) -> impl Iterator<Item = usize> + use<> {
+        let digest = Sha256::digest(key);
+        let first = u64::from_be_bytes(digest[..8].try_into().unwrap());
+        // Odd, so the probes cycle through every position when `len` is a power of two
+        let step = u64::from_be_bytes(digest[8..16].try_into().unwrap()) | 1;
+        let len = self.len as u64;
+
+        (0..self.hashes as u64)
+            .map(move |i| (first.wrapping_add(i.wrapping_mul(step)) % len) as usize)
+    }
+}
@@ -0,0 +1,223 @@
+use crate::Reference;
+use crate::store::{PageStore, Store};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::{Arc, RwLock};
+
+/// A page store that can be shared between threads and updated through `&self`
+///
+/// An `MST` over the plain `Store` is already `Sync`, so wrapping it in an `Arc`
+/// serves concurrent `get_value` calls without any locking. This store is for a
+/// pool of pages written while it is being read, such as a replica receiving pages
+/// from peers while answering requests. Reads take a shared lock, so they never
+/// wait on each other, only on a concurrent write. Clones are handles to the same
+/// pages.
+///
+/// Pages are handed out as `Arc`s rather than borrows, which is why this type does
+/// not implement `PageStore` itself. To back an `MST`, take a `handle`, which
+/// writes through to the shared pages, or a `snapshot`, which copies them into a
+/// plain `Store`.
+pub struct ConcurrentStore<Key, Value> {
+    pages: Arc<RwLock<HashMap<Key, Arc<Value>>>>,
+}
+
+impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> ConcurrentStore<Key, Value> {
+    pub fn new() -> Self {
+        ConcurrentStore {
+            pages: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn put(&self, key: Key, value: Value) -> Key {
+        self.pages.write().unwrap().insert(key, Arc::new(value));
+        key
+    }
+
+    pub fn get(&self, key: Key) -> Option<Arc<Value>> {
+        self.pages.read().unwrap().get(&key).cloned()
+    }
+
+    pub fn has(&self, key: Key) -> bool {
+        self.pages.read().unwrap().contains_key(&key)
+    }
+
+    pub fn remove(&self, key: Key) -> Option<Arc<Value>> {
+        self.pages.write().unwrap().remove(&key)
+    }
+
+    /// Collects the keys referenced from `root`, directly or transitively, that
+    /// are not in the store, like `PageStore::missing_set`. The whole walk sees
+    /// one consistent state of the store.
+    pub fn missing_set(&self, root: Key) -> HashSet<Key> {
+        let pages = self.pages.read().unwrap();
+        let mut result = HashSet::new();
+        let mut to_visit = vec![root];
+        let mut visited = HashSet::new();
+
+        while let Some(hash) = to_visit.pop() {
+            if !visited.insert(hash) {
+                continue;
+            }
+            match pages.get(&hash) {
+                None => {
+                    result.insert(hash);
+                }
+                Some(page) => to_visit.extend(page.refs()),
+            }
+        }
+
+        result
+    }
+
+    /// Returns a handle on the current pages that implements `PageStore`, see
+    /// `ConcurrentStoreHandle`
+    pub fn handle(&self) -> ConcurrentStoreHandle<Key, Value> {
+        ConcurrentStoreHandle {
+            shared: self.clone(),
+            pages: self.pages.read().unwrap().clone(),
+        }
+    }
+
+    /// Copies the current pages into a plain `Store`, e.g. to build an `MST`
+    pub fn snapshot(&self) -> Store<Key, Value>
+    where
+        Value: Clone,
+    {
+        let mut store = Store::new();
+        for (key, page) in self.pages.read().unwrap().iter() {
+            store.put(*key, Value::clone(page));
+        }
+        store
+    }
+}
+
+impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Default
+    for ConcurrentStore<Key, Value>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Key, Value> Clone for ConcurrentStore<Key, Value> {
+    fn clone(&self) -> Self {
+        ConcurrentStore {
+            pages: Arc::clone(&self.pages),
+        }
+    }
+}
+
+/// A `PageStore` over the pages of a `ConcurrentStore`, so it can back an `MST`
+///
+/// The handle keeps its own map of the pages, sharing each one with the store
+/// through its `Arc` rather than copying it, so `get` can lend a borrow without
+/// holding the lock. Pages it writes are written to the shared store too, and so
+/// are seen by every other handle after a `refresh`. Pages others write after
+/// the handle was taken are not seen until then.
+///
+/// Removals only drop the page from the handle. The shared store holds the
+/// pages of every tree over it, so `MST::gc` or consuming a tree with
+/// `into_iter` must not delete pages other trees still use; delete from the
+/// shared store deliberately through `shared`.
+///
+/// # Example
+/// ```
+/// use mst::{ConcurrentStore, MST};
+/// use mst::test_utils::{TestValue, create_key};
+/// use sha2::Sha256;
+///
+/// let shared = ConcurrentStore::new();
+/// let mut tree: MST<TestValue, Sha256, _> = MST::with_store_and_hasher(None, shared.handle());
+/// let key = create_key(b"key");
+/// tree.insert(key, TestValue { key, data: [1, 0, 0, 0] });
+///
+/// // The pages of the tree are in the shared store
+/// assert!(shared.missing_set(tree.root.unwrap()).is_empty());
+/// ```
+pub struct ConcurrentStoreHandle<Key, Value> {
+    shared: ConcurrentStore<Key, Value>,
+    pages: HashMap<Key, Arc<Value>>,
+}
+
+impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>>
+    ConcurrentStoreHandle<Key, Value>
+{
+    /// Returns the shared store the handle writes to
+    pub fn shared(&self) -> &ConcurrentStore<Key, Value> {
+        &self.shared
+    }
+
+    /// Catches up with the shared store, taking in the pages others wrote and
+    /// dropping those they removed since the handle was taken
+    pub fn refresh(&mut self) {
+        self.pages = self.shared.pages.read().unwrap().clone();
+    }
+}
+
+impl<Key, Value> PageStore<Key, Value> for ConcurrentStoreHandle<Key, Value>
+where
+    Key: AsRef<[u8]> + Eq + Hash + Copy,
+    Value: Reference<Key = Key> + Clone,
+{
+    type Iter<'a>
+        = Box<dyn Iterator<Item = (&'a Key, &'a Value)> + 'a>
+    where
+        Self: 'a,
+        Key: 'a,
+        Value: 'a;
+
+    fn put(&mut self, key: Key, value: Value) -> Key {
+        let page = Arc::new(value);
+        self.shared
+            .pages
+            .write()
+            .unwrap()
+            .insert(key, Arc::clone(&page));
+        self.pages.insert(key, page);
+        key
+    }
+
+    /// Takes the write lock once for the whole batch
+    fn put_many(&mut self, pages: impl IntoIterator<Item = (Key, Value)>) {
+        let mut shared = self.shared.pages.write().unwrap();
+        for (key, value) in pages {
+            let page = Arc::new(value);
+            shared.insert(key, Arc::clone(&page));
+            self.pages.insert(key, page);
+        }
+    }
+
+    fn get(&self, key: Key) -> Option<&Value> {
+        self.pages.get(&key).map(Arc::as_ref)
+    }
+
+    /// Drops the page from the handle only, leaving it in the shared store
+    ///
+    /// Returns the page, a copy of it if the shared store or another handle
+    /// still holds it.
+    fn remove(&mut self, key: Key) -> Option<Value> {
+        self.pages.remove(&key).map(Arc::unwrap_or_clone)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        Box::new(self.pages.iter().map(|(key, page)| (key, page.as_ref())))
+    }
+}
+
+impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Default
+    for ConcurrentStoreHandle<Key, Value>
+{
+    /// A handle on a new, empty `ConcurrentStore`
+    fn default() -> Self {
+        ConcurrentStore::new().handle()
+    }
+}
+
+impl<Key: Clone, Value> Clone for ConcurrentStoreHandle<Key, Value> {
+    fn clone(&self) -> Self {
+        ConcurrentStoreHandle {
+            shared: self.shared.clone(),
+            pages: self.pages.clone(),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+
+/// Errors returned by fallible tree operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MstError {
+    /// `KeyComparable::compare_keys` contradicted itself, so it is not a total order
+    InconsistentOrdering,
+    /// Bytes passed to `decode_page` are not a valid page encoding
+    InvalidPageEncoding,
+    /// Input expected in key order was not sorted
+    UnsortedInput,
+    /// Trees passed to `MST::join` have overlapping key ranges
+    RangesOverlap,
+    /// Bytes passed to `BloomFilter::from_bytes` are not a valid filter encoding
+    InvalidBloomEncoding,
+}
+
+impl fmt::Display for MstError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MstError::InconsistentOrdering => {
+                write!(f, "key comparison is not a consistent total order")
+            }
+            MstError::InvalidPageEncoding => write!(f, "invalid page encoding"),
+            MstError::UnsortedInput => write!(f, "input is not sorted by key"),
+            MstError::RangesOverlap => write!(f, "key ranges of the trees overlap"),
+            MstError::InvalidBloomEncoding => write!(f, "invalid Bloom filter encoding"),
+        }
+    }
+}
+
+impl std::error::Error for MstError {}
+
+/// Corruption found by `MST::verify_integrity` or `MST::import_validated`, naming
+/// the first offending page key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityError<K> {
+    /// The page stored under this key does not hash to it
+    HashMismatch(K),
+    /// This key is referenced by the tree but has no page in the store
+    MissingPage(K),
+    /// The page stored under this key is not at a lower level than the page
+    /// pointing to it, as when a pointer leads back up to an ancestor
+    Cycle(K),
+    /// The page stored under this key breaks the tree's layout: its entries are
+    /// not in strictly ascending key order, or its `subtree_size` is not the
+    /// number of its entries plus the sizes of its children
+    Malformed(K),
+}
+
+impl<K: fmt::Debug> fmt::Display for IntegrityError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityError::HashMismatch(key) => {
+                write!(f, "page stored under {:?} does not match its hash", key)
+            }
+            IntegrityError::MissingPage(key) => write!(f, "page {:?} is missing", key),
+            IntegrityError::Cycle(key) => {
+                write!(f, "page {:?} is not below the page pointing to it", key)
+            }
+            IntegrityError::Malformed(key) => {
+                write!(f, "page {:?} is out of order or miscounts its subtree", key)
+            }
+        }
+    }
+}
+
+impl<K: fmt::Debug> std::error::Error for IntegrityError<K> {}
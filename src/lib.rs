@@ -1,16 +1,40 @@
+#[cfg(feature = "async")]
+pub mod async_store;
+pub mod bloom;
+#[cfg(feature = "concurrent")]
+pub mod concurrent;
+pub mod error;
 pub mod mst;
+pub mod proof;
 pub mod store;
 pub mod test_utils;
 pub mod utils;
 
 // Re-export main items for convenience
-pub use mst::MST;
+#[cfg(feature = "async")]
+pub use async_store::{AsyncPageStore, MstAsync};
+pub use bloom::BloomFilter;
+#[cfg(feature = "concurrent")]
+pub use concurrent::{ConcurrentStore, ConcurrentStoreHandle};
+pub use error::{IntegrityError, MstError};
+pub use mst::{
+    Divergence, Entry, FixedKeyMST, IntoIter, LevelMapper, MST, MergePolicy, MergeResult, MstEvent,
+    MstView, PagePath, TraversalControl, TraversalEvent, TraversalStrategy, TreeStats, TreeStore,
+};
+pub use proof::{
+    AbsenceProof, InclusionProof, ProofEntry, ProofPage, StreamVerifier, verify_absence_proof,
+    verify_absence_proof_with, verify_proof, verify_proof_with,
+};
 pub use store::Store;
-pub use store::{Page, PageData};
-pub use utils::{KeyComparable, MSTKey, Merge, calc_level, compare, hash};
+pub use store::{OverlayStore, Page, PageData, PageStore};
+pub use utils::{
+    DEFAULT_LEVEL_BASE, FixedKey, KeyComparable, LEVEL_FORMAT_VERSION, MAX_LEVEL, MAX_RAW_KEY_LEN,
+    MSTKey, Merge, MstHasher, Truncated, calc_level, calc_level_base, calc_level_base_with,
+    calc_level_with, compare, hash, hash_with, raw_key, raw_key_bytes,
+};
 
-// Re-export hash_page at the crate root
-pub use mst::hash_page;
+// Re-export hash_page, hash_value and the page encoding at the crate root
+pub use mst::{decode_page, encode_page, hash_page, hash_page_with, hash_value, hash_value_with};
 
 /// A trait for types that can reference other objects via keys
 ///
@@ -41,4 +65,14 @@ pub trait Reference {
 
     /// Returns a vector of keys that this object references
     fn refs(&self) -> Vec<Self::Key>;
+
+    /// Returns the keys of content the object keeps alive without it being part
+    /// of its own structure, none unless overridden
+    ///
+    /// A `Page` reports what its values reference here, so that
+    /// `PageStore::reachable_set` and `MST::gc` keep that content while walks of
+    /// the tree itself only follow `refs`.
+    fn value_refs(&self) -> Vec<Self::Key> {
+        Vec::new()
+    }
 }
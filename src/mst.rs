@@ -1,44 +1,137 @@
-use crate::calc_level;
-use crate::store::{Page, PageData};
+use crate::bloom::BloomFilter;
+use crate::error::{IntegrityError, MstError};
+use crate::proof::{AbsenceProof, InclusionProof, ProofPage};
+use crate::store::{Page, PageData, PageStore, ScratchStore};
 use crate::utils::KeyComparable;
-use crate::utils::Merge;
+use crate::utils::{DEFAULT_LEVEL_BASE, LEVEL_FORMAT_VERSION, calc_level_base_with};
+use crate::utils::{Merge, MstHasher, Truncated, hash_with, raw_key, raw_key_bytes};
 use crate::{MSTKey, Reference, Store};
-use sha2::Digest;
 use sha2::Sha256;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fs;
 use std::hash::Hash;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+/// The in-memory store holding the pages of a tree addressed by `H`
+pub type TreeStore<Value, H = Sha256> =
+    Store<<H as MstHasher>::Key, Page<<H as MstHasher>::Key, Value>>;
+
+/// An MST over `N`-byte keys taken from truncated SHA-256 digests
+///
+/// `MST<Value>` keeps today's 32-byte `MSTKey`; this alias is for smaller key spaces.
+pub type FixedKeyMST<Value, const N: usize> = MST<Value, Truncated<Sha256, N>>;
+
+/// Pages on a search path with their keys, from the root down, as returned by `get_path`
+pub type PagePath<'a, K, V> = Vec<(K, &'a Page<K, V>)>;
+
+/// The pair of page keys, one per tree, where `first_divergence` found two trees
+/// parting ways. A side is None where that tree has no subtree.
+pub type Divergence<K> = (Option<K>, Option<K>);
+
+/// Maps the level `calc_level_base` gives a key to the level it is placed at,
+/// see `MST::with_level_mapper`
+pub type LevelMapper = fn(u32) -> u32;
+
+/// How a tree combines a value with one already stored under the same key, see
+/// `MST::with_merge_policy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Combine the values with `Merge`, as `existing.merge(new)`
+    #[default]
+    Merge,
+    /// Keep the new value without calling `Merge`
+    Replace,
+}
+
+impl MergePolicy {
+    /// Combines the value already stored, `existing`, with `new` under this policy
+    pub fn resolve<Value: Merge>(self, existing: Value, new: Value) -> Value {
+        match self {
+            MergePolicy::Merge => existing.merge(new),
+            MergePolicy::Replace => new,
+        }
+    }
+}
+
+/// The root and store of a merged tree, or the first page missing from either
+/// input, as returned by `MST::merge`
+pub type MergeResult<K, S> = Result<(Option<K>, S), IntegrityError<K>>;
+
+/// Shape of the pages reachable from a tree's root, as returned by `stats`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TreeStats {
+    /// Number of pages reachable from the root
+    pub pages: usize,
+    /// Number of entries across those pages
+    pub entries: usize,
+    /// Fewest entries in a single page
+    pub min_fanout: usize,
+    /// Most entries in a single page
+    pub max_fanout: usize,
+    /// Mean number of entries per page
+    pub average_fanout: f64,
+    /// Highest page level
+    pub max_level: u32,
+    /// Number of different page levels present
+    pub distinct_levels: usize,
+}
 
 /// A Merkle Search Tree combines properties of search trees with content-addressable storage,
 /// providing efficient lookups while cryptographically verifying content.
 ///
 /// # Key Features
-/// - Content-addressed via SHA-256 hashes
+/// - Content-addressed via SHA-256 hashes, or any other `MstHasher`
 /// - Self-balancing structure
 /// - Efficient search and insertion
 /// - Tree merging support
 ///
 /// # Type Parameters
-/// * `Value`: Must implement `Hash`, `Debug`, `AsRef<[u8]>`, `Reference`, `Copy`, and `Merge`
-pub struct MST<Value: Hash + std::fmt::Debug + KeyComparable<Key = MSTKey>> {
-    /// The hash key of the root node
-    pub root: MSTKey,
+/// * `Value`: Must implement `Hash`, `Debug`, `AsRef<[u8]>`, `Reference`, `Clone`, and `Merge`
+/// * `H`: The hash function addressing pages and placing keys, SHA-256 by default.
+///   Its output type is the tree's key type.
+/// * `S`: The `PageStore` holding the pages, the in-memory `Store` by default
+pub struct MST<Value, H = Sha256, S = TreeStore<Value, H>>
+where
+    Value: Hash + std::fmt::Debug + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// The hash key of the root node, or None for an empty tree
+    ///
+    /// Emptiness is never encoded as a key, so a page that happens to hash to
+    /// all zeros is stored and found like any other.
+    pub root: Option<H::Key>,
     /// Content-addressable storage mapping hash keys to pages
-    pub store: Store<MSTKey, Page<MSTKey, Value>>,
+    pub store: S,
+    /// Number of entries reachable from the root
+    len: usize,
+    /// Base of the levels keys are placed at, see `with_level_base`
+    level_base: u32,
+    /// Applied to each key's level before placement, see `with_level_mapper`
+    level_mapper: LevelMapper,
+    /// How a value meets an existing one under the same key, see `with_merge_policy`
+    merge_policy: MergePolicy,
+    /// Receives structural events, see `set_observer`
+    observer: Option<Observer<H::Key>>,
+    marker: PhantomData<fn() -> (H, Value)>,
 }
 
 impl<
     Value: AsRef<[u8]>
         + Hash
         + Reference<Key = MSTKey>
-        + Copy
+        + Clone
         + std::fmt::Debug
         + Merge
         + KeyComparable<Key = MSTKey>,
 > MST<Value>
 {
-    /// Creates a new empty MST with the default root key
+    /// Creates a new empty MST
     ///
     /// # Example
     /// ```
@@ -48,26 +141,42 @@ impl<
     /// let mst: MST<TestValue> = MST::new();
     /// ```
     pub fn new() -> Self {
-        Self {
-            root: MSTKey::default(),
-            store: Store::new(),
-        }
+        Self::with_hasher()
     }
 
-    /// Creates a new empty MST with the specified root key
+    /// Creates a new empty MST whose store has room for at least `capacity` pages
+    ///
+    /// A tree of `n` entries has about `n / 2` pages, and every insert also leaves
+    /// replaced pages behind until `gc`, so size the capacity for the expected
+    /// number of writes rather than just the final tree.
     ///
     /// # Example
     /// ```
-    /// use mst::{MST, MSTKey};
+    /// use mst::MST;
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mst: MST<TestValue> = MST::with_capacity(1024);
+    /// assert!(mst.store.capacity() >= 1024);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_store(None, Store::with_capacity(capacity))
+    }
+
+    /// Creates a new MST with the specified root key and an empty store
+    ///
+    /// A root of None is the empty tree.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
     /// use mst::test_utils::TestValue;
     ///
-    /// let root_key = MSTKey::default();
-    /// let mst: MST<TestValue> = MST::with_root(root_key);
+    /// let mst: MST<TestValue> = MST::with_root(None);
     /// ```
-    pub fn with_root(root_key: MSTKey) -> Self {
+    pub fn with_root(root_key: Option<MSTKey>) -> Self {
         Self {
             root: root_key,
-            store: Store::new(),
+            ..Self::with_hasher()
         }
     }
 
@@ -75,890 +184,4298 @@ impl<
     ///
     /// # Arguments
     ///
-    /// * `root_key`: The hash key of the root node
+    /// * `root_key`: The hash key of the root node, or None for an empty tree
     /// * `store`: Pre-existing store of pages
     ///
     /// # Returns
     ///
     /// A new MST instance with the provided store
-    pub fn with_store(root_key: MSTKey, store: Store<MSTKey, Page<MSTKey, Value>>) -> Self {
+    pub fn with_store(root_key: Option<MSTKey>, store: TreeStore<Value>) -> Self {
+        Self::with_store_and_hasher(root_key, store)
+    }
+}
+
+impl<
+    Value: AsRef<[u8]>
+        + Hash
+        + Reference<Key = H::Key>
+        + Clone
+        + std::fmt::Debug
+        + Merge
+        + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+> MST<Value, H, S>
+{
+    /// Creates a new empty MST addressed by the hasher `H`
+    ///
+    /// `new` always uses SHA-256; this is the constructor for any other hasher.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
+    /// use sha2::Sha256;
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mst = MST::<TestValue, Sha256>::with_hasher();
+    /// ```
+    pub fn with_hasher() -> Self
+    where
+        S: Default,
+    {
         Self {
+            root: None,
+            store: S::default(),
+            len: 0,
+            level_base: DEFAULT_LEVEL_BASE,
+            level_mapper: identity_level,
+            merge_policy: MergePolicy::Merge,
+            observer: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Creates a new MST addressed by the hasher `H` with the provided store
+    ///
+    /// This is also the constructor for a tree over a custom `PageStore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `root_key`: The hash key of the root node, or None for an empty tree
+    /// * `store`: Pre-existing store of pages
+    pub fn with_store_and_hasher(root_key: Option<H::Key>, store: S) -> Self {
+        let mut mst = Self {
             root: root_key,
             store,
+            len: 0,
+            level_base: DEFAULT_LEVEL_BASE,
+            level_mapper: identity_level,
+            merge_policy: MergePolicy::Merge,
+            observer: None,
+            marker: PhantomData,
+        };
+        // An external store carries no counter, so count the entries once
+        mst.len = mst.iter().count();
+        mst
+    }
+
+    /// Builds a tree from pages received from an untrusted source
+    ///
+    /// Unlike `with_store_and_hasher`, nothing is taken on trust: each page is
+    /// stored only after checking that it hashes to the key it came with, and once
+    /// all pages are in, every page reachable from `root` has to be present and
+    /// laid out as `verify_integrity` requires. This is the safe way to ingest
+    /// pages from a sync peer.
+    ///
+    /// # Returns
+    ///
+    /// The tree, or the error for the first offending page. A single bad page
+    /// rejects the whole import.
+    pub fn import_validated(
+        root: Option<H::Key>,
+        pages: impl IntoIterator<Item = (H::Key, Page<H::Key, Value>)>,
+    ) -> Result<Self, IntegrityError<H::Key>>
+    where
+        S: Default,
+    {
+        let mut store = S::default();
+        for (key, page) in pages {
+            if hash_page_with::<H, _, _>(&page) != key {
+                return Err(IntegrityError::HashMismatch(key));
+            }
+            store.put(key, page);
         }
+
+        // Every page was hashed on the way in, so only presence and layout are
+        // left to check
+        Self::check_pages(&store, root, false)?;
+
+        Ok(Self::with_store_and_hasher(root, store))
     }
 
-    /// Retrieves a page from the store by its key.
+    /// Checks every page reachable from `root` in `store`
     ///
-    /// This is a low-level operation that provides direct access to the tree's pages.
-    /// Most users should use `get_value()` instead.
-    pub fn get(&self, page_key: MSTKey) -> Option<&Page<MSTKey, Value>> {
-        self.store.get(page_key)
+    /// Every page has to be present, with its entries in strictly ascending key
+    /// order, each child at a lower level than the page, and a `subtree_size`
+    /// equal to its own entries plus the sizes its children claim. With `rehash`,
+    /// every page also has to hash to its key, which is checked as soon as the
+    /// page is read so that a corrupted child is blamed before its size is used.
+    ///
+    /// # Returns
+    ///
+    /// The error for the first offending page in depth-first order:
+    /// `IntegrityError::MissingPage`, `IntegrityError::HashMismatch`,
+    /// `IntegrityError::Cycle` for a child that is not below its parent, or
+    /// `IntegrityError::Malformed`
+    fn check_pages(
+        store: &S,
+        root: Option<H::Key>,
+        rehash: bool,
+    ) -> Result<(), IntegrityError<H::Key>> {
+        let fetch = |key| {
+            let page = store.get(key).ok_or(IntegrityError::MissingPage(key))?;
+            if rehash && hash_page_with::<H, _, _>(page) != key {
+                return Err(IntegrityError::HashMismatch(key));
+            }
+            Ok(page)
+        };
+
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+        if let Some(root) = root {
+            stack.push((root, fetch(root)?));
+        }
+        while let Some((key, page)) = stack.pop() {
+            if !visited.insert(key) {
+                continue;
+            }
+            let sorted = page
+                .list
+                .windows(2)
+                .all(|pair| Value::compare_keys(&pair[0].key, &pair[1].key) == Ordering::Less);
+            if !sorted {
+                return Err(IntegrityError::Malformed(key));
+            }
+
+            let mut size = page.list.len() as u64;
+            let mut children = Vec::new();
+            for child in page
+                .low
+                .into_iter()
+                .chain(page.list.iter().filter_map(|entry| entry.next))
+            {
+                let child_page = fetch(child)?;
+                if child_page.level >= page.level {
+                    return Err(IntegrityError::Cycle(child));
+                }
+                size = size.saturating_add(child_page.subtree_size);
+                children.push((child, child_page));
+            }
+            if size != page.subtree_size {
+                return Err(IntegrityError::Malformed(key));
+            }
+
+            // Push in reverse so children are checked in key order
+            stack.extend(children.into_iter().rev());
+        }
+        Ok(())
     }
 
-    /// Converts the tree to a sorted list of values using MST-specific traversal.
+    /// Writes the tree to a single file at `path`, for `load_from_path`
+    ///
+    /// The file opens with a magic number and `LEVEL_FORMAT_VERSION`, followed by
+    /// the root, the level base and every page reachable from the root in its
+    /// `encode_page` form, in key order. Orphaned pages are left out. A level
+    /// mapper is not saved and has to be set again after loading.
+    pub fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let mut bytes = FILE_MAGIC.to_vec();
+        bytes.extend_from_slice(&LEVEL_FORMAT_VERSION.to_be_bytes());
+        match self.root {
+            Some(root) => {
+                bytes.push(1);
+                bytes.extend_from_slice(root.as_ref());
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&self.level_base.to_be_bytes());
+
+        let mut keys: Vec<H::Key> = self
+            .root
+            .map(|root| self.store.reachable_set(root).into_iter().collect())
+            .unwrap_or_default();
+        keys.sort();
+        bytes.extend_from_slice(&(keys.len() as u32).to_be_bytes());
+        for key in keys {
+            let Some(page) = self.store.get(key) else {
+                continue;
+            };
+            let encoded = encode_page(page);
+            bytes.extend_from_slice(key.as_ref());
+            bytes.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&encoded);
+        }
+
+        fs::write(path, bytes)
+    }
+
+    /// Reads a tree written by `save_to_path`
+    ///
+    /// Values are rebuilt with `decode_value` as in `decode_page`. The pages are
+    /// then checked like in `import_validated`, so a corrupted or incomplete
+    /// file is rejected rather than loaded. A file saved under another
+    /// `LEVEL_FORMAT_VERSION` is rejected too, since its pages were laid out and
+    /// hashed by different rules.
+    ///
+    /// # Returns
+    ///
+    /// The tree, with the root and level base it was saved with, or an error of
+    /// kind `InvalidData` if the file is not a saved tree, is from another level
+    /// format version, is malformed or a page fails the checks
+    pub fn load_from_path<F>(path: &Path, mut decode_value: F) -> io::Result<Self>
+    where
+        S: Default,
+        H::Key: AsMut<[u8]>,
+        F: FnMut(&H::Key, &[u8]) -> Option<Value>,
+    {
+        let invalid = |err: &dyn std::fmt::Display| {
+            io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+        };
+
+        let bytes = fs::read(path)?;
+        let mut reader = PageReader { bytes: &bytes };
+        if reader.take(FILE_MAGIC.len()).ok() != Some(FILE_MAGIC.as_slice()) {
+            return Err(invalid(&"not a saved tree"));
+        }
+        let version = reader.read_u32().map_err(|err| invalid(&err))?;
+        if version != LEVEL_FORMAT_VERSION {
+            return Err(invalid(&format!(
+                "level format version {} does not match {}",
+                version, LEVEL_FORMAT_VERSION
+            )));
+        }
+        let root = reader.read_optional_key().map_err(|err| invalid(&err))?;
+        let level_base = reader.read_u32().map_err(|err| invalid(&err))?;
+        if level_base < 2 || !level_base.is_power_of_two() {
+            return Err(invalid(&format!("invalid level base {}", level_base)));
+        }
+
+        let count = reader.read_u32().map_err(|err| invalid(&err))?;
+        let mut pages = Vec::new();
+        for _ in 0..count {
+            let key: H::Key = reader.read_key().map_err(|err| invalid(&err))?;
+            let length = reader.read_u32().map_err(|err| invalid(&err))? as usize;
+            let encoded = reader.take(length).map_err(|err| invalid(&err))?;
+            let page = decode_page(encoded, &mut decode_value).map_err(|err| invalid(&err))?;
+            pages.push((key, page));
+        }
+        if !reader.bytes.is_empty() {
+            return Err(invalid(&MstError::InvalidPageEncoding));
+        }
+
+        let mut tree = Self::import_validated(root, pages).map_err(|err| invalid(&err))?;
+        tree.level_base = level_base;
+        Ok(tree)
+    }
+
+    /// Sets the base of the levels keys are placed at, `DEFAULT_LEVEL_BASE` unless set
+    ///
+    /// Levels are computed with `calc_level_base`, so pages hold `base` entries on
+    /// average and higher bases give shallower, wider trees. The base is part of
+    /// the tree's shape, and so of its root hash: it has to be set before anything
+    /// is inserted, and to the base the pages were built with when reopening a
+    /// stored tree. Trees that are merged or joined must use the same base.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base` is not a power of two of at least 2
     ///
     /// # Example
     /// ```
-    /// use mst::{MST, MSTKey};
+    /// use mst::MST;
     /// use mst::test_utils::TestValue;
     ///
-    /// let mut mst: MST<TestValue> = MST::new();
-    /// let values = mst.to_list();
+    /// let mst: MST<TestValue> = MST::new().with_level_base(16);
+    /// assert_eq!(mst.level_base(), 16);
     /// ```
-    pub fn to_list(&self) -> Vec<Value> {
-        // Return empty vector if the tree is empty
-        if self.root == MSTKey::default() {
-            return Vec::new();
-        }
-
-        let mut result_values = Vec::new();
+    pub fn with_level_base(mut self, base: u32) -> Self {
+        assert!(
+            base >= 2 && base.is_power_of_two(),
+            "level base must be a power of two of at least 2, got {}",
+            base
+        );
+        self.level_base = base;
+        self
+    }
 
-        let visitor = |event: TraversalEvent<MSTKey, Value>| {
-            if let TraversalEvent::VisitEntry(_, entry) = event {
-                result_values.push(entry.value);
-            }
-            TraversalControl::Continue
-        };
+    /// Returns the base of the levels keys are placed at
+    pub fn level_base(&self) -> u32 {
+        self.level_base
+    }
 
-        // Use MST-specific traversal order
-        self.traverse_tree(TraversalStrategy::MSTOrder, visitor);
-        result_values
+    /// Sets the function mapping each key's level to the level it is placed at,
+    /// the identity unless set
+    ///
+    /// The mapper receives the level `calc_level_base` computes with the tree's
+    /// base and returns the level actually used, e.g. `|level| level / 2` to
+    /// halve the height of the tree and make its pages correspondingly wider,
+    /// without changing the hash function. Like the base, it is part of the
+    /// tree's shape: two trees built with different mappers do not share root
+    /// hashes for the same contents, and it has to be set before anything is
+    /// inserted and to the same mapper on trees that are merged or joined.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
+    /// use mst::test_utils::{TestValue, create_key};
+    ///
+    /// let mut mst: MST<TestValue> = MST::new().with_level_mapper(|level| level / 2);
+    /// for byte in 0..100u8 {
+    ///     let key = create_key(&[byte]);
+    ///     mst.insert(key, TestValue { key, data: [byte; 4] });
+    /// }
+    /// assert_eq!(mst.len(), 100);
+    /// ```
+    pub fn with_level_mapper(mut self, mapper: LevelMapper) -> Self {
+        self.level_mapper = mapper;
+        self
     }
 
-    /// Inserts a new key-value pair into the tree.
+    /// Sets how a value meets one already stored under its key, `MergePolicy::Merge`
+    /// unless set
     ///
-    /// The insertion process maintains the tree's ordered structure and balance.
-    /// If the key already exists, the values will be merged using the `Merge` trait.
+    /// With `MergePolicy::Replace` the new value is kept as-is and `Merge` is never
+    /// called, so one value type can be stored with either behaviour. The policy
+    /// applies to every insert, to `insert_batch`, and to `merge`, `merge_all`,
+    /// `union` and `intersection`, where the value of the other tree counts as
+    /// the new one. Explicit resolvers, as in `merge_with`, are not affected.
     ///
     /// # Example
     /// ```
-    /// use mst::{MST, MSTKey};
+    /// use mst::{MST, MSTKey, MergePolicy};
     /// use mst::test_utils::TestValue;
     ///
-    /// let mut mst: MST<TestValue> = MST::new();
+    /// let mut mst: MST<TestValue> = MST::new().with_merge_policy(MergePolicy::Replace);
     /// let key = MSTKey::default();
-    /// let value = TestValue { key, data: [0; 4] };
-    /// mst.insert(key, value);
+    /// mst.insert(key, TestValue { key, data: [1; 4] });
+    /// mst.insert(key, TestValue { key, data: [2; 4] });
+    /// assert_eq!(mst.get_value(key).unwrap().data, [2; 4]);
     /// ```
-    pub fn insert(&mut self, item_key: MSTKey, item_value: Value) -> MSTKey {
-        let level = calc_level(&item_key);
-        let (new_root, _) = self.insert_at(self.root, item_key, item_value, level);
-        self.root = new_root;
-        self.root
+    pub fn with_merge_policy(mut self, policy: MergePolicy) -> Self {
+        self.merge_policy = policy;
+        self
     }
 
-    /// Helper function that recursively inserts an item at the specified level.
-    /// Returns the new root key of the affected subtree and any modified store.
-    fn insert_at(
-        &mut self,
-        current_root: MSTKey,
-        item_key: MSTKey,
-        item_value: Value,
-        level: u32,
-    ) -> (MSTKey, bool) {
-        // Handle empty tree or non-existent root
-        if current_root == MSTKey::default() || !self.store.has(current_root) {
-            let new_page = Page {
-                level,
-                low: None,
-                list: vec![PageData {
-                    key: item_key,
-                    value: item_value,
-                    next: None,
-                }],
-            };
-            let new_key = hash_page(&new_page);
-            self.store.put(new_key, new_page);
-            return (new_key, true);
-        }
+    /// Returns the tree's `MergePolicy`
+    pub fn merge_policy(&self) -> MergePolicy {
+        self.merge_policy
+    }
 
-        // Get the current page
-        let current_page = self.store.get(current_root).cloned().unwrap();
+    /// The level `key` is placed at in this tree
+    fn level_of(&self, key: H::Key) -> u32 {
+        (self.level_mapper)(calc_level_base_with::<H, _>(key, self.level_base))
+    }
 
-        // Case 1: Current level is less than item level
-        if current_page.level < level {
-            // We need to create a new node at a higher level
-            // First split the tree at our insertion point
-            let (low_key, high_key) = self.split(Some(current_root), item_key);
+    /// Registers a callback receiving the structural changes the tree makes
+    ///
+    /// Every page hashed into the store is reported as `MstEvent::PageCreated`
+    /// and every split of a subtree as `MstEvent::Split`, whichever operation
+    /// caused them; merges report the pages of the merged store. `insert` and
+    /// `remove` also report each page they leave unreachable from the root as
+    /// `MstEvent::PageOrphaned`, which with the created pages gives their write
+    /// amplification. Events arrive as the changes happen, so a failed insert may
+    /// have reported pages it then abandoned.
+    ///
+    /// Without an observer nothing is recorded. The callback is `Send + Sync`
+    /// so the tree stays shareable between threads. It replaces any previous
+    /// observer, and is not carried over by `clone` or to the trees `split_at`
+    /// returns.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MstEvent};
+    /// use mst::test_utils::{TestValue, create_key};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let created = Arc::new(Mutex::new(0));
+    /// let counter = Arc::clone(&created);
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// mst.set_observer(Box::new(move |event| {
+    ///     if let MstEvent::PageCreated { .. } = event {
+    ///         *counter.lock().unwrap() += 1;
+    ///     }
+    /// }));
+    ///
+    /// let key = create_key(b"key");
+    /// mst.insert(key, TestValue { key, data: [1, 0, 0, 0] });
+    /// assert_eq!(*created.lock().unwrap(), 1);
+    /// ```
+    pub fn set_observer(&mut self, callback: Box<dyn FnMut(MstEvent<H::Key>) + Send + Sync>) {
+        self.observer = Some(Observer {
+            callback,
+            created: None,
+        });
+    }
 
-            // Create a new page with our item between the split parts
-            let new_page = Page {
-                level,
-                low: low_key,
-                list: vec![PageData {
-                    key: item_key,
-                    value: item_value,
-                    next: high_key,
-                }],
-            };
-            let new_key = hash_page(&new_page);
-            self.store.put(new_key, new_page);
-            return (new_key, true);
+    /// Removes the observer registered with `set_observer`, if any
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    /// Passes an event to the observer, if there is one
+    fn notify(&mut self, event: MstEvent<H::Key>) {
+        if let Some(observer) = &mut self.observer {
+            (observer.callback)(event);
         }
-        // Case 2: Current level is equal to item level
-        else if current_page.level == level {
-            let mut new_page = Page {
-                level: current_page.level,
-                low: current_page.low,
-                list: Vec::with_capacity(current_page.list.len() + 1),
-            };
+    }
 
-            // Handle empty list case
-            if current_page.list.is_empty() {
-                new_page.list.push(PageData {
-                    key: item_key,
-                    value: item_value,
-                    next: None,
-                });
-            } else {
-                let first_key = current_page.list[0].key;
+    /// Starts recording the pages created, for `report_orphans` to tell which
+    /// pages the operation replaced
+    fn track_created(&mut self) {
+        if let Some(observer) = &mut self.observer {
+            observer.created = Some(HashSet::new());
+        }
+    }
 
-                if Value::compare_keys(&item_key, &first_key) == Ordering::Less {
-                    // Item belongs before the first element
-                    let (low2a, low2b) = self.split(current_page.low, item_key);
+    /// Reports the pages reachable from `old_root` but no longer from the root
+    /// as orphaned, and stops recording created pages
+    ///
+    /// Pages the new tree shares with the old one hang below the pages created
+    /// since `track_created`, so only those are walked to find what is kept, and
+    /// the old tree is walked down to the kept pages.
+    fn report_orphans(&mut self, old_root: Option<H::Key>) {
+        let Some(created) = self
+            .observer
+            .as_mut()
+            .and_then(|observer| observer.created.take())
+        else {
+            return;
+        };
 
-                    // Create new list starting with our item
-                    new_page.list.push(PageData {
-                        key: item_key,
-                        value: item_value,
-                        next: low2b,
-                    });
+        let children = |page: &Page<H::Key, Value>| {
+            std::iter::once(page.low)
+                .chain(page.list.iter().map(|entry| entry.next))
+                .flatten()
+                .collect::<Vec<_>>()
+        };
 
-                    // Add the rest of the original list
-                    new_page.list.extend(current_page.list);
-                    new_page.low = low2a;
-                } else {
-                    // Item belongs after the first element
-                    new_page.list =
-                        self.insert_after_first(&current_page.list, item_key, item_value);
-                }
+        let mut kept = HashSet::new();
+        let mut stack = Vec::from_iter(self.root);
+        while let Some(key) = stack.pop() {
+            if !created.contains(&key) {
+                kept.insert(key);
+            } else if let Some(page) = self.store.get(key) {
+                stack.extend(children(page));
             }
-
-            let new_key = hash_page(&new_page);
-            self.store.put(new_key, new_page);
-            return (new_key, true);
         }
-        // Case 3: Current level is greater than item level
-        else {
-            if current_page.list.is_empty() {
-                // No items in this node, insert into low child
-                let low_key = current_page.low;
-                let (new_low_key, low_modified) = match low_key {
-                    Some(key) => self.insert_at(key, item_key, item_value, level),
-                    None => {
-                        // Create a new page for the item
-                        let new_page = Page {
-                            level,
-                            low: None,
-                            list: vec![PageData {
-                                key: item_key,
-                                value: item_value,
-                                next: None,
-                            }],
-                        };
-                        let new_key = hash_page(&new_page);
-                        self.store.put(new_key, new_page);
-                        (new_key, true)
-                    }
-                };
 
-                // Only create a new page if the child was modified
-                if low_modified {
-                    let mut new_page = current_page.clone();
-                    new_page.low = Some(new_low_key);
-                    let new_key = hash_page(&new_page);
-                    self.store.put(new_key, new_page);
-                    return (new_key, true);
-                } else {
-                    return (current_root, false);
-                }
+        let mut orphaned = Vec::new();
+        let mut stack = Vec::from_iter(old_root);
+        while let Some(key) = stack.pop() {
+            if kept.contains(&key) || created.contains(&key) {
+                continue;
             }
+            orphaned.push(key);
+            if let Some(page) = self.store.get(key) {
+                stack.extend(children(page));
+            }
+        }
+        for key in orphaned {
+            self.notify(MstEvent::PageOrphaned(key));
+        }
+    }
 
-            let first_key = current_page.list[0].key;
-
-            if Value::compare_keys(&item_key, &first_key) == Ordering::Less {
-                // Key is less than first entry - go to low child
-                let low_key = current_page.low;
-                let (new_low_key, low_modified) = match low_key {
-                    Some(key) => self.insert_at(key, item_key, item_value, level),
-                    None => {
-                        // Create a new page for the item
-                        let new_page = Page {
-                            level,
-                            low: None,
-                            list: vec![PageData {
-                                key: item_key,
-                                value: item_value,
-                                next: None,
-                            }],
-                        };
-                        let new_key = hash_page(&new_page);
-                        self.store.put(new_key, new_page);
-                        (new_key, true)
-                    }
-                };
+    /// An empty tree in a fresh store that places keys like this one
+    fn empty_like(&self) -> Self
+    where
+        S: Default,
+    {
+        Self {
+            level_base: self.level_base,
+            level_mapper: self.level_mapper,
+            merge_policy: self.merge_policy,
+            ..Self::with_hasher()
+        }
+    }
 
-                // Only create a new page if the child was modified
-                if low_modified {
+    /// Builds a tree from items already sorted by `Value::compare_keys`
+    ///
+    /// Instead of inserting one item at a time, the pages are built directly: the
+    /// items at the highest level form the root page, and the runs of items between
+    /// them are built the same way into its children. The result is the same
+    /// canonical tree, with the same root, that inserting the items one by one
+    /// would produce. Consecutive items with equal keys are merged as `insert` would.
+    /// The tree uses `DEFAULT_LEVEL_BASE`.
+    ///
+    /// # Returns
+    ///
+    /// The tree, or `MstError::UnsortedInput` if an item's key is smaller than the
+    /// one before it
+    pub fn from_sorted(items: impl IntoIterator<Item = (H::Key, Value)>) -> Result<Self, MstError>
+    where
+        S: Default,
+    {
+        let mut mst = Self::with_hasher();
+        let mut entries: Vec<(u32, PageData<H::Key, Value>)> = Vec::new();
+        for (key, value) in items {
+            if let Some((_, last)) = entries.last_mut() {
+                match Value::compare_keys(&last.key, &key) {
+                    Ordering::Less => {}
+                    Ordering::Equal => {
+                        last.value = last.value.clone().merge(value);
+                        continue;
+                    }
+                    Ordering::Greater => return Err(MstError::UnsortedInput),
+                }
+            }
+            let level = mst.level_of(key);
+            entries.push((
+                level,
+                PageData {
+                    key,
+                    value,
+                    next: None,
+                },
+            ));
+        }
+
+        mst.len = entries.len();
+        mst.root = mst.build_sorted(&entries);
+        Ok(mst)
+    }
+
+    /// Builds a tree from sorted items like `from_sorted`, using `threads` threads
+    ///
+    /// The items are split into `threads` contiguous chunks of roughly equal size.
+    /// Each chunk is built into its own tree on a separate thread, and the trees are
+    /// then joined along their boundaries with `join`. The result is the same
+    /// canonical tree, with the same root, as `from_sorted` builds. `threads` is
+    /// the only tuning knob; 0 is treated as 1, and `std::thread::available_parallelism`
+    /// is a good default.
+    ///
+    /// # Returns
+    ///
+    /// The tree, or `MstError::UnsortedInput` if an item's key is smaller than the
+    /// one before it
+    #[cfg(feature = "parallel")]
+    pub fn from_sorted_parallel(
+        items: impl IntoIterator<Item = (H::Key, Value)>,
+        threads: usize,
+    ) -> Result<Self, MstError>
+    where
+        S: Default + Send,
+        Value: Send + Sync,
+        H::Key: Send + Sync,
+    {
+        // Merge duplicates first, so no key is split across two chunks
+        let mut sorted: Vec<(H::Key, Value)> = Vec::new();
+        for (key, value) in items {
+            if let Some((last_key, last_value)) = sorted.last_mut() {
+                match Value::compare_keys(last_key, &key) {
+                    Ordering::Less => {}
+                    Ordering::Equal => {
+                        *last_value = last_value.clone().merge(value);
+                        continue;
+                    }
+                    Ordering::Greater => return Err(MstError::UnsortedInput),
+                }
+            }
+            sorted.push((key, value));
+        }
+
+        let chunk_size = sorted.len().div_ceil(threads.max(1)).max(1);
+        let chunks: Vec<Result<Self, MstError>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = sorted
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(|| Self::from_sorted(chunk.iter().cloned())))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("chunk build panicked"))
+                .collect()
+        });
+
+        let mut tree = Self::with_hasher();
+        for chunk in chunks {
+            tree = Self::join(tree, chunk?)?;
+        }
+        Ok(tree)
+    }
+
+    /// Builds the subtree holding a sorted run of `(level, entry)` pairs
+    ///
+    /// # Returns
+    /// The key of the subtree's root page, or None if the run is empty
+    fn build_sorted(&mut self, entries: &[(u32, PageData<H::Key, Value>)]) -> Option<H::Key> {
+        let level = entries.iter().map(|(level, _)| *level).max()?;
+
+        // Entries at the top level go in this page, the runs between them below it
+        let mut list: Vec<PageData<H::Key, Value>> = Vec::new();
+        let mut low = None;
+        let mut run_start = 0;
+        for (index, (entry_level, entry)) in entries.iter().enumerate() {
+            if *entry_level != level {
+                continue;
+            }
+            let child = self.build_sorted(&entries[run_start..index]);
+            match list.last_mut() {
+                Some(last) => last.next = child,
+                None => low = child,
+            }
+            list.push(entry.clone());
+            run_start = index + 1;
+        }
+        let child = self.build_sorted(&entries[run_start..]);
+        if let Some(last) = list.last_mut() {
+            last.next = child;
+        }
+
+        Some(self.create_and_store_page(level, low, list))
+    }
+
+    /// Returns the number of entries in the tree
+    ///
+    /// The count is maintained by `insert` and `remove`, so this is O(1).
+    /// Editing `store` directly bypasses the counter.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the tree holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Empties the tree, removing every page from the store
+    ///
+    /// Unlike constructing a new tree, this keeps the store, and with `Store` its
+    /// allocated capacity, so a tree reused across the iterations of a loop does
+    /// not grow its map from scratch each time. The level base is kept too.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.store.clear();
+        self.len = 0;
+    }
+
+    /// Retrieves a page from the store by its key.
+    ///
+    /// This is a low-level operation that provides direct access to the tree's pages.
+    /// Most users should use `get_value()` instead.
+    pub fn get(&self, page_key: H::Key) -> Option<&Page<H::Key, Value>> {
+        self.store.get(page_key)
+    }
+
+    /// Returns the root page, or None for an empty tree or a root missing from
+    /// the store
+    pub fn root_page(&self) -> Option<&Page<H::Key, Value>> {
+        self.store.get(self.root?)
+    }
+
+    /// Converts the tree to a sorted list of values using MST-specific traversal.
+    ///
+    /// Tombstones (see `Merge::is_tombstone`) are left out.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let values = mst.to_list();
+    /// ```
+    pub fn to_list(&self) -> Vec<Value> {
+        self.iter()
+            .filter(|entry| !entry.value.is_tombstone())
+            .map(|entry| entry.value.clone())
+            .collect()
+    }
+
+    /// Builds a Bloom filter of the keys in the tree, in a single walk over it
+    ///
+    /// A client holding the filter can rule out absent keys without a lookup or a
+    /// network round trip; see `BloomFilter` for choosing `bits` and `hashes`
+    /// against the false-positive rate. Tombstoned keys are left out, like in
+    /// `get_value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` or `hashes` is zero.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
+    /// use mst::test_utils::{TestValue, create_key};
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = create_key(b"present");
+    /// mst.insert(key, TestValue { key, data: [1, 0, 0, 0] });
+    ///
+    /// let filter = mst.bloom_summary(1024, 7);
+    /// assert!(filter.maybe_contains(key));
+    /// ```
+    pub fn bloom_summary(&self, bits: usize, hashes: u8) -> BloomFilter {
+        let mut filter = BloomFilter::new(bits, hashes);
+        for entry in self.iter() {
+            if !entry.value.is_tombstone() {
+                filter.insert(entry.key);
+            }
+        }
+        filter
+    }
+
+    /// Returns a lazy iterator over the entries of the tree in ascending key order.
+    ///
+    /// Pages are only looked up as the iterator advances, so taking a prefix
+    /// of a large tree does not walk the rest of it.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mst: MST<TestValue> = MST::new();
+    /// let first_ten: Vec<_> = mst.iter().take(10).collect();
+    /// ```
+    pub fn iter(&self) -> Iter<'_, Value, H, S> {
+        Iter::new(&self.store, self.root, false)
+    }
+
+    /// Returns a lazy iterator over the entries of the tree in descending key order.
+    ///
+    /// Yields exactly the reverse sequence of `iter()`, which makes "largest N"
+    /// queries cheap when keys encode time.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mst: MST<TestValue> = MST::new();
+    /// let last_ten: Vec<_> = mst.iter_rev().take(10).collect();
+    /// ```
+    pub fn iter_rev(&self) -> Iter<'_, Value, H, S> {
+        Iter::new(&self.store, self.root, true)
+    }
+
+    /// Returns a lazy iterator over the keys of the tree in ascending order
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mst: MST<TestValue> = MST::new();
+    /// let first_ten: Vec<_> = mst.keys().take(10).collect();
+    /// ```
+    pub fn keys(&self) -> impl Iterator<Item = H::Key> + '_ {
+        self.iter().map(|entry| entry.key)
+    }
+
+    /// Returns a lazy iterator over the values of the tree in ascending key order
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|entry| &entry.value)
+    }
+
+    /// Returns a lazy iterator over the keys of the tree paired with their values,
+    /// in ascending key order
+    ///
+    /// The key is the one the entry is stored under, not one derived from the
+    /// value, so this also serves values whose own key differs from the tree's.
+    pub fn iter_pairs(&self) -> impl Iterator<Item = (H::Key, &Value)> {
+        self.iter().map(|entry| (entry.key, &entry.value))
+    }
+
+    /// Returns a lazy iterator over the entries with `start <= key < end` in ascending order.
+    ///
+    /// Either bound may be omitted to leave that side open. The iterator descends
+    /// directly to the first key `>= start` instead of walking from the smallest key,
+    /// so a narrow range over a large tree only touches the pages along its path.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mst: MST<TestValue> = MST::new();
+    /// let start = MSTKey::default();
+    /// let entries: Vec<_> = mst.range(Some(start), None).collect();
+    /// ```
+    pub fn range(
+        &self,
+        start: Option<H::Key>,
+        end: Option<H::Key>,
+    ) -> impl Iterator<Item = &PageData<H::Key, Value>> {
+        let iter = match start {
+            Some(start) => Iter::<Value, H, S>::starting_at(&self.store, self.root, start),
+            None => Iter::new(&self.store, self.root, false),
+        };
+
+        iter.take_while(move |entry| match end {
+            Some(end) => Value::compare_keys(&entry.key, &end) == Ordering::Less,
+            None => true,
+        })
+    }
+
+    /// Returns a lazy iterator over the entries whose raw key starts with `prefix`,
+    /// in ascending order
+    ///
+    /// Assumes the tree is keyed by `raw_key`, which sorts keys sharing a prefix
+    /// next to each other. The iterator descends directly to the first key `>=`
+    /// the prefix and stops at the first key without it. An empty prefix matches
+    /// every raw key.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, raw_key};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// for name in [&b"alice"[..], b"bob", b"alicia"] {
+    ///     let key = raw_key(name).unwrap();
+    ///     mst.insert(key, TestValue { key, data: [0; 4] });
+    /// }
+    /// assert_eq!(mst.scan_prefix(b"ali").count(), 2);
+    /// ```
+    pub fn scan_prefix(&self, prefix: &[u8]) -> impl Iterator<Item = &PageData<H::Key, Value>>
+    where
+        H: MstHasher<Key = MSTKey>,
+    {
+        // The smallest raw key with the prefix is the prefix itself. A prefix too
+        // long for a raw key matches nothing, which the check below handles.
+        self.range(raw_key(prefix), None).take_while(move |entry| {
+            raw_key_bytes(&entry.key).is_some_and(|bytes| bytes.starts_with(prefix))
+        })
+    }
+
+    /// Inserts a new key-value pair into the tree.
+    ///
+    /// The insertion process maintains the tree's ordered structure and balance.
+    /// If the key already exists, the values will be merged using the `Merge` trait,
+    /// as `existing.merge(new)`: the stored value is the receiver and the inserted
+    /// one the argument. `insert_merging_existing_into_new` merges the other way round.
+    /// Under `MergePolicy::Replace` the inserted value replaces the stored one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Value::compare_keys` is not a consistent total order.
+    /// Use `try_insert` to handle that case as an error instead.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = MSTKey::default();
+    /// let value = TestValue { key, data: [0; 4] };
+    /// mst.insert(key, value);
+    /// ```
+    pub fn insert(&mut self, item_key: H::Key, item_value: Value) -> H::Key {
+        match self.try_insert(item_key, item_value) {
+            Ok(root) => root,
+            Err(err) => panic!("insert failed: {}", err),
+        }
+    }
+
+    /// Inserts a key-value pair like `insert`, but merges an existing value into
+    /// the new one, as `new.merge(existing)`
+    ///
+    /// This matters when `Merge` is not commutative, e.g. when it prefers one of
+    /// its two sides. Under `MergePolicy::Replace` the new value is kept, as by
+    /// `insert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Value::compare_keys` is not a consistent total order, like `insert`.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// // TestValue's merge keeps its argument
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = MSTKey::default();
+    /// mst.insert(key, TestValue { key, data: [1; 4] });
+    /// mst.insert_merging_existing_into_new(key, TestValue { key, data: [2; 4] });
+    /// assert_eq!(mst.get_value(key).unwrap().data, [1; 4]);
+    /// ```
+    pub fn insert_merging_existing_into_new(
+        &mut self,
+        item_key: H::Key,
+        item_value: Value,
+    ) -> H::Key {
+        // Replace keeps the incoming value whichever way round a merge would go
+        let policy = self.merge_policy;
+        match self.insert_resolving(
+            item_key,
+            item_value,
+            &|old: Value, new: Value| match policy {
+                MergePolicy::Merge => new.merge(old),
+                MergePolicy::Replace => new,
+            },
+        ) {
+            Ok((root, _)) => root,
+            Err(err) => panic!("insert failed: {}", err),
+        }
+    }
+
+    /// Inserts a new key-value pair into the tree, reporting ordering errors
+    ///
+    /// Behaves like `insert`, but returns `MstError::InconsistentOrdering` instead of
+    /// panicking when `Value::compare_keys` turns out not to be a total order. On error
+    /// the root is left unchanged.
+    ///
+    /// # Returns
+    ///
+    /// The new root key on success
+    pub fn try_insert(&mut self, item_key: H::Key, item_value: Value) -> Result<H::Key, MstError> {
+        self.insert_entry(item_key, item_value)
+            .map(|(root, _)| root)
+    }
+
+    /// Inserts a key-value pair, also returning the value previously stored under
+    /// the key
+    ///
+    /// The tree is updated exactly as by `insert`, so an existing value is merged
+    /// with the new one. The returned value is the one from before that merge.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Value::compare_keys` is not a consistent total order, like `insert`.
+    ///
+    /// # Returns
+    ///
+    /// The new root key, and the previous value or None if the key is new
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = MSTKey::default();
+    /// let value = TestValue { key, data: [0; 4] };
+    /// assert_eq!(mst.insert_with_old(key, value).1, None);
+    /// assert_eq!(mst.insert_with_old(key, value).1, Some(value));
+    /// ```
+    pub fn insert_with_old(
+        &mut self,
+        item_key: H::Key,
+        item_value: Value,
+    ) -> (H::Key, Option<Value>) {
+        match self.insert_entry(item_key, item_value) {
+            Ok(inserted) => inserted,
+            Err(err) => panic!("insert failed: {}", err),
+        }
+    }
+
+    /// Inserts a key-value pair like `insert`, reporting whether the root changed
+    ///
+    /// An insert whose value merges to the bytes already stored leaves the root as
+    /// it was, so replication can skip propagating it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Value::compare_keys` is not a consistent total order, like `insert`.
+    ///
+    /// # Returns
+    ///
+    /// The new root key, or None if the insert left the root unchanged
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = MSTKey::default();
+    /// let value = TestValue { key, data: [0; 4] };
+    /// assert!(mst.insert_if_changed(key, value).is_some());
+    /// assert_eq!(mst.insert_if_changed(key, value), None);
+    /// ```
+    pub fn insert_if_changed(&mut self, item_key: H::Key, item_value: Value) -> Option<H::Key> {
+        let before = self.root;
+        let root = self.insert(item_key, item_value);
+        (Some(root) != before).then_some(root)
+    }
+
+    /// Returns the root an insert would produce, without changing this tree
+    ///
+    /// Runs the same insert on a scratch layer over the store, so no page reaches
+    /// the real store and `self.root` is unchanged. If the result equals `self.root`,
+    /// the insert would be a no-op, e.g. because the value merges to the same bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Value::compare_keys` is not a consistent total order, like `insert`.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = MSTKey::default();
+    /// let value = TestValue { key, data: [0; 4] };
+    /// let preview = mst.preview_insert(key, value);
+    /// assert_eq!(mst.insert(key, value), preview);
+    /// assert_eq!(Some(mst.preview_insert(key, value)), mst.root);
+    /// ```
+    pub fn preview_insert(&self, item_key: H::Key, item_value: Value) -> H::Key {
+        let mut scratch = MST::<Value, H, ScratchStore<'_, H::Key, Page<H::Key, Value>, S>> {
+            root: self.root,
+            store: ScratchStore::new(&self.store),
+            len: self.len,
+            level_base: self.level_base,
+            level_mapper: self.level_mapper,
+            merge_policy: self.merge_policy,
+            observer: None,
+            marker: PhantomData,
+        };
+        scratch.insert(item_key, item_value)
+    }
+
+    /// Inserts a batch of key-value pairs, hashing each affected page only once
+    ///
+    /// Inserting the items one at a time rebuilds and rehashes the path to every
+    /// key, so pages shared by many of those paths are hashed over and over. Here
+    /// the items are sorted and built into a tree of their own with `from_sorted`'s
+    /// construction, which is then merged into this one structurally, as by `merge`.
+    /// Only the pages where the two trees interleave are rebuilt. The result is the
+    /// same canonical tree as inserting the items in order; values with equal keys
+    /// are combined with `Merge` in that order, which assumes `Merge` is associative.
+    ///
+    /// # Returns
+    ///
+    /// The new root key, None if both the tree and the batch are empty
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let items: Vec<_> = [3u8, 1, 2]
+    ///     .into_iter()
+    ///     .map(|byte| {
+    ///         let key = MSTKey::clone_from_slice(&[byte; 32]);
+    ///         (key, TestValue { key, data: [byte; 4] })
+    ///     })
+    ///     .collect();
+    ///
+    /// let mut batched: MST<TestValue> = MST::new();
+    /// batched.insert_batch(items.clone());
+    /// let mut sequential: MST<TestValue> = MST::new();
+    /// for (key, value) in items {
+    ///     sequential.insert(key, value);
+    /// }
+    /// assert_eq!(batched.root, sequential.root);
+    /// ```
+    pub fn insert_batch(&mut self, mut items: Vec<(H::Key, Value)>) -> Option<H::Key> {
+        // A stable sort keeps equal keys in insertion order for merging
+        items.sort_by(|a, b| Value::compare_keys(&a.0, &b.0));
+
+        let mut entries: Vec<(u32, PageData<H::Key, Value>)> = Vec::with_capacity(items.len());
+        for (key, value) in items {
+            if let Some((_, last)) = entries.last_mut()
+                && Value::compare_keys(&last.key, &key) == Ordering::Equal
+            {
+                last.value = self.merge_policy.resolve(last.value.clone(), value);
+                continue;
+            }
+            let level = self.level_of(key);
+            entries.push((
+                level,
+                PageData {
+                    key,
+                    value,
+                    next: None,
+                },
+            ));
+        }
+
+        let batch = self.build_sorted(&entries);
+        let policy = self.merge_policy;
+        self.root = self.merge_subtrees(self.root, batch, &|old: &Value, new: &Value| {
+            policy.resolve(old.clone(), new.clone())
+        });
+        self.len = self.subtree_size(self.root) as usize;
+        self.root
+    }
+
+    /// Looks up `key` once, for reading and updating its value in place
+    ///
+    /// The returned `Entry` holds the current value, if any, so deciding what to
+    /// write does not walk the tree again. Writing goes through the usual insert
+    /// path, so the pages on the way to the key are rebuilt and rehashed once.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = MSTKey::default();
+    /// let count = |data| TestValue { key, data };
+    ///
+    /// mst.entry(key).or_insert(count([1, 0, 0, 0]));
+    /// mst.entry(key)
+    ///     .and_modify(|value| value.data[0] += 1)
+    ///     .or_insert(count([1, 0, 0, 0]));
+    /// assert_eq!(mst.get_value(key), Some(count([2, 0, 0, 0])));
+    /// ```
+    pub fn entry(&mut self, key: H::Key) -> Entry<'_, Value, H, S> {
+        let value = self.get_value(key);
+        Entry {
+            tree: self,
+            key,
+            value,
+        }
+    }
+
+    /// Shared implementation of the insert methods, returning the new root and the
+    /// value the key held before, if any
+    fn insert_entry(
+        &mut self,
+        item_key: H::Key,
+        item_value: Value,
+    ) -> Result<(H::Key, Option<Value>), MstError> {
+        let policy = self.merge_policy;
+        self.insert_resolving(item_key, item_value, &|old: Value, new| {
+            policy.resolve(old, new)
+        })
+    }
+
+    /// Like `insert_entry`, but an existing value `old` becomes `resolve(old, new)`
+    /// instead of being merged with the new one
+    fn insert_resolving<F>(
+        &mut self,
+        item_key: H::Key,
+        item_value: Value,
+        resolve: &F,
+    ) -> Result<(H::Key, Option<Value>), MstError>
+    where
+        F: Fn(Value, Value) -> Value,
+    {
+        let level = self.level_of(item_key);
+        let mut previous = None;
+        let old_root = self.root;
+        self.track_created();
+        let inserted = self.insert_at(
+            self.root,
+            item_key,
+            item_value,
+            level,
+            &mut previous,
+            resolve,
+        );
+        let (new_root, _) = match inserted {
+            Ok(inserted) => inserted,
+            Err(err) => {
+                self.report_orphans(old_root);
+                return Err(err);
+            }
+        };
+        self.root = Some(new_root);
+        self.report_orphans(old_root);
+
+        // Only a genuinely new key grows the tree; a merge replaces an entry
+        if previous.is_none() {
+            self.len += 1;
+        }
+        Ok((new_root, previous))
+    }
+
+    /// Helper function that recursively inserts an item at the specified level.
+    /// An absent subtree (None) becomes a new page holding just the item.
+    /// Returns the new root key of the affected subtree and any modified store.
+    /// If the key already existed, `previous` receives its old value, which is
+    /// replaced by `resolve(old, item_value)`.
+    /// Fails with `MstError::InconsistentOrdering` if `compare_keys` contradicts itself.
+    fn insert_at<F>(
+        &mut self,
+        current_root: Option<H::Key>,
+        item_key: H::Key,
+        item_value: Value,
+        level: u32,
+        previous: &mut Option<Value>,
+        resolve: &F,
+    ) -> Result<(H::Key, bool), MstError>
+    where
+        F: Fn(Value, Value) -> Value,
+    {
+        // Handle empty tree or non-existent root
+        let Some(current_root) = current_root.filter(|root| self.store.has(*root)) else {
+            let new_key = self.create_and_store_page(
+                level,
+                None,
+                vec![PageData {
+                    key: item_key,
+                    value: item_value,
+                    next: None,
+                }],
+            );
+            return Ok((new_key, true));
+        };
+
+        // Get the current page
+        let current_page = self.store.get(current_root).cloned().unwrap();
+
+        // Case 1: Current level is less than item level
+        if current_page.level < level {
+            // We need to create a new node at a higher level
+            // First split the tree at our insertion point
+            let (low_key, high_key) = self.split(Some(current_root), item_key);
+
+            // Create a new page with our item between the split parts
+            let new_key = self.create_and_store_page(
+                level,
+                low_key,
+                vec![PageData {
+                    key: item_key,
+                    value: item_value,
+                    next: high_key,
+                }],
+            );
+            Ok((new_key, true))
+        }
+        // Case 2: Current level is equal to item level
+        else if current_page.level == level {
+            let mut new_page = Page {
+                level: current_page.level,
+                subtree_size: 0,
+                low: current_page.low,
+                list: Vec::with_capacity(current_page.list.len() + 1),
+            };
+
+            // Handle empty list case
+            if current_page.list.is_empty() {
+                new_page.list.push(PageData {
+                    key: item_key,
+                    value: item_value,
+                    next: None,
+                });
+            } else {
+                let first_key = current_page.list[0].key;
+
+                if Value::compare_keys(&item_key, &first_key) == Ordering::Less {
+                    // Item belongs before the first element
+                    let (low2a, low2b) = self.split(current_page.low, item_key);
+
+                    // Create new list starting with our item
+                    new_page.list.push(PageData {
+                        key: item_key,
+                        value: item_value,
+                        next: low2b,
+                    });
+
+                    // Add the rest of the original list
+                    new_page.list.extend(current_page.list);
+                    new_page.low = low2a;
+                } else {
+                    // Item belongs after the first element
+                    new_page.list = self.insert_after_first(
+                        &current_page.list,
+                        item_key,
+                        item_value,
+                        previous,
+                        resolve,
+                    )?;
+                }
+            }
+
+            let new_key = self.store_page(new_page);
+            Ok((new_key, true))
+        }
+        // Case 3: Current level is greater than item level
+        else {
+            if current_page.list.is_empty() {
+                // No items in this node, insert into low child
+                let low_key = current_page.low;
+                let (new_low_key, low_modified) =
+                    self.insert_at(low_key, item_key, item_value, level, previous, resolve)?;
+
+                // Only create a new page if the child was modified
+                if low_modified {
+                    let mut new_page = current_page.clone();
+                    new_page.low = Some(new_low_key);
+                    let new_key = self.store_page(new_page);
+                    return Ok((new_key, true));
+                } else {
+                    return Ok((current_root, false));
+                }
+            }
+
+            let first_key = current_page.list[0].key;
+
+            if Value::compare_keys(&item_key, &first_key) == Ordering::Less {
+                // Key is less than first entry - go to low child
+                let low_key = current_page.low;
+                let (new_low_key, low_modified) =
+                    self.insert_at(low_key, item_key, item_value, level, previous, resolve)?;
+
+                // Only create a new page if the child was modified
+                if low_modified {
                     let mut new_page = current_page.clone();
                     new_page.low = Some(new_low_key);
-                    let new_key = hash_page(&new_page);
-                    self.store.put(new_key, new_page);
-                    return (new_key, true);
+                    let new_key = self.store_page(new_page);
+                    Ok((new_key, true))
+                } else {
+                    Ok((current_root, false))
+                }
+            } else {
+                // Find where the item belongs in the list
+                let mut new_page = current_page.clone();
+                let modified = self.insert_into_list(
+                    &mut new_page.list,
+                    item_key,
+                    item_value,
+                    level,
+                    previous,
+                    resolve,
+                )?;
+
+                // Only create a new page if a child was modified
+                if modified {
+                    let new_key = self.store_page(new_page);
+                    Ok((new_key, true))
+                } else {
+                    Ok((current_root, false))
+                }
+            }
+        }
+    }
+
+    /// Helper function to insert an item into the right position in a list
+    /// Returns true if any modifications were made
+    fn insert_into_list<F>(
+        &mut self,
+        list: &mut [PageData<H::Key, Value>],
+        item_key: H::Key,
+        item_value: Value,
+        level: u32,
+        previous: &mut Option<Value>,
+        resolve: &F,
+    ) -> Result<bool, MstError>
+    where
+        F: Fn(Value, Value) -> Value,
+    {
+        for i in 0..list.len() {
+            // Check if the item belongs before this entry
+            if i < list.len() - 1
+                && Value::compare_keys(&item_key, &list[i + 1].key) == Ordering::Less
+            {
+                // Item belongs between entries i and i+1
+                let next_key = list[i].next;
+                let (new_next_key, next_modified) =
+                    self.insert_at(next_key, item_key, item_value, level, previous, resolve)?;
+
+                // Only update the list if the child was modified
+                if next_modified {
+                    list[i].next = Some(new_next_key);
+                    return Ok(true);
+                } else {
+                    return Ok(false);
+                }
+            }
+        }
+
+        // If we get here, the item belongs after the last entry
+        let last_idx = list.len() - 1;
+        let next_key = list[last_idx].next;
+        let (new_next_key, next_modified) =
+            self.insert_at(next_key, item_key, item_value, level, previous, resolve)?;
+
+        // Only update the list if the child was modified
+        if next_modified {
+            list[last_idx].next = Some(new_next_key);
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Helper function to insert a key-value pair after the first entry in a list
+    fn insert_after_first<F>(
+        &mut self,
+        entries: &[PageData<H::Key, Value>],
+        item_key: H::Key,
+        item_value: Value,
+        previous: &mut Option<Value>,
+        resolve: &F,
+    ) -> Result<Vec<PageData<H::Key, Value>>, MstError>
+    where
+        F: Fn(Value, Value) -> Value,
+    {
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut result_entries = Vec::with_capacity(entries.len() + 1);
+        let mut current_idx = 0;
+
+        while current_idx < entries.len() {
+            let entry = &entries[current_idx];
+
+            match Value::compare_keys(&entry.key, &item_key) {
+                Ordering::Equal => {
+                    // Key already exists - combine values
+                    *previous = Some(entry.value.clone());
+                    let merged_value = resolve(entry.value.clone(), item_value);
+                    result_entries.push(PageData {
+                        key: entry.key,
+                        value: merged_value,
+                        next: entry.next,
+                    });
+                    // Append the rest of the entries
+                    result_entries.extend_from_slice(&entries[current_idx + 1..]);
+                    break;
+                }
+                Ordering::Less => {
+                    if current_idx == entries.len() - 1
+                        || Value::compare_keys(&item_key, &entries[current_idx + 1].key)
+                            == Ordering::Less
+                    {
+                        // Insert between current entry and next entry
+                        let (left_subtree, right_subtree) = self.split(entry.next, item_key);
+                        result_entries.push(PageData {
+                            key: entry.key,
+                            value: entry.value.clone(),
+                            next: left_subtree,
+                        });
+                        result_entries.push(PageData {
+                            key: item_key,
+                            value: item_value,
+                            next: right_subtree,
+                        });
+                        // Append the rest
+                        result_entries.extend_from_slice(&entries[current_idx + 1..]);
+                        break;
+                    } else {
+                        // Not the right spot yet, keep current entry and continue
+                        result_entries.push(entries[current_idx].clone());
+                        current_idx += 1;
+                    }
+                }
+                Ordering::Greater => {
+                    // Only reachable if compare_keys is not a consistent total order
+                    return Err(MstError::InconsistentOrdering);
+                }
+            }
+        }
+
+        Ok(result_entries)
+    }
+
+    /// Partitions the tree into two independent trees at `key`
+    ///
+    /// The first tree holds every key `< key` and the second every key `>= key`.
+    /// Only the pages along the path to `key` are rewritten, so this is O(depth).
+    /// Both trees get a clone of the store, so each still holds the other half's
+    /// pages until `gc` is called. Merging the two halves again gives back the
+    /// original root.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// for byte in [1u8, 2, 3] {
+    ///     let key = MSTKey::clone_from_slice(&[byte; 32]);
+    ///     mst.insert(key, TestValue { key, data: [byte; 4] });
+    /// }
+    /// let (low, high) = mst.split_at(MSTKey::clone_from_slice(&[2; 32]));
+    /// assert_eq!((low.len(), high.len()), (1, 2));
+    /// ```
+    pub fn split_at(mut self, key: H::Key) -> (Self, Self)
+    where
+        S: Clone,
+    {
+        let (low, high) = self.split(self.root, key);
+
+        let high_tree = Self {
+            root: high,
+            store: self.store.clone(),
+            len: self.subtree_size(high) as usize,
+            level_base: self.level_base,
+            level_mapper: self.level_mapper,
+            merge_policy: self.merge_policy,
+            observer: None,
+            marker: PhantomData,
+        };
+        self.len = self.subtree_size(low) as usize;
+        self.root = low;
+        (self, high_tree)
+    }
+
+    /// Concatenates two trees where every key in `left` is smaller than every key
+    /// in `right`
+    ///
+    /// This is the inverse of `split_at`. The trees are stitched together along the
+    /// boundary between them, so only O(depth) pages are rewritten; the pages of
+    /// `right` are copied into the store of `left`. The result has the same root as
+    /// a tree built from all the entries of both.
+    ///
+    /// # Returns
+    ///
+    /// The joined tree, or `MstError::RangesOverlap` if the largest key of `left`
+    /// is not smaller than the smallest key of `right`
+    pub fn join(mut left: Self, right: Self) -> Result<Self, MstError> {
+        if let (Some(last), Some(first)) = (left.last(), right.first())
+            && Value::compare_keys(&last.key, &first.key) != Ordering::Less
+        {
+            return Err(MstError::RangesOverlap);
+        }
+
+        right.copy_pages_into(&mut left);
+        left.root = left.join_subtrees(left.root, right.root);
+        left.len += right.len;
+        Ok(left)
+    }
+
+    /// Splits the tree into two parts at the given key.
+    ///
+    /// This operation divides the tree into two separate subtrees:
+    /// - Left subtree: Contains all keys strictly less than split_key
+    /// - Right subtree: Contains all keys greater than or equal to split_key
+    ///
+    /// A page lying wholly on one side is returned as-is under its existing key,
+    /// rather than being rebuilt and hashed again. Pages that are rebuilt stay in
+    /// the store, like every page an insert replaces, so earlier roots remain
+    /// readable until `gc` drops them.
+    ///
+    /// # Arguments
+    /// * `node_key_opt`: Optional key to the node where splitting starts
+    /// * `split_key`: The key value at which to split the tree
+    ///
+    /// In debug builds every split is checked with `check_split`.
+    ///
+    /// # Returns
+    /// A tuple of (left_subtree_key, right_subtree_key), both optional
+    fn split(
+        &mut self,
+        node_key_opt: Option<H::Key>,
+        split_key: H::Key,
+    ) -> (Option<H::Key>, Option<H::Key>) {
+        if node_key_opt.is_some() {
+            self.notify(MstEvent::Split { at: split_key });
+        }
+        let halves = self.split_subtree(node_key_opt, split_key);
+        #[cfg(debug_assertions)]
+        self.check_split(split_key, halves);
+        halves
+    }
+
+    /// Asserts that splitting at `split_key` into `halves` put the largest entry
+    /// of the left half below the split key and the smallest entry of the right
+    /// half at or above it
+    ///
+    /// Only the boundary entries are read, so this is O(depth) and keeps debug
+    /// builds usable on large trees.
+    #[cfg(debug_assertions)]
+    fn check_split(&self, split_key: H::Key, (left, right): (Option<H::Key>, Option<H::Key>)) {
+        if let Some(entry) = self.edge_entry(left, true)
+            && Value::compare_keys(&entry.key, &split_key) != Ordering::Less
+        {
+            panic!("split at {:?} left {:?} on the left", split_key, entry.key);
+        }
+        if let Some(entry) = self.edge_entry(right, false)
+            && Value::compare_keys(&entry.key, &split_key) == Ordering::Less
+        {
+            panic!("split at {:?} left {:?} on the right", split_key, entry.key);
+        }
+    }
+
+    /// The body of `split`, recursing into itself to skip the debug check below
+    /// the top
+    fn split_subtree(
+        &mut self,
+        node_key_opt: Option<H::Key>,
+        split_key: H::Key,
+    ) -> (Option<H::Key>, Option<H::Key>) {
+        // Early return for empty trees - nothing to split
+        let Some(node_key) = node_key_opt else {
+            return (None, None);
+        };
+
+        let current_page = match self.store.get(node_key).cloned() {
+            Some(page) => page,
+            None => return (None, None),
+        };
+
+        let level = current_page.level;
+        let low_child = current_page.low;
+        let entries = current_page.list;
+
+        // If page has no entries, the left result is just the low branch
+        if entries.is_empty() {
+            return (low_child, None);
+        }
+
+        let first_entry = &entries[0];
+
+        // Compare split key with first entry to determine how to split
+        match Value::compare_keys(&split_key, &first_entry.key) {
+            Ordering::Less | Ordering::Equal => {
+                // Split key is not above the first entry - need to split the low branch
+                // and move all entries to the right subtree
+                let (lowlow, lowhi) = self.split_subtree(low_child, split_key);
+
+                // The whole page lies right of the split, keep it without rehashing
+                if lowhi == low_child {
+                    return (lowlow, Some(node_key));
+                }
+
+                // Build a right page holding all the original entries
+                let right_page_key = self.create_and_store_page(level, lowhi, entries.clone());
+
+                (lowlow, Some(right_page_key))
+            }
+            Ordering::Greater => {
+                // Split key is greater than the first entry
+                // We'll process entries one by one to determine where the split occurs
+                let mut left_entries = Vec::new();
+                let mut right_result = None;
+
+                // Process entries
+                let mut i = 0;
+                while i < entries.len() {
+                    let entry = &entries[i];
+
+                    if i < entries.len() - 1
+                        && Value::compare_keys(&split_key, &entries[i + 1].key) != Ordering::Greater
+                    {
+                        // We found the split point: between current entry and next entry
+                        // Current entry goes to left, entries after it go to right
+                        let (next_left, next_right) = self.split_subtree(entry.next, split_key);
+
+                        // Add current entry to the left part with the proper next pointer
+                        left_entries.push(PageData {
+                            key: entry.key,
+                            value: entry.value.clone(),
+                            next: next_left,
+                        });
+
+                        // Create right page with all remaining entries
+                        let right_entries = entries[i + 1..].to_vec();
+
+                        let right_page_key =
+                            self.create_and_store_page(level, next_right, right_entries);
+
+                        right_result = Some(right_page_key);
+                        break;
+                    }
+
+                    if i == entries.len() - 1 {
+                        // We've reached the last entry - need to split its next branch
+                        let (next_left, next_right) = self.split_subtree(entry.next, split_key);
+
+                        // The whole page lies left of the split, keep it without rehashing
+                        if next_left == entry.next {
+                            return (Some(node_key), next_right);
+                        }
+
+                        // Add the last entry to the left subtree
+                        left_entries.push(PageData {
+                            key: entry.key,
+                            value: entry.value.clone(),
+                            next: next_left,
+                        });
+
+                        right_result = next_right;
+                        break;
+                    }
+
+                    // Current entry belongs fully in the left part, continue to next
+                    left_entries.push(entry.clone());
+                    i += 1;
+                }
+
+                // Build the left page
+                let left_page_key = self.create_and_store_page(level, low_child, left_entries);
+
+                (Some(left_page_key), right_result)
+            }
+        }
+    }
+
+    /// Joins two subtrees where every key in `left` is smaller than every key in `right`.
+    ///
+    /// This is the inverse of `split`:
+    /// - Pages at the same level are concatenated, with the left page's last `next`
+    ///   joined to the right page's `low`
+    /// - A higher-level page absorbs the lower subtree along its boundary edge
+    ///
+    /// # Returns
+    /// The key of the joined subtree, or None if both sides are empty
+    fn join_subtrees(&mut self, left: Option<H::Key>, right: Option<H::Key>) -> Option<H::Key> {
+        let (left_key, right_key) = match (left, right) {
+            (None, right) => return right,
+            (left, None) => return left,
+            (Some(left_key), Some(right_key)) => (left_key, right_key),
+        };
+
+        // A missing page is treated as an empty subtree, like in `split`
+        let left_page = match self.store.get(left_key).cloned() {
+            Some(page) => page,
+            None => return Some(right_key),
+        };
+        let right_page = match self.store.get(right_key).cloned() {
+            Some(page) => page,
+            None => return Some(left_key),
+        };
+
+        let new_page = match left_page.level.cmp(&right_page.level) {
+            Ordering::Greater => {
+                // The right subtree hangs below the left page's last entry
+                let mut new_page = left_page;
+                match new_page.list.last_mut() {
+                    Some(last) => last.next = self.join_subtrees(last.next, Some(right_key)),
+                    None => new_page.low = self.join_subtrees(new_page.low, Some(right_key)),
+                }
+                new_page
+            }
+            Ordering::Less => {
+                // The left subtree hangs below the right page's low pointer
+                let mut new_page = right_page;
+                new_page.low = self.join_subtrees(Some(left_key), new_page.low);
+                new_page
+            }
+            Ordering::Equal => {
+                // Same level - stitch the boundary and concatenate the entries
+                let mut new_page = left_page;
+                let mut right_page = right_page;
+                match new_page.list.last_mut() {
+                    Some(last) => last.next = self.join_subtrees(last.next, right_page.low),
+                    None => new_page.low = self.join_subtrees(new_page.low, right_page.low),
+                }
+                new_page.list.append(&mut right_page.list);
+                new_page
+            }
+        };
+
+        let new_key = self.store_page(new_page);
+        Some(new_key)
+    }
+
+    /// Removes a key from the tree, returning its value if it was present.
+    ///
+    /// The removed entry's `next` subtree is re-joined with the subtree to its left
+    /// (the previous entry's `next`, or the page's `low`). A page whose list becomes
+    /// empty collapses into its `low` child, and removing the last entry resets the
+    /// root to None.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let key = MSTKey::default();
+    /// let value = TestValue { key, data: [0; 4] };
+    /// mst.insert(key, value);
+    /// assert_eq!(mst.remove(key), Some(value));
+    /// assert_eq!(mst.remove(key), None);
+    /// ```
+    pub fn remove(&mut self, item_key: H::Key) -> Option<Value> {
+        let old_root = self.root?;
+        self.track_created();
+        let removed = self.remove_at(old_root, item_key);
+        if let Some((new_root, _)) = removed {
+            self.root = new_root;
+            // Like in `recompute_root`, trust the new root over a counter that a
+            // swapped root or store may have left behind
+            self.len = self.subtree_size(new_root) as usize;
+        }
+        self.report_orphans(Some(old_root));
+        removed.map(|(_, removed_value)| removed_value)
+    }
+
+    /// Helper function that recursively removes an item below the given node.
+    /// Returns the new key of the affected subtree (None if it became empty)
+    /// together with the removed value, or None if the key was not found.
+    fn remove_at(&mut self, node_key: H::Key, item_key: H::Key) -> Option<(Option<H::Key>, Value)> {
+        let mut new_page = self.store.get(node_key).cloned()?;
+
+        // Locate the entry, or the child pointer the key falls under
+        // (None means the low child, Some(i) means the next pointer of entry i)
+        let mut child_idx = None;
+        let mut removed_value = None;
+        for (i, entry) in new_page.list.iter().enumerate() {
+            match Value::compare_keys(&item_key, &entry.key) {
+                Ordering::Equal => {
+                    removed_value = Some((i, entry.value.clone(), entry.next));
+                    break;
+                }
+                Ordering::Less => break,
+                Ordering::Greater => child_idx = Some(i),
+            }
+        }
+
+        let value = match removed_value {
+            Some((i, value, next)) => {
+                // Re-join the subtree left of the entry with the entry's next subtree
+                new_page.list.remove(i);
+                if i == 0 {
+                    new_page.low = self.join_subtrees(new_page.low, next);
                 } else {
-                    return (current_root, false);
+                    new_page.list[i - 1].next = self.join_subtrees(new_page.list[i - 1].next, next);
+                }
+                value
+            }
+            None => {
+                // Descend into the child the key belongs to
+                let child_key = match child_idx {
+                    None => new_page.low,
+                    Some(i) => new_page.list[i].next,
+                }?;
+                let (new_child, value) = self.remove_at(child_key, item_key)?;
+                match child_idx {
+                    None => new_page.low = new_child,
+                    Some(i) => new_page.list[i].next = new_child,
+                }
+                value
+            }
+        };
+
+        // Collapse a page with no entries left into its low child
+        if new_page.list.is_empty() {
+            return Some((new_page.low, value));
+        }
+
+        let new_key = self.store_page(new_page);
+        Some((Some(new_key), value))
+    }
+
+    /// Merges this MST with another MST, combining their contents.
+    ///
+    /// This operation creates a new tree that contains all items from both trees,
+    /// properly handling duplicate keys by using the Merge trait to combine values.
+    /// The merge operation preserves the cryptographic properties of both trees.
+    ///
+    /// The merge is structural: both trees are walked together from their roots,
+    /// and only the pages whose key ranges actually interleave are rebuilt.
+    /// Subtrees with the same hash on both sides are reused as-is, which assumes
+    /// `Merge` is idempotent (`v.merge(v) == v`). On a key collision the value
+    /// from `self` is merged with the value from `other`, as `self.merge(other)`.
+    ///
+    /// Every page reachable from either root has to be in its tree's store. On a
+    /// partially synced replica the merge is refused rather than silently leaving
+    /// out the entries below a missing page.
+    ///
+    /// # Returns
+    ///
+    /// The merged root and store, or `IntegrityError::MissingPage` with the key of
+    /// the first missing page, checking `self` before `other`
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst1: MST<TestValue> = MST::new();
+    /// let mst2: MST<TestValue> = MST::new();
+    /// let (merged_root, merged_store) = mst1.merge(&mst2).unwrap();
+    /// ```
+    pub fn merge(&mut self, other: &Self) -> MergeResult<H::Key, S>
+    where
+        S: Default,
+    {
+        let policy = self.merge_policy;
+        self.merge_with(other, |mine, theirs| {
+            policy.resolve(mine.clone(), theirs.clone())
+        })
+    }
+
+    /// Merges this MST with another MST, treating a missing page as an empty subtree
+    ///
+    /// Behaves like `merge` on complete stores. Where a page is missing, the
+    /// entries below it are left out of the result instead of failing.
+    pub fn merge_infallible(&mut self, other: &Self) -> (Option<H::Key>, S)
+    where
+        S: Default,
+    {
+        let policy = self.merge_policy;
+        self.merge_resolving(other, &|mine: &Value, theirs: &Value| {
+            policy.resolve(mine.clone(), theirs.clone())
+        })
+    }
+
+    /// Merges this MST with another MST, resolving key collisions with `resolve`
+    ///
+    /// Behaves like `merge`, but a key present in both trees gets the value
+    /// `resolve(&self_value, &other_value)` instead of going through `Merge`: the
+    /// first argument always comes from `self` and the second from `other`. The
+    /// resolver is only called for keys whose values actually meet; subtrees with
+    /// the same hash on both sides are reused as-is, so `resolve` should return `v`
+    /// for `resolve(&v, &v)`.
+    ///
+    /// # Returns
+    ///
+    /// The merged root and store, or `IntegrityError::MissingPage` as for `merge`
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let key = MSTKey::default();
+    /// let mut mst1: MST<TestValue> = MST::new();
+    /// mst1.insert(key, TestValue { key, data: [1; 4] });
+    /// let mut mst2: MST<TestValue> = MST::new();
+    /// mst2.insert(key, TestValue { key, data: [2; 4] });
+    ///
+    /// // Keep our own value on conflicts
+    /// let (merged_root, merged_store) = mst1.merge_with(&mst2, |mine, _| *mine).unwrap();
+    /// let merged = MST::with_store(merged_root, merged_store);
+    /// assert_eq!(merged.get_value(key).unwrap().data, [1; 4]);
+    /// ```
+    pub fn merge_with<F>(&mut self, other: &Self, resolve: F) -> MergeResult<H::Key, S>
+    where
+        S: Default,
+        F: Fn(&Value, &Value) -> Value,
+    {
+        self.check_pages_present()?;
+        other.check_pages_present()?;
+        Ok(self.merge_resolving(other, &resolve))
+    }
+
+    /// Merges `other` into a new store like `merge_with`, without checking that
+    /// the pages of either tree are present
+    fn merge_resolving<F>(&mut self, other: &Self, resolve: &F) -> (Option<H::Key>, S)
+    where
+        S: Default,
+        F: Fn(&Value, &Value) -> Value,
+    {
+        // Create a new empty MST
+        let mut new_mst = self.empty_like();
+
+        // Seed it with the pages of both trees so their subtrees can be reused
+        self.copy_pages_into(&mut new_mst);
+        other.copy_pages_into(&mut new_mst);
+
+        // Borrow the observer for the pages the merge creates
+        new_mst.observer = self.observer.take();
+        new_mst.root = new_mst.merge_subtrees(self.root, other.root, resolve);
+        self.observer = new_mst.observer.take();
+
+        (new_mst.root, new_mst.store)
+    }
+
+    /// Checks that every page reachable from the root is in the store
+    ///
+    /// # Returns
+    ///
+    /// `IntegrityError::MissingPage` for the first missing page in depth-first order
+    fn check_pages_present(&self) -> Result<(), IntegrityError<H::Key>> {
+        let mut visited = HashSet::new();
+        let mut stack = Vec::from_iter(self.root);
+        while let Some(key) = stack.pop() {
+            if !visited.insert(key) {
+                continue;
+            }
+            let page = self
+                .store
+                .get(key)
+                .ok_or(IntegrityError::MissingPage(key))?;
+            stack.extend(page.list.iter().rev().filter_map(|entry| entry.next));
+            stack.extend(page.low);
+        }
+        Ok(())
+    }
+
+    /// Merges any number of trees into one, like folding `merge` over them
+    ///
+    /// The pages of every tree are copied into a single new store once, and the
+    /// trees are then merged structurally into it one after another. They are
+    /// merged in ascending order of their root keys rather than in the order
+    /// given, so a key present in several trees has its values combined in that
+    /// order. With a commutative `Merge` this makes no difference to the values,
+    /// and either way the root is the same for every ordering of `trees`. The
+    /// result uses the level base of the first tree.
+    ///
+    /// Like `merge`, every tree is first checked for missing pages, so a partially
+    /// synced replica fails the merge instead of silently losing entries.
+    ///
+    /// # Returns
+    ///
+    /// The merged root and store, or `IntegrityError::MissingPage` with the key of
+    /// the first missing page, checking the trees in the order given
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
+    /// use mst::test_utils::{TestValue, create_key};
+    ///
+    /// let trees: Vec<MST<TestValue>> = (0..3u8)
+    ///     .map(|byte| {
+    ///         let mut mst = MST::new();
+    ///         let key = create_key(&[byte]);
+    ///         mst.insert(key, TestValue { key, data: [byte; 4] });
+    ///         mst
+    ///     })
+    ///     .collect();
+    ///
+    /// let (root, store) = MST::merge_all(&[&trees[2], &trees[0], &trees[1]]).unwrap();
+    /// let merged = MST::with_store(root, store);
+    /// assert_eq!(merged.len(), 3);
+    /// ```
+    pub fn merge_all(trees: &[&Self]) -> MergeResult<H::Key, S>
+    where
+        S: Default,
+    {
+        for tree in trees {
+            tree.check_pages_present()?;
+        }
+
+        let mut new_mst = match trees.first() {
+            Some(tree) => tree.empty_like(),
+            None => Self::with_hasher(),
+        };
+        for tree in trees {
+            tree.copy_pages_into(&mut new_mst);
+        }
+
+        let policy = new_mst.merge_policy;
+        let mut roots: Vec<H::Key> = trees.iter().filter_map(|tree| tree.root).collect();
+        roots.sort();
+        roots.dedup();
+        for root in roots {
+            new_mst.root = new_mst.merge_subtrees(
+                new_mst.root,
+                Some(root),
+                &|mine: &Value, theirs: &Value| policy.resolve(mine.clone(), theirs.clone()),
+            );
+        }
+
+        Ok((new_mst.root, new_mst.store))
+    }
+
+    /// Removes every entry for which `keep` returns false
+    ///
+    /// `keep` is called once per entry, in ascending key order. Pages with nothing
+    /// removed from them or below them are kept under their existing keys, and
+    /// where an entry is removed the subtrees on either side of it are joined, so
+    /// the result is the canonical tree of the retained entries.
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
+    /// use mst::test_utils::{TestValue, create_key};
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// for byte in 0..10u8 {
+    ///     let key = create_key(&[byte]);
+    ///     mst.insert(key, TestValue { key, data: [byte, 0, 0, 0] });
+    /// }
+    /// mst.retain(|_, value| value.data[0] % 2 == 0);
+    /// assert_eq!(mst.len(), 5);
+    /// ```
+    pub fn retain<F: FnMut(&H::Key, &Value) -> bool>(&mut self, mut keep: F) {
+        self.root = self.retain_subtree(self.root, &mut keep);
+        self.len = self.subtree_size(self.root) as usize;
+    }
+
+    /// Applies `retain` to the subtree at `node_key`
+    ///
+    /// # Returns
+    /// The key of the filtered subtree, or None if nothing is kept
+    fn retain_subtree<F: FnMut(&H::Key, &Value) -> bool>(
+        &mut self,
+        node_key: Option<H::Key>,
+        keep: &mut F,
+    ) -> Option<H::Key> {
+        // A missing page is treated as an empty subtree, like in `split`
+        let page = self.store.get(node_key?).cloned()?;
+
+        let mut changed = false;
+        let mut pending = self.retain_subtree(page.low, keep);
+        changed |= pending != page.low;
+        let mut low = None;
+        let mut list: Vec<PageData<H::Key, Value>> = Vec::new();
+
+        for entry in page.list {
+            let kept = keep(&entry.key, &entry.value);
+            let next = self.retain_subtree(entry.next, keep);
+            changed |= !kept || next != entry.next;
+
+            if kept {
+                match list.last_mut() {
+                    Some(last) => last.next = pending.take(),
+                    None => low = pending.take(),
+                }
+                list.push(PageData {
+                    next: None,
+                    ..entry
+                });
+                pending = next;
+            } else {
+                // The gaps on either side of a removed entry become one
+                pending = self.join_subtrees(pending, next);
+            }
+        }
+
+        if !changed {
+            return node_key;
+        }
+        match list.last_mut() {
+            Some(last) => last.next = pending,
+            None => return pending,
+        }
+        Some(self.create_and_store_page(page.level, low, list))
+    }
+
+    /// Returns a new tree holding the keys of both trees
+    ///
+    /// Like `merge`, but leaves `self` alone and returns the result as a tree.
+    /// Values of keys present in both are combined under the `MergePolicy` of
+    /// `self`: as `self_value.merge(other_value)` under `MergePolicy::Merge`, the
+    /// default, while under `MergePolicy::Replace` the value from `other` wins.
+    /// The result keeps the policy of `self`.
+    pub fn union(&self, other: &Self) -> Self
+    where
+        S: Default,
+    {
+        let mut result = self.seeded_with(other);
+        let policy = self.merge_policy;
+        result.root =
+            result.merge_subtrees(self.root, other.root, &|mine: &Value, theirs: &Value| {
+                policy.resolve(mine.clone(), theirs.clone())
+            });
+        result.len = result.subtree_size(result.root) as usize;
+        result
+    }
+
+    /// Returns a new tree holding only the keys present in both trees
+    ///
+    /// Values are combined under the `MergePolicy` of `self`, like in `union`: as
+    /// `self_value.merge(other_value)` under `MergePolicy::Merge`, while under
+    /// `MergePolicy::Replace` the value from `other` wins. Both trees are walked
+    /// together like in `merge`, and a subtree with the same hash on both sides is
+    /// kept whole without being opened, which assumes `Merge` is idempotent.
+    pub fn intersection(&self, other: &Self) -> Self
+    where
+        S: Default,
+    {
+        let mut result = self.seeded_with(other);
+        result.root = result.filter_subtrees(self.root, other.root, true);
+        result.len = result.subtree_size(result.root) as usize;
+        result
+    }
+
+    /// Returns a new tree holding the entries of `self` whose keys are not in `other`
+    ///
+    /// A subtree with the same hash on both sides is dropped whole without being
+    /// opened, and one with nothing to compare against is kept as-is.
+    pub fn difference(&self, other: &Self) -> Self
+    where
+        S: Default,
+    {
+        let mut result = self.seeded_with(other);
+        result.root = result.filter_subtrees(self.root, other.root, false);
+        result.len = result.subtree_size(result.root) as usize;
+        result
+    }
+
+    /// Creates an empty tree with the same level base, holding the pages of both trees
+    fn seeded_with(&self, other: &Self) -> Self
+    where
+        S: Default,
+    {
+        let mut result = self.empty_like();
+        self.copy_pages_into(&mut result);
+        other.copy_pages_into(&mut result);
+        result
+    }
+
+    /// Helper function to copy every page reachable from this tree's root into another MST
+    fn copy_pages_into(&self, target: &mut Self) {
+        let visitor = |event: TraversalEvent<H::Key, Value>| -> TraversalControl {
+            if let TraversalEvent::VisitNode(node_key, page) = event {
+                target.store.put(node_key, page.clone());
+            }
+            TraversalControl::Continue
+        };
+
+        // A subtree shared between positions is visited at each, and putting it again is harmless
+        self.traverse_tree(TraversalStrategy::MSTOrder, visitor);
+    }
+
+    /// Structurally merges two subtrees whose pages are both in this tree's store.
+    ///
+    /// The higher of the two root levels becomes the level of the merged page; a
+    /// lower-level side is treated as a page at that level with no entries. The
+    /// entries at that level are merged in key order, and each gap between them is
+    /// filled by recursively merging the pieces of both sides that fall into it,
+    /// splitting a side's subtree at the other side's keys where needed. Values of
+    /// colliding keys are combined as `resolve(left, right)`.
+    ///
+    /// # Returns
+    /// The key of the merged subtree, or None if both sides are empty
+    fn merge_subtrees<F>(
+        &mut self,
+        left: Option<H::Key>,
+        right: Option<H::Key>,
+        resolve: &F,
+    ) -> Option<H::Key>
+    where
+        F: Fn(&Value, &Value) -> Value,
+    {
+        let (left_key, right_key) = match (left, right) {
+            (None, right) => return right,
+            (left, None) => return left,
+            (Some(left_key), Some(right_key)) => (left_key, right_key),
+        };
+
+        // Identical subtrees hold identical entries, nothing to merge
+        if left_key == right_key {
+            return Some(left_key);
+        }
+
+        // A missing page is treated as an empty subtree, like in `split`
+        let left_page = match self.store.get(left_key).cloned() {
+            Some(page) => page,
+            None => return Some(right_key),
+        };
+        let right_page = match self.store.get(right_key).cloned() {
+            Some(page) => page,
+            None => return Some(left_key),
+        };
+
+        let level = left_page.level.max(right_page.level);
+        let (mut left_gap, left_entries) = if left_page.level == level {
+            (left_page.low, left_page.list)
+        } else {
+            (Some(left_key), Vec::new())
+        };
+        let (mut right_gap, right_entries) = if right_page.level == level {
+            (right_page.low, right_page.list)
+        } else {
+            (Some(right_key), Vec::new())
+        };
+
+        let mut low = None;
+        let mut list: Vec<PageData<H::Key, Value>> = Vec::new();
+        let mut left_iter = left_entries.into_iter().peekable();
+        let mut right_iter = right_entries.into_iter().peekable();
+
+        loop {
+            let order = match (left_iter.peek(), right_iter.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(l), Some(r)) => Value::compare_keys(&l.key, &r.key),
+            };
+
+            // Fill the gap before the next entry and advance past it
+            let (gap, key, value) = match order {
+                Ordering::Less => {
+                    let entry = left_iter.next().unwrap();
+                    let (below, above) = self.split(right_gap, entry.key);
+                    let gap = self.merge_subtrees(left_gap, below, resolve);
+                    left_gap = entry.next;
+                    right_gap = above;
+                    (gap, entry.key, entry.value)
+                }
+                Ordering::Greater => {
+                    let entry = right_iter.next().unwrap();
+                    let (below, above) = self.split(left_gap, entry.key);
+                    let gap = self.merge_subtrees(below, right_gap, resolve);
+                    left_gap = above;
+                    right_gap = entry.next;
+                    (gap, entry.key, entry.value)
+                }
+                Ordering::Equal => {
+                    let left_entry = left_iter.next().unwrap();
+                    let right_entry = right_iter.next().unwrap();
+                    let gap = self.merge_subtrees(left_gap, right_gap, resolve);
+                    left_gap = left_entry.next;
+                    right_gap = right_entry.next;
+                    (
+                        gap,
+                        left_entry.key,
+                        resolve(&left_entry.value, &right_entry.value),
+                    )
+                }
+            };
+
+            match list.last_mut() {
+                Some(last) => last.next = gap,
+                None => low = gap,
+            }
+            list.push(PageData {
+                key,
+                value,
+                next: None,
+            });
+        }
+
+        // Whatever remains lies after the last entry
+        let gap = self.merge_subtrees(left_gap, right_gap, resolve);
+        match list.last_mut() {
+            Some(last) => last.next = gap,
+            None => return gap,
+        }
+
+        Some(self.create_and_store_page(level, low, list))
+    }
+
+    /// Filters the subtree `left` by membership in the subtree `right`, both of
+    /// whose pages are in this tree's store
+    ///
+    /// With `keep_common` the entries of `left` whose keys are in `right` are kept,
+    /// their values merged with `right`'s, otherwise the entries whose keys are not.
+    /// The sides are aligned level by level like in `merge_subtrees`. A key has
+    /// the same level in both trees, so the entries of the two pages at the current
+    /// level are the only places it can meet its counterpart. Where an entry is
+    /// dropped, the filtered gaps on either side of it are joined.
+    ///
+    /// # Returns
+    /// The key of the filtered subtree, or None if nothing is kept
+    fn filter_subtrees(
+        &mut self,
+        left: Option<H::Key>,
+        right: Option<H::Key>,
+        keep_common: bool,
+    ) -> Option<H::Key> {
+        let (left_key, right_key) = match (left, right) {
+            (None, _) => return None,
+            (left, None) => return if keep_common { None } else { left },
+            (Some(left_key), Some(right_key)) => (left_key, right_key),
+        };
+
+        // Identical subtrees share every key
+        if left_key == right_key {
+            return if keep_common { Some(left_key) } else { None };
+        }
+
+        // A missing page is treated as an empty subtree, like in `split`
+        let left_page = self.store.get(left_key).cloned()?;
+        let Some(right_page) = self.store.get(right_key).cloned() else {
+            return if keep_common { None } else { Some(left_key) };
+        };
+
+        let level = left_page.level.max(right_page.level);
+        let (mut left_gap, left_entries) = if left_page.level == level {
+            (left_page.low, left_page.list)
+        } else {
+            (Some(left_key), Vec::new())
+        };
+        let (mut right_gap, right_entries) = if right_page.level == level {
+            (right_page.low, right_page.list)
+        } else {
+            (Some(right_key), Vec::new())
+        };
+
+        // The filtered subtree waiting for the next kept entry, joined across dropped ones
+        let mut pending = None;
+        let mut low = None;
+        let mut list: Vec<PageData<H::Key, Value>> = Vec::new();
+        let mut left_iter = left_entries.into_iter().peekable();
+        let mut right_iter = right_entries.into_iter().peekable();
+
+        loop {
+            let order = match (left_iter.peek(), right_iter.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(l), Some(r)) => Value::compare_keys(&l.key, &r.key),
+            };
+
+            // Filter the gap before the next entry and decide whether the entry stays
+            let (gap, kept) = match order {
+                Ordering::Less => {
+                    let entry = left_iter.next().unwrap();
+                    let (below, above) = self.split(right_gap, entry.key);
+                    let gap = self.filter_subtrees(left_gap, below, keep_common);
+                    left_gap = entry.next;
+                    right_gap = above;
+                    (gap, (!keep_common).then_some(entry))
+                }
+                Ordering::Greater => {
+                    let entry = right_iter.next().unwrap();
+                    let (below, above) = self.split(left_gap, entry.key);
+                    let gap = self.filter_subtrees(below, right_gap, keep_common);
+                    left_gap = above;
+                    right_gap = entry.next;
+                    (gap, None)
+                }
+                Ordering::Equal => {
+                    let mut left_entry = left_iter.next().unwrap();
+                    let right_entry = right_iter.next().unwrap();
+                    let gap = self.filter_subtrees(left_gap, right_gap, keep_common);
+                    left_gap = left_entry.next;
+                    right_gap = right_entry.next;
+                    left_entry.value = self
+                        .merge_policy
+                        .resolve(left_entry.value, right_entry.value);
+                    (gap, keep_common.then_some(left_entry))
+                }
+            };
+
+            pending = self.join_subtrees(pending, gap);
+            if let Some(entry) = kept {
+                match list.last_mut() {
+                    Some(last) => last.next = pending.take(),
+                    None => low = pending.take(),
+                }
+                list.push(PageData {
+                    key: entry.key,
+                    value: entry.value,
+                    next: None,
+                });
+            }
+        }
+
+        // Whatever remains lies after the last entry
+        let gap = self.filter_subtrees(left_gap, right_gap, keep_common);
+        pending = self.join_subtrees(pending, gap);
+        match list.last_mut() {
+            Some(last) => last.next = pending,
+            None => return pending,
+        }
+
+        Some(self.create_and_store_page(level, low, list))
+    }
+
+    /// Get a specific value by key from the tree
+    ///
+    /// # Arguments
+    ///
+    /// * `search_key`: The key to search for
+    ///
+    /// # Returns
+    ///
+    /// Option containing the value if found, None otherwise, including when the
+    /// key holds a tombstone
+    pub fn get_value(&self, search_key: H::Key) -> Option<Value> {
+        self.get_value_ref(search_key)
+            .filter(|value| !value.is_tombstone())
+            .cloned()
+    }
+
+    /// Retrieves the value for a key, telling an absent key apart from one whose
+    /// path leads to a page missing from the store
+    ///
+    /// `get_value` reads a missing page as an empty subtree, so on a partially
+    /// synced replica it cannot say whether a key does not exist or its page has
+    /// not arrived yet. This follows the same path, but stops at the first
+    /// missing page instead.
+    ///
+    /// # Returns
+    ///
+    /// The value, or None if the key is absent or holds a tombstone, or
+    /// `IntegrityError::MissingPage` with the key of the page that has to be
+    /// fetched before the lookup can be answered
+    pub fn try_get_value(
+        &self,
+        search_key: H::Key,
+    ) -> Result<Option<Value>, IntegrityError<H::Key>> {
+        let mut descent = Descent::new(self.root);
+
+        while let Some(current) = descent.next_key() {
+            let page = self
+                .store
+                .get(current)
+                .ok_or(IntegrityError::MissingPage(current))?;
+
+            if let Some(Ok(index)) = descent.step(page, &search_key) {
+                let value = &page.list[index].value;
+                return Ok((!value.is_tombstone()).then(|| value.clone()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Checks whether a key is present in the tree
+    ///
+    /// Follows the same path as `get_value`, but stops at the matching entry
+    /// without copying its value.
+    ///
+    /// # Arguments
+    ///
+    /// * `search_key`: The key to search for
+    ///
+    /// # Returns
+    ///
+    /// true if the key is present, false otherwise or if it holds a tombstone
+    pub fn contains_key(&self, search_key: H::Key) -> bool {
+        let mut descent = Descent::new(self.root);
+
+        while let Some(current) = descent.next_key() {
+            let Some(page) = self.store.get(current) else {
+                return false;
+            };
+            if let Some(Ok(index)) = descent.step(page, &search_key) {
+                return !page.list[index].value.is_tombstone();
+            }
+        }
+
+        false
+    }
+
+    /// Looks up many keys in a single sweep over the tree
+    ///
+    /// The keys are sorted and pushed down the tree together, each page splitting
+    /// them among its children, so a page on the path of several keys is read once
+    /// rather than once per key.
+    ///
+    /// # Returns
+    ///
+    /// The value for each key, in the order of `keys`, None for tombstones like
+    /// `get_value`
+    pub fn get_many(&self, keys: &[H::Key]) -> Vec<Option<Value>> {
+        let mut sorted: Vec<(usize, H::Key)> = keys.iter().copied().enumerate().collect();
+        sorted.sort_by(|(_, a), (_, b)| Value::compare_keys(a, b));
+
+        let mut results = vec![None; keys.len()];
+        self.get_many_from(self.root, None, &sorted, &mut results);
+        results
+    }
+
+    /// Fills in `results` for the sorted `keys` that fall into the subtree at
+    /// `node_key`, a child of a page at `parent_level`
+    ///
+    /// Like `Descent`, gives up on a page that is not below its parent rather
+    /// than recursing forever through a cycle.
+    fn get_many_from(
+        &self,
+        node_key: Option<H::Key>,
+        parent_level: Option<u32>,
+        mut keys: &[(usize, H::Key)],
+        results: &mut [Option<Value>],
+    ) {
+        if keys.is_empty() {
+            return;
+        }
+        let Some(page) = node_key.and_then(|key| self.store.get(key)) else {
+            return;
+        };
+        if parent_level.is_some_and(|level| page.level >= level) {
+            return;
+        }
+        let level = Some(page.level);
+
+        let mut child = page.low;
+        for entry in &page.list {
+            // Keys below this entry belong to the child before it
+            let below = keys
+                .partition_point(|(_, key)| Value::compare_keys(key, &entry.key) == Ordering::Less);
+            self.get_many_from(child, level, &keys[..below], results);
+            keys = &keys[below..];
+
+            let equal = keys.partition_point(|(_, key)| {
+                Value::compare_keys(key, &entry.key) == Ordering::Equal
+            });
+            if !entry.value.is_tombstone() {
+                for (index, _) in &keys[..equal] {
+                    results[*index] = Some(entry.value.clone());
                 }
+            }
+            keys = &keys[equal..];
+            child = entry.next;
+        }
+        self.get_many_from(child, level, keys, results);
+    }
+
+    /// Returns the current root as a handle to this version of the tree
+    ///
+    /// Pages are never modified in place, and inserts and removals leave the pages
+    /// they replace in the store, so the handle stays readable through `view`
+    /// until `gc` is run without it among the live roots.
+    pub fn snapshot(&self) -> Option<H::Key> {
+        self.root
+    }
+
+    /// Returns a read-only view of the tree as of `root`, typically a `snapshot`,
+    /// reading from this tree's store
+    ///
+    /// # Example
+    /// ```
+    /// use mst::MST;
+    /// use mst::test_utils::{TestValue, create_key};
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// let before = mst.snapshot();
+    /// let key = create_key(b"key");
+    /// mst.insert(key, TestValue { key, data: [1, 0, 0, 0] });
+    /// assert_eq!(mst.view(before).get_value(key), None);
+    /// ```
+    pub fn view(&self, root: Option<H::Key>) -> MstView<'_, Value, H, S> {
+        MstView {
+            store: &self.store,
+            root,
+            marker: PhantomData,
+        }
+    }
+
+    /// Removes every page not reachable from `live_roots` or the current root
+    ///
+    /// Inserts and removals write new pages without deleting the ones they replace,
+    /// so the store keeps growing. This keeps the pages of the given historical roots
+    /// (and of `self.root`, which is always live) and discards the rest. Pages a
+    /// value references through `Reference::value_refs` are kept along with the value.
+    ///
+    /// # Returns
+    ///
+    /// The number of pages removed from the store
+    pub fn gc(&mut self, live_roots: &[H::Key]) -> usize {
+        let mut live = self
+            .root
+            .map(|root| self.store.reachable_set(root))
+            .unwrap_or_default();
+        for root in live_roots {
+            if !live.contains(root) {
+                live.extend(self.store.reachable_set(*root));
+            }
+        }
+
+        let garbage: Vec<H::Key> = self
+            .store
+            .iter()
+            .map(|(key, _)| *key)
+            .filter(|key| !live.contains(key))
+            .collect();
+        for key in &garbage {
+            self.store.remove(*key);
+        }
+        garbage.len()
+    }
+
+    /// Rebuilds the tree from its entries and drops every other page
+    ///
+    /// The entries are read in key order and built bottom-up into fresh pages like
+    /// `from_sorted` does, which yields the canonical tree for the key set, and
+    /// then `gc` removes the old pages. Nothing else is kept live, so roots from
+    /// earlier `snapshot`s are no longer readable afterwards. This is maintenance
+    /// for long-lived trees whose stores have accumulated replaced pages.
+    pub fn compact(&mut self) {
+        let entries: Vec<(u32, PageData<H::Key, Value>)> = self
+            .iter()
+            .map(|entry| {
+                (
+                    self.level_of(entry.key),
+                    PageData {
+                        key: entry.key,
+                        value: entry.value.clone(),
+                        next: None,
+                    },
+                )
+            })
+            .collect();
+
+        self.len = entries.len();
+        self.root = self.build_sorted(&entries);
+        self.gc(&[]);
+    }
+
+    /// Rehashes the tree bottom-up after its pages were edited in the store
+    ///
+    /// An escape hatch for surgery on `store`: a page changed in place is still
+    /// stored under its old key, so it and every page above it no longer match
+    /// their hashes and `root` is stale. Starting from the root, this follows the
+    /// stored child pointers, stores each page again under its recomputed hash
+    /// once its children have been, with its subtree size recounted, and points
+    /// its parent at the new key. Pages are shared between positions rehash once.
+    /// Every page is hashed, edited or not. Replaced pages are left in the store;
+    /// `gc` removes them.
+    ///
+    /// The edits must keep the tree's invariants, with entries in key order and
+    /// at the pages their levels place them, for the result to be the root a
+    /// clean rebuild would give. `compact` rebuilds the tree from its entries
+    /// regardless of how they are arranged.
+    ///
+    /// # Returns
+    ///
+    /// The new root, or `IntegrityError::MissingPage` for the first child pointer
+    /// that leads to no page, or `IntegrityError::Cycle` for the first page not
+    /// below its parent, in which case `root` is left unchanged
+    pub fn recompute_root(&mut self) -> Result<Option<H::Key>, IntegrityError<H::Key>> {
+        let mut rehashed = HashMap::new();
+        let root = match self.root {
+            Some(root) => Some(self.rehash_subtree(root, None, &mut rehashed)?),
+            None => None,
+        };
+
+        self.root = root;
+        self.len = self.subtree_size(root) as usize;
+        Ok(root)
+    }
+
+    /// Applies `recompute_root` to the subtree at `node_key`, a child of a page at
+    /// `parent_level`, recording the new key of every page in `rehashed`
+    fn rehash_subtree(
+        &mut self,
+        node_key: H::Key,
+        parent_level: Option<u32>,
+        rehashed: &mut HashMap<H::Key, H::Key>,
+    ) -> Result<H::Key, IntegrityError<H::Key>> {
+        if let Some(new_key) = rehashed.get(&node_key) {
+            return Ok(*new_key);
+        }
+
+        let mut page = self
+            .store
+            .get(node_key)
+            .cloned()
+            .ok_or(IntegrityError::MissingPage(node_key))?;
+        // A page is only rehashed once its children are, so a cycle would recurse
+        // forever before `rehashed` could cut it short
+        if parent_level.is_some_and(|level| page.level >= level) {
+            return Err(IntegrityError::Cycle(node_key));
+        }
+        let level = Some(page.level);
+        if let Some(low) = page.low {
+            page.low = Some(self.rehash_subtree(low, level, rehashed)?);
+        }
+        for entry in &mut page.list {
+            if let Some(next) = entry.next {
+                entry.next = Some(self.rehash_subtree(next, level, rehashed)?);
+            }
+        }
+
+        let new_key = self.store_page(page);
+        rehashed.insert(node_key, new_key);
+        Ok(new_key)
+    }
+
+    /// Summarizes the pages reachable from the root
+    ///
+    /// Orphaned pages left in the store by earlier versions of the tree are not
+    /// counted. Walks every page of the tree, so it is O(tree).
+    ///
+    /// # Returns
+    ///
+    /// The tree's `TreeStats`, all zero for an empty tree
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let mut levels = HashSet::new();
+
+        for key in self.page_keys() {
+            let Some(page) = self.store.get(key) else {
+                continue;
+            };
+            let fanout = page.list.len();
+            stats.min_fanout = if stats.pages == 0 {
+                fanout
             } else {
-                // Find where the item belongs in the list
-                let mut new_page = current_page.clone();
-                let modified =
-                    self.insert_into_list(&mut new_page.list, item_key, item_value, level);
+                stats.min_fanout.min(fanout)
+            };
+            stats.max_fanout = stats.max_fanout.max(fanout);
+            stats.max_level = stats.max_level.max(page.level);
+            stats.pages += 1;
+            stats.entries += fanout;
+            levels.insert(page.level);
+        }
+
+        if stats.pages > 0 {
+            stats.average_fanout = stats.entries as f64 / stats.pages as f64;
+        }
+        stats.distinct_levels = levels.len();
+        stats
+    }
+
+    /// Returns the total length of the `encode_page` encodings of the pages
+    /// reachable from the root, approximating the tree's size on disk or on the
+    /// wire
+    ///
+    /// Each page is counted once, however many places refer to it, and orphaned
+    /// pages are not counted. Walks every page of the tree, so it is O(tree).
+    pub fn byte_size(&self) -> usize {
+        let mut size = 0;
+        for key in self.page_keys() {
+            if let Some(page) = self.store.get(key) {
+                write_page(page, |bytes| size += bytes.len());
+            }
+        }
+        size
+    }
+
+    /// Checks that both trees have the same root and can both serve its content
+    ///
+    /// Comparing roots alone says two trees hold the same entries, but not that a
+    /// tree's store actually has them: a truncated or partially synced store can
+    /// claim the root of a complete one. This also walks both trees and requires
+    /// every page reachable from the root to be present in each store. Page
+    /// contents are not rehashed; `verify_integrity` does that.
+    ///
+    /// # Returns
+    ///
+    /// true if the roots match and neither store is missing a reachable page,
+    /// including when both trees are empty
+    pub fn structurally_equal(&self, other: &Self) -> bool {
+        self.root == other.root
+            && self.check_pages_present().is_ok()
+            && other.check_pages_present().is_ok()
+    }
+
+    /// Checks that every page reachable from the root is present and intact
+    ///
+    /// Walks the tree from `self.root`, recomputing the hash of each page and
+    /// comparing it with the key it is stored under. This detects corrupted pages,
+    /// for example from bit-rot or a faulty `PageStore`, which can otherwise still
+    /// be walked. The same walk checks the layout `import_validated` requires:
+    /// sorted entries, children below their parents, and cached subtree sizes
+    /// that match, since `rank` and `select` trust those sizes.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the tree is intact (including an empty tree), or the error for
+    /// the first offending page in depth-first order
+    pub fn verify_integrity(&self) -> Result<(), IntegrityError<H::Key>> {
+        Self::check_pages(&self.store, self.root, true)
+    }
+
+    /// Builds an inclusion proof for a key
+    ///
+    /// Records every page on the search path from the root to the page holding the
+    /// key, so a verifier that only knows `self.root` can recompute the hashes and
+    /// confirm the entry is part of the tree.
+    ///
+    /// # Returns
+    ///
+    /// The proof, or None if the key is not present
+    pub fn prove(&self, key: H::Key) -> Option<InclusionProof<Value, H::Key>> {
+        let mut pages = Vec::new();
+        let mut descent = Descent::new(self.root);
+
+        while let Some(current) = descent.next_key() {
+            let page = self.store.get(current)?;
+            pages.push(ProofPage::from_page::<H, Value>(page));
+
+            if let Ok(index) = descent.step(page, &key)? {
+                return Some(InclusionProof {
+                    pages,
+                    entry: page.list[index].clone(),
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Builds an absence proof for a key
+    ///
+    /// Records the search path for the key from the root down to the page where
+    /// it runs out of children, along with the entries immediately before and after
+    /// the key, all of which lie on that path. Either neighbour is None when the key
+    /// is below the smallest or above the largest key.
+    ///
+    /// # Returns
+    ///
+    /// The proof, or None if the key is present (or a page is missing)
+    pub fn prove_absence(&self, key: H::Key) -> Option<AbsenceProof<Value, H::Key>> {
+        let mut pages = Vec::new();
+        let mut predecessor = None;
+        let mut successor = None;
+        let mut descent = Descent::new(self.root);
+
+        while let Some(current) = descent.next_key() {
+            let page = self.store.get(current)?;
+            pages.push(ProofPage::from_page::<H, Value>(page));
+
+            // Each deeper page lies between the neighbours found so far, so its
+            // entries are closer to the key
+            let index = descent.step(page, &key)?.err()?;
+            if let Some(entry) = index.checked_sub(1).map(|i| &page.list[i]) {
+                predecessor = Some(entry.clone());
+            }
+            if let Some(entry) = page.list.get(index) {
+                successor = Some(entry.clone());
+            }
+        }
+
+        Some(AbsenceProof {
+            pages,
+            predecessor,
+            successor,
+        })
+    }
+
+    /// Reports the keys present in only one of two trees
+    ///
+    /// Both trees are walked in key order at the same time, and whenever the next
+    /// unvisited subtree has the same hash on both sides it is skipped without being
+    /// loaded. Only the pages along paths that actually differ are read, so trees
+    /// that mostly overlap are compared far more cheaply than via `to_list`.
+    /// Keys present in both trees are not reported, even if their values differ.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of (keys only in self, keys only in other), each in ascending order
+    pub fn diff(&self, other: &Self) -> (Vec<H::Key>, Vec<H::Key>) {
+        let (only_in_self, only_in_other, _) = self.diff_with_stats(other);
+        (only_in_self, only_in_other)
+    }
+
+    /// Like `diff`, but also returns the number of pages that had to be loaded
+    /// from either store to compute it
+    pub fn diff_with_stats(&self, other: &Self) -> (Vec<H::Key>, Vec<H::Key>, usize) {
+        let mut only_in_self = Vec::new();
+        let mut only_in_other = Vec::new();
+        let mut pages_visited = 0;
+
+        let mut left = DiffCursor::<Value, H, S>::new(&self.store, self.root);
+        let mut right = DiffCursor::<Value, H, S>::new(&other.store, other.root);
+
+        loop {
+            // Identical subtrees hold identical keys, skip them unopened
+            if left.subtree().is_some() && left.subtree() == right.subtree() {
+                left.stack.pop();
+                right.stack.pop();
+                continue;
+            }
+
+            left.load(&mut pages_visited);
+            right.load(&mut pages_visited);
+
+            match (left.stack.last(), right.stack.last()) {
+                (None, None) => break,
+                (Some(DiffItem::Entry(left_key)), Some(DiffItem::Entry(right_key))) => {
+                    match Value::compare_keys(left_key, right_key) {
+                        Ordering::Equal => {
+                            left.stack.pop();
+                            right.stack.pop();
+                        }
+                        Ordering::Less => {
+                            only_in_self.push(*left_key);
+                            left.stack.pop();
+                        }
+                        Ordering::Greater => {
+                            only_in_other.push(*right_key);
+                            right.stack.pop();
+                        }
+                    }
+                }
+                (Some(DiffItem::Entry(left_key)), None) => {
+                    only_in_self.push(*left_key);
+                    left.stack.pop();
+                }
+                (None, Some(DiffItem::Entry(right_key))) => {
+                    only_in_other.push(*right_key);
+                    right.stack.pop();
+                }
+                (left_item, right_item) => {
+                    // At least one side is a page: open the taller one, or both on a tie
+                    let left_level = left_item.and_then(DiffItem::level);
+                    let right_level = right_item.and_then(DiffItem::level);
+                    if left_level >= right_level {
+                        left.expand();
+                    }
+                    if right_level >= left_level {
+                        right.expand();
+                    }
+                }
+            }
+        }
+
+        (only_in_self, only_in_other, pages_visited)
+    }
+
+    /// Finds the deepest pair of pages where two trees part ways
+    ///
+    /// Both trees are descended in lockstep from their roots. While the two pages
+    /// hold the same entries at the same level, their differences lie in their
+    /// children, so the walk follows the first child pointer (`low`, then each
+    /// `next`) that differs. It stops at the first pair of pages whose own entries
+    /// differ, or that cannot be opened because a page is missing from a store.
+    /// Only the pages along that one path are loaded.
+    ///
+    /// # Returns
+    ///
+    /// None if the roots are equal. Otherwise the pair of (self's page, other's
+    /// page) keys at the divergence point. A side is None when the other side has
+    /// a subtree where it has none, such as a whole page present in only one tree.
+    pub fn first_divergence(&self, other: &Self) -> Option<Divergence<H::Key>> {
+        let (mut left, mut right) = (self.root, other.root);
+        if left == right {
+            return None;
+        }
+
+        // Descend while both sides are pages with the same entries
+        while let (Some(left_key), Some(right_key)) = (left, right) {
+            let (Some(left_page), Some(right_page)) =
+                (self.store.get(left_key), other.store.get(right_key))
+            else {
+                break;
+            };
+            let same_entries = left_page.level == right_page.level
+                && left_page.list.len() == right_page.list.len()
+                && left_page.list.iter().zip(&right_page.list).all(|(l, r)| {
+                    Value::compare_keys(&l.key, &r.key) == Ordering::Equal
+                        && l.value.as_ref() == r.value.as_ref()
+                });
+            if !same_entries {
+                break;
+            }
+
+            let left_children =
+                std::iter::once(left_page.low).chain(left_page.list.iter().map(|e| e.next));
+            let right_children =
+                std::iter::once(right_page.low).chain(right_page.list.iter().map(|e| e.next));
+            match left_children.zip(right_children).find(|(l, r)| l != r) {
+                Some((left_child, right_child)) => (left, right) = (left_child, right_child),
+                // Only the recorded subtree sizes differ, the pages themselves are the answer
+                None => break,
+            }
+        }
+
+        Some((left, right))
+    }
+
+    /// Debug function to dump the tree structure
+    ///
+    /// The tree's `Display` implementation writes the same output.
+    ///
+    /// # Returns
+    ///
+    /// A string representation of the tree
+    pub fn dump(&self) -> String {
+        let Some(root) = self.root else {
+            return String::new();
+        };
+
+        let mut output = String::new();
+        let mut depth_map = HashMap::new();
+        depth_map.insert(root, 0);
+
+        let visitor = |event: TraversalEvent<H::Key, Value>| -> TraversalControl {
+            match event {
+                TraversalEvent::VisitNode(node_key, page) => {
+                    let depth = depth_map.get(&node_key).copied().unwrap_or(0);
+                    let indent = "  ".repeat(depth);
+                    output.push_str(&format!("{}{:?} ({})\n", indent, node_key, page.level));
 
-                // Only create a new page if a child was modified
-                if modified {
-                    let new_key = hash_page(&new_page);
-                    self.store.put(new_key, new_page);
-                    return (new_key, true);
-                } else {
-                    return (current_root, false);
+                    // Store depths for children
+                    if let Some(low) = page.low {
+                        depth_map.insert(low, depth + 1);
+                    }
+
+                    for entry in &page.list {
+                        if let Some(next) = entry.next {
+                            depth_map.insert(next, depth + 1);
+                        }
+                    }
+
+                    TraversalControl::Continue
                 }
+                TraversalEvent::VisitEntry(node_key, entry) => {
+                    let depth = depth_map.get(&node_key).copied().unwrap_or(0);
+                    let indent = "  ".repeat(depth);
+                    output.push_str(&format!(
+                        "{}- {:?} => {:?}\n",
+                        indent, node_key, entry.value
+                    ));
+                    TraversalControl::Continue
+                }
+                _ => TraversalControl::Continue,
             }
-        }
+        };
+
+        self.traverse_tree(TraversalStrategy::DepthFirst, visitor);
+        output
     }
 
-    /// Helper function to insert an item into the right position in a list
-    /// Returns true if any modifications were made
-    fn insert_into_list(
-        &mut self,
-        list: &mut Vec<PageData<MSTKey, Value>>,
-        item_key: MSTKey,
-        item_value: Value,
-        level: u32,
-    ) -> bool {
-        for i in 0..list.len() {
-            // Check if the item belongs before this entry
-            if i < list.len() - 1
-                && Value::compare_keys(&item_key, &list[i + 1].key) == Ordering::Less
+    /// Debug function to export the tree structure as a GraphViz digraph
+    ///
+    /// Each page becomes a node labeled with its level and the keys of its entries,
+    /// with edges to its `low` child and to the `next` child of each entry (labeled
+    /// with that entry's key). Pages and keys are shown by the hex of their first
+    /// four bytes. Pages are visited in the same order as `dump`; a page reachable
+    /// from several places is emitted once.
+    ///
+    /// # Returns
+    ///
+    /// The tree in DOT syntax, e.g. for `dot -Tsvg`
+    pub fn to_dot(&self) -> String {
+        let mut output = String::from("digraph MST {\n    node [shape=box];\n");
+        let mut emitted = HashSet::new();
+
+        let visitor = |event: TraversalEvent<H::Key, Value>| -> TraversalControl {
+            if let TraversalEvent::VisitNode(node_key, page) = event
+                && emitted.insert(node_key)
             {
-                // Item belongs between entries i and i+1
-                let next_key = list[i].next;
-                let (new_next_key, next_modified) = match next_key {
-                    Some(key) => self.insert_at(key, item_key, item_value, level),
-                    None => {
-                        // Create a new page for the item
-                        let new_page = Page {
-                            level,
-                            low: None,
-                            list: vec![PageData {
-                                key: item_key,
-                                value: item_value,
-                                next: None,
-                            }],
-                        };
-                        let new_key = hash_page(&new_page);
-                        self.store.put(new_key, new_page);
-                        (new_key, true)
-                    }
-                };
+                let id = short_hex(node_key.as_ref());
+                let keys: Vec<String> = page
+                    .list
+                    .iter()
+                    .map(|entry| short_hex(entry.key.as_ref()))
+                    .collect();
+                output.push_str(&format!(
+                    "    \"{}\" [label=\"level {}\\n{}\"];\n",
+                    id,
+                    page.level,
+                    keys.join(" ")
+                ));
 
-                // Only update the list if the child was modified
-                if next_modified {
-                    list[i].next = Some(new_next_key);
-                    return true;
-                } else {
-                    return false;
+                if let Some(low) = page.low {
+                    output.push_str(&format!(
+                        "    \"{}\" -> \"{}\" [label=\"low\"];\n",
+                        id,
+                        short_hex(low.as_ref())
+                    ));
+                }
+                for (entry, key) in page.list.iter().zip(&keys) {
+                    if let Some(next) = entry.next {
+                        output.push_str(&format!(
+                            "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                            id,
+                            short_hex(next.as_ref()),
+                            key
+                        ));
+                    }
                 }
             }
-        }
-
-        // If we get here, the item belongs after the last entry
-        let last_idx = list.len() - 1;
-        let next_key = list[last_idx].next;
-        let (new_next_key, next_modified) = match next_key {
-            Some(key) => self.insert_at(key, item_key, item_value, level),
-            None => {
-                // Create a new page for the item
-                let new_page = Page {
-                    level,
-                    low: None,
-                    list: vec![PageData {
-                        key: item_key,
-                        value: item_value,
-                        next: None,
-                    }],
-                };
-                let new_key = hash_page(&new_page);
-                self.store.put(new_key, new_page);
-                (new_key, true)
-            }
+            TraversalControl::Continue
         };
 
-        // Only update the list if the child was modified
-        if next_modified {
-            list[last_idx].next = Some(new_next_key);
-            return true;
-        }
-
-        false
+        self.traverse_tree(TraversalStrategy::DepthFirst, visitor);
+        output.push_str("}\n");
+        output
     }
 
-    /// Helper function to insert a key-value pair after the first entry in a list
-    fn insert_after_first(
+    /// Creates and stores a page, returning its key
+    fn create_and_store_page(
         &mut self,
-        entries: &[PageData<MSTKey, Value>],
-        item_key: MSTKey,
-        item_value: Value,
-    ) -> Vec<PageData<MSTKey, Value>> {
-        if entries.is_empty() {
-            return Vec::new();
+        level: u32,
+        low: Option<H::Key>,
+        entries: impl IntoIterator<Item = PageData<H::Key, Value>>,
+    ) -> H::Key {
+        let list = entries.into_iter().collect();
+        self.store_page(Page {
+            level,
+            subtree_size: 0,
+            low,
+            list,
+        })
+    }
+
+    /// Fills in the page's cached subtree size from its children, then hashes and
+    /// stores it, returning its key. Children must already be in the store.
+    fn store_page(&mut self, mut page: Page<H::Key, Value>) -> H::Key {
+        page.subtree_size = page.list.len() as u64
+            + self.subtree_size(page.low)
+            + page
+                .list
+                .iter()
+                .map(|entry| self.subtree_size(entry.next))
+                .sum::<u64>();
+        let key = hash_page_with::<H, _, _>(&page);
+        if let Some(observer) = &mut self.observer {
+            if let Some(created) = &mut observer.created {
+                created.insert(key);
+            }
+            (observer.callback)(MstEvent::PageCreated {
+                key,
+                level: page.level,
+            });
         }
+        self.store.put(key, page);
+        key
+    }
 
-        let mut result_entries = Vec::with_capacity(entries.len() + 1);
-        let mut current_idx = 0;
+    /// Walks the tree with a custom visitor
+    ///
+    /// `visitor` is called with a `TraversalEvent` for every page and entry reached,
+    /// in the order given by `strategy`, and steers the walk with the
+    /// `TraversalControl` it returns:
+    /// - `Continue` goes on normally
+    /// - `Skip` on a page skips its entries and children, on an entry skips the
+    ///   subtree after it (its `next`)
+    /// - `Return(r)` stops the whole traversal at once
+    ///
+    /// Traversal does not recurse, so deep trees cannot overflow the stack, and a
+    /// page already on the path from the root is not entered again, so cycles in a
+    /// corrupted store end. A page missing from the store is skipped.
+    ///
+    /// # Returns
+    ///
+    /// The value passed to `Return`, or None if the traversal ran to the end
+    ///
+    /// # Example
+    /// ```
+    /// use mst::{MST, MSTKey, TraversalControl, TraversalEvent, TraversalStrategy};
+    /// use mst::test_utils::TestValue;
+    ///
+    /// let mut mst: MST<TestValue> = MST::new();
+    /// for byte in [1u8, 2, 3] {
+    ///     let key = MSTKey::clone_from_slice(&[byte; 32]);
+    ///     mst.insert(key, TestValue { key, data: [byte; 4] });
+    /// }
+    ///
+    /// // The first entry in key order with an even first data byte
+    /// let found = mst.traverse(TraversalStrategy::MSTOrder, |event| match event {
+    ///     TraversalEvent::VisitEntry(_, entry) if entry.value.data[0] % 2 == 0 => {
+    ///         TraversalControl::Return(entry.key)
+    ///     }
+    ///     _ => TraversalControl::Continue,
+    /// });
+    /// assert_eq!(found, Some(MSTKey::clone_from_slice(&[2; 32])));
+    /// ```
+    pub fn traverse<F, R>(&self, strategy: TraversalStrategy, visitor: F) -> Option<R>
+    where
+        F: FnMut(TraversalEvent<H::Key, Value>) -> TraversalControl<R>,
+    {
+        self.traverse_tree(strategy, visitor)
+    }
 
-        while current_idx < entries.len() {
-            let entry = &entries[current_idx];
+    /// General-purpose tree traversal method that can be used by multiple functions
+    fn traverse_tree<F, R>(&self, strategy: TraversalStrategy, mut visitor: F) -> Option<R>
+    where
+        F: FnMut(TraversalEvent<H::Key, Value>) -> TraversalControl<R>,
+    {
+        // Start from root
+        let start_key = self.root;
+        // Pages between the root and the current page, to break cycles. Identical
+        // subtrees in different positions are each traversed.
+        let mut path = HashSet::new();
 
-            match Value::compare_keys(&entry.key, &item_key) {
-                Ordering::Equal => {
-                    // Key already exists - merge values
-                    let merged_value = entry.value.merge(item_value);
-                    result_entries.push(PageData {
-                        key: entry.key,
-                        value: merged_value,
-                        next: entry.next,
-                    });
-                    // Append the rest of the entries
-                    for i in (current_idx + 1)..entries.len() {
-                        result_entries.push(entries[i].clone());
-                    }
+        // Choose traversal strategy
+        let flow = match strategy {
+            TraversalStrategy::DepthFirst => {
+                self.depth_first_traverse(start_key, &mut visitor, &mut path)
+            }
+            TraversalStrategy::MSTOrder => {
+                self.mst_order_traverse(start_key, &mut visitor, &mut path)
+            }
+        };
+        match flow {
+            ControlFlow::Break(value) => Some(value),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Pre-order traversal: each page is visited before its children.
+    ///
+    /// Uses an explicit stack of `TraversalFrame`s rather than recursion, so deep
+    /// trees cannot overflow the call stack. A frame is the equivalent of one
+    /// recursive call, so `Skip` only ends the page it was returned for, and the
+    /// traversal resumes with the parent page.
+    fn depth_first_traverse<F, R>(
+        &self,
+        start: Option<H::Key>,
+        visitor: &mut F,
+        path: &mut HashSet<H::Key>,
+    ) -> ControlFlow<R>
+    where
+        F: FnMut(TraversalEvent<H::Key, Value>) -> TraversalControl<R>,
+    {
+        let mut stack = Vec::new();
+        let mut pending = start;
+
+        loop {
+            // Enter the pending page and its chain of low children
+            while let Some(key) = pending.take() {
+                let Some(page) = self.enter_page(key, path) else {
                     break;
-                }
-                Ordering::Less => {
-                    if current_idx == entries.len() - 1
-                        || Value::compare_keys(&item_key, &entries[current_idx + 1].key)
-                            == Ordering::Less
-                    {
-                        // Insert between current entry and next entry
-                        let (left_subtree, right_subtree) = self.split(entry.next, item_key);
-                        result_entries.push(PageData {
-                            key: entry.key,
-                            value: entry.value.clone(),
-                            next: left_subtree,
-                        });
-                        result_entries.push(PageData {
-                            key: item_key,
-                            value: item_value,
-                            next: right_subtree,
-                        });
-                        // Append the rest
-                        for i in (current_idx + 1)..entries.len() {
-                            result_entries.push(entries[i].clone());
+                };
+                match visitor(TraversalEvent::VisitNode(key, page)) {
+                    TraversalControl::Return(value) => return ControlFlow::Break(value),
+                    TraversalControl::Skip => {
+                        if let TraversalControl::Return(value) =
+                            visitor(TraversalEvent::ExitNode(key))
+                        {
+                            return ControlFlow::Break(value);
                         }
                         break;
-                    } else {
-                        // Not the right spot yet, keep current entry and continue
-                        result_entries.push(entries[current_idx].clone());
-                        current_idx += 1;
                     }
+                    TraversalControl::Continue => {}
                 }
-                Ordering::Greater => {
-                    // This should never happen in insert_after_first
-                    panic!("Unexpected order in insert_after_first");
+                path.insert(key);
+                stack.push(TraversalFrame {
+                    key,
+                    page,
+                    next_entry: Some(0),
+                });
+                pending = page.low;
+            }
+
+            if stack.is_empty() {
+                return ControlFlow::Continue(());
+            }
+            pending = Self::advance_frame(&mut stack, visitor, path)?;
+        }
+    }
+
+    /// Specific traversal for MST-ordered values that preserves the sorted order of keys.
+    ///
+    /// Unlike traditional tree traversals, MST Order follows the specific Merkle Search Tree
+    /// structure to visit nodes in strict key order:
+    ///
+    /// ```text
+    ///        [Entry1, Entry2, Entry3]
+    ///        /       |       |      \
+    ///     Low     Next1    Next2   Next3
+    /// ```
+    ///
+    /// Where the traversal visits regions in this sequence:
+    /// - Low: all keys < Entry1
+    /// - Entry1
+    /// - Next1: keys between Entry1 and Entry2
+    /// - Entry2
+    /// - Next2: keys between Entry2 and Entry3
+    /// - Entry3
+    /// - Next3: all keys > Entry3
+    ///
+    /// This ensures keys are visited in strictly ascending order - a fundamental
+    /// requirement for many MST operations.
+    fn mst_order_traverse<F, R>(
+        &self,
+        start: Option<H::Key>,
+        visitor: &mut F,
+        path: &mut HashSet<H::Key>,
+    ) -> ControlFlow<R>
+    where
+        F: FnMut(TraversalEvent<H::Key, Value>) -> TraversalControl<R>,
+    {
+        // Same explicit stack as `depth_first_traverse`, but a page is only
+        // visited once its low subtree is done
+        let mut stack = Vec::new();
+        let mut pending = start;
+
+        loop {
+            // Enter the pending page and its chain of low children
+            while let Some(key) = pending.take() {
+                let Some(page) = self.enter_page(key, path) else {
+                    break;
+                };
+                path.insert(key);
+                stack.push(TraversalFrame {
+                    key,
+                    page,
+                    next_entry: None,
+                });
+                pending = page.low;
+            }
+
+            let Some(frame) = stack.last_mut() else {
+                return ControlFlow::Continue(());
+            };
+
+            if frame.next_entry.is_none() {
+                // The low subtree is done, visit the node itself
+                match visitor(TraversalEvent::VisitNode(frame.key, frame.page)) {
+                    TraversalControl::Return(value) => return ControlFlow::Break(value),
+                    TraversalControl::Skip => {
+                        // Jump past the last entry, so the page is exited next
+                        frame.next_entry = Some(frame.page.list.len());
+                    }
+                    TraversalControl::Continue => frame.next_entry = Some(0),
                 }
             }
+
+            pending = Self::advance_frame(&mut stack, visitor, path)?;
+        }
+    }
+
+    /// Loads a page to traverse, as the start of a recursive call would.
+    /// Returns None for a page already on the path (a cycle) or a missing page.
+    fn enter_page(&self, key: H::Key, path: &HashSet<H::Key>) -> Option<&Page<H::Key, Value>> {
+        if path.contains(&key) {
+            return None;
+        }
+        self.get(key)
+    }
+
+    /// Finishes the page on top of the stack, taking it off the path
+    fn pop_frame(stack: &mut Vec<TraversalFrame<'_, H::Key, Value>>, path: &mut HashSet<H::Key>) {
+        if let Some(frame) = stack.pop() {
+            path.remove(&frame.key);
+        }
+    }
+
+    /// Visits the next entry of the frame on top of the stack, or exits its page
+    /// once all entries are done. Continues with the entry's `next` subtree to
+    /// enter, or breaks if the visitor returned early.
+    fn advance_frame<F, R>(
+        stack: &mut Vec<TraversalFrame<'_, H::Key, Value>>,
+        visitor: &mut F,
+        path: &mut HashSet<H::Key>,
+    ) -> ControlFlow<R, Option<H::Key>>
+    where
+        F: FnMut(TraversalEvent<H::Key, Value>) -> TraversalControl<R>,
+    {
+        let Some(frame) = stack.last_mut() else {
+            return ControlFlow::Continue(None);
+        };
+        let index = frame.next_entry.unwrap_or(0);
+        let Some(entry) = frame.page.list.get(index) else {
+            let key = frame.key;
+            Self::pop_frame(stack, path);
+            return match visitor(TraversalEvent::ExitNode(key)) {
+                TraversalControl::Return(value) => ControlFlow::Break(value),
+                _ => ControlFlow::Continue(None),
+            };
+        };
+
+        frame.next_entry = Some(index + 1);
+        match visitor(TraversalEvent::VisitEntry(frame.key, entry)) {
+            TraversalControl::Return(value) => ControlFlow::Break(value),
+            TraversalControl::Skip => ControlFlow::Continue(None),
+            TraversalControl::Continue => ControlFlow::Continue(entry.next),
         }
+    }
+}
+
+impl<Value, H, S> MST<Value, H, S>
+where
+    Value: Hash + std::fmt::Debug + Reference<Key = H::Key> + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// Get a reference to the value stored under a key
+    ///
+    /// Unlike `get_value`, this does not copy the value out of the store, so it is
+    /// available for any value type. The search stops at the first matching entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `search_key`: The key to search for
+    ///
+    /// # Returns
+    ///
+    /// Option containing a reference to the value if found, None otherwise
+    pub fn get_value_ref(&self, search_key: H::Key) -> Option<&Value> {
+        let (page_key, index) = self.locate(search_key)?;
+        Some(&self.store.get(page_key)?.list[index].value)
+    }
+
+    /// Finds where a key is stored
+    ///
+    /// Follows the same path as `get_value`, but stops at the entry rather than
+    /// reading its value, e.g. to prefetch or inspect the page. Unlike `get_value`,
+    /// a tombstone is located like any other entry.
+    ///
+    /// # Returns
+    ///
+    /// The key of the page holding `key` and the entry's index in its `list`, or
+    /// None if the key is absent
+    pub fn locate(&self, search_key: H::Key) -> Option<(H::Key, usize)> {
+        // Start from the root
+        self.locate_from_node(self.root?, search_key)
+    }
+
+    /// Returns the entry with the smallest key, following `low` pointers down
+    /// from the root in O(depth)
+    pub fn first(&self) -> Option<&PageData<H::Key, Value>> {
+        self.edge_entry(self.root, false)
+    }
+
+    /// Returns the entry with the largest key, following the last `next` pointer
+    /// down from the root in O(depth)
+    pub fn last(&self) -> Option<&PageData<H::Key, Value>> {
+        self.edge_entry(self.root, true)
+    }
+
+    /// Returns the smallest key in the tree, found like `first`
+    ///
+    /// Together with `max_key` this gives the key range a tree covers in
+    /// O(depth), e.g. for a shard.
+    pub fn min_key(&self) -> Option<H::Key> {
+        self.first().map(|entry| entry.key)
+    }
 
-        result_entries
+    /// Returns the largest key in the tree, found like `last`
+    pub fn max_key(&self) -> Option<H::Key> {
+        self.last().map(|entry| entry.key)
+    }
+
+    /// Compares two keys in the order the tree keeps them
+    ///
+    /// This is the `KeyComparable` order of `Value` that `insert`, `split_at` and
+    /// every lookup use, without having to import the trait or name the value
+    /// type, e.g. to place range boundaries outside the tree.
+    pub fn compare_keys(a: &H::Key, b: &H::Key) -> Ordering {
+        Value::compare_keys(a, b)
+    }
+
+    /// Finds the entry with the largest key `<= key`
+    ///
+    /// # Returns
+    ///
+    /// The entry, or None if every key in the tree is greater than `key`
+    pub fn floor(&self, key: H::Key) -> Option<&PageData<H::Key, Value>> {
+        self.neighbours(key).0
     }
 
-    /// Splits the tree into two parts at the given key.
+    /// Finds the entry with the smallest key `>= key`
     ///
-    /// This operation divides the tree into two separate subtrees:
-    /// - Left subtree: Contains all keys strictly less than split_key
-    /// - Right subtree: Contains all keys greater than or equal to split_key
+    /// # Returns
     ///
-    /// # Arguments
-    /// * `node_key_opt`: Optional key to the node where splitting starts
-    /// * `split_key`: The key value at which to split the tree
+    /// The entry, or None if every key in the tree is smaller than `key`
+    pub fn ceiling(&self, key: H::Key) -> Option<&PageData<H::Key, Value>> {
+        self.neighbours(key).1
+    }
+
+    /// Returns the entry at position `n` (zero-based) in key order
+    ///
+    /// Uses the cached subtree sizes to skip whole subtrees, so this is O(depth).
     ///
     /// # Returns
-    /// A tuple of (left_subtree_key, right_subtree_key), both optional
-    fn split(
-        &mut self,
-        node_key_opt: Option<MSTKey>,
-        split_key: MSTKey,
-    ) -> (Option<MSTKey>, Option<MSTKey>) {
-        // Early return for empty or default trees - nothing to split
-        if node_key_opt.is_none() || node_key_opt == Some(MSTKey::default()) {
-            return (None, None);
-        }
+    ///
+    /// The entry, or None if the tree has `n` or fewer entries
+    pub fn select(&self, n: usize) -> Option<&PageData<H::Key, Value>> {
+        let mut remaining = n as u64;
+        let mut descent = Descent::new(self.root);
 
-        let node_key = node_key_opt.unwrap();
-        let current_page = match self.store.get(node_key).cloned() {
-            Some(page) => page,
-            None => return (None, None),
-        };
+        while let Some(current) = descent.next_key() {
+            // Either the entry lies under this page, or it is past the end of the tree
+            let page = self.store.get(current)?;
+            if !descent.enter(page) || remaining >= page.subtree_size {
+                return None;
+            }
 
-        // Remove the current page as we'll be creating new pages with its content
-        self.store.remove(node_key);
+            let mut child = page.low;
+            for entry in &page.list {
+                let child_size = self.subtree_size(child);
+                if remaining < child_size {
+                    break;
+                }
+                remaining -= child_size;
+                if remaining == 0 {
+                    return Some(entry);
+                }
+                remaining -= 1;
+                child = entry.next;
+            }
+            descent.follow(child);
+        }
 
-        let level = current_page.level;
-        let low_child = current_page.low;
-        let entries = current_page.list;
+        None
+    }
 
-        // If page has no entries, the left result is just the low branch
-        if entries.is_empty() {
-            return (low_child, None);
+    /// Counts the entries whose key is strictly smaller than `key`
+    ///
+    /// For a key in the tree this is its position in key order, the inverse of
+    /// `select`. Uses the cached subtree sizes, so this is O(depth).
+    pub fn rank(&self, key: H::Key) -> usize {
+        let mut rank = 0;
+        let mut descent = Descent::new(self.root);
+
+        while let Some(page) = descent.next_key().and_then(|key| self.store.get(key)) {
+            let Some(position) = descent.step(page, &key) else {
+                break;
+            };
+            // Every entry before the position counts, with the branch below it
+            let (Ok(index) | Err(index)) = position;
+            rank += index as u64;
+            rank += (0..index)
+                .map(|i| self.subtree_size(branch(page, i)))
+                .sum::<u64>();
+            if position.is_ok() {
+                return (rank + self.subtree_size(branch(page, index))) as usize;
+            }
         }
 
-        let first_entry = &entries[0];
+        rank as usize
+    }
 
-        // Compare split key with first entry to determine how to split
-        match Value::compare_keys(&split_key, &first_entry.key) {
-            Ordering::Less => {
-                // Split key is less than first entry - need to split the low branch
-                // and move all entries to the right subtree
-                let (lowlow, lowhi) = self.split(low_child, split_key);
+    /// Counts the entries with `start <= key < end`, the same ones `range` yields
+    ///
+    /// Computed from two `rank` lookups, so this is O(depth) however many entries
+    /// the range holds. A range with `start` after `end` is empty.
+    pub fn count_range(&self, start: H::Key, end: H::Key) -> usize {
+        self.rank(end).saturating_sub(self.rank(start))
+    }
 
-                // Create right page with all the original entries
-                let right_page_key = self.create_and_store_page(level, lowhi, entries.clone());
+    /// Returns the pages visited when searching for `key`, from the root down
+    ///
+    /// The last page is the one holding `key`, or where the search ran out of
+    /// children if `key` is absent. Each page is paired with its key, so a caller
+    /// can check the links between them as `verify_proof` does.
+    ///
+    /// # Returns
+    ///
+    /// The path, empty for an empty tree. It stops early at a page missing from
+    /// the store.
+    pub fn get_path(&self, key: H::Key) -> PagePath<'_, H::Key, Value> {
+        let mut path = Vec::new();
+        let mut descent = Descent::new(self.root);
 
-                (lowlow, Some(right_page_key))
+        while let Some(page_key) = descent.next_key() {
+            let Some(page) = self.store.get(page_key) else {
+                break;
+            };
+            if descent.step(page, &key).is_none() {
+                break;
             }
-            _ => {
-                // Split key is greater than or equal to first entry
-                // We'll process entries one by one to determine where the split occurs
-                let mut left_entries = Vec::new();
-                let mut right_result = None;
+            path.push((page_key, page));
+        }
 
-                // Process entries
-                let mut i = 0;
-                while i < entries.len() {
-                    let entry = &entries[i];
+        path
+    }
 
-                    if i < entries.len() - 1
-                        && Value::compare_keys(&split_key, &entries[i + 1].key) == Ordering::Less
-                    {
-                        // We found the split point: between current entry and next entry
-                        // Current entry goes to left, entries after it go to right
-                        let (next_left, next_right) = self.split(entry.next, split_key);
+    /// Returns the number of pages on the longest path from the root to a leaf page
+    ///
+    /// This counts pointer hops rather than page levels, since levels can be
+    /// skipped. Walks every page of the tree, so it is O(tree); a page reachable
+    /// more than once is only descended into the first time.
+    ///
+    /// # Returns
+    ///
+    /// The height, 0 for an empty tree
+    pub fn height(&self) -> u32 {
+        let mut height = 0;
+        let mut visited = HashSet::new();
+        let mut stack = Vec::from_iter(self.root.map(|root| (root, 1u32)));
 
-                        // Add current entry to the left part with the proper next pointer
-                        left_entries.push(PageData {
-                            key: entry.key,
-                            value: entry.value,
-                            next: next_left,
-                        });
+        while let Some((key, depth)) = stack.pop() {
+            let Some(page) = self.store.get(key) else {
+                continue;
+            };
+            if !visited.insert(key) {
+                continue;
+            }
+            height = height.max(depth);
+            let children = page
+                .low
+                .into_iter()
+                .chain(page.list.iter().filter_map(|e| e.next));
+            stack.extend(children.map(|child| (child, depth + 1)));
+        }
 
-                        // Create right page with all remaining entries
-                        let mut right_entries = Vec::new();
-                        for j in i + 1..entries.len() {
-                            right_entries.push(entries[j].clone());
-                        }
+        height
+    }
 
-                        let right_page_key =
-                            self.create_and_store_page(level, next_right, right_entries);
+    /// Returns a lazy iterator over the keys of every page reachable from the root
+    ///
+    /// Pages are found depth-first through `Reference::refs`, parents before their
+    /// children. Each key is yielded once even if it is reachable from several
+    /// places, so a store containing a cycle cannot make the walk loop. Keys with
+    /// no page in the store are skipped, like in `PageStore::reachable_set`. The
+    /// result can be fed to `PageStore::collect_pages` or used to serialize a tree.
+    pub fn page_keys(&self) -> impl Iterator<Item = H::Key> + '_ {
+        let mut visited = HashSet::new();
+        let mut stack = Vec::from_iter(self.root);
 
-                        right_result = Some(right_page_key);
-                        break;
-                    }
+        std::iter::from_fn(move || {
+            while let Some(key) = stack.pop() {
+                let Some(page) = self.store.get(key) else {
+                    continue;
+                };
+                if !visited.insert(key) {
+                    continue;
+                }
+                // Push in reverse so children are visited in key order
+                stack.extend(page.refs().into_iter().rev());
+                return Some(key);
+            }
+            None
+        })
+    }
 
-                    if i == entries.len() - 1 {
-                        // We've reached the last entry - need to split its next branch
-                        let (next_left, next_right) = self.split(entry.next, split_key);
+    /// Collects the entries of every reachable page at exactly `level`, in
+    /// ascending key order
+    ///
+    /// A diagnostic for how `calc_level` spreads keys over the levels. Walks
+    /// every page of the tree, so it is O(tree).
+    ///
+    /// # Returns
+    ///
+    /// The entries, empty if no page is at `level`
+    pub fn entries_at_level(&self, level: u32) -> Vec<&PageData<H::Key, Value>> {
+        self.page_keys()
+            .filter_map(|key| self.store.get(key))
+            .filter(|page| page.level == level)
+            .flat_map(|page| &page.list)
+            .collect()
+    }
 
-                        // Add the last entry to the left subtree
-                        left_entries.push(PageData {
-                            key: entry.key,
-                            value: entry.value,
-                            next: next_left,
-                        });
+    /// Returns the number of pages traversed from the root to reach `key`
+    ///
+    /// # Returns
+    ///
+    /// The depth, 1 for a key in the root page, or None if the key is absent
+    pub fn depth_of(&self, key: H::Key) -> Option<usize> {
+        let path = self.get_path(key);
+        let (_, page) = path.last()?;
+        page.list
+            .iter()
+            .any(|entry| Value::compare_keys(&entry.key, &key) == Ordering::Equal)
+            .then_some(path.len())
+    }
 
-                        right_result = next_right;
-                        break;
-                    }
+    /// Descends towards `key`, keeping the closest entries seen on either side.
+    /// Each deeper page lies between the best candidates so far, so its entries
+    /// can only be closer. An exact match is returned on both sides.
+    fn neighbours(&self, key: H::Key) -> Neighbours<'_, H::Key, Value> {
+        let mut below = None;
+        let mut above = None;
+        let mut descent = Descent::new(self.root);
 
-                    // Current entry belongs fully in the left part, continue to next
-                    left_entries.push(entry.clone());
-                    i += 1;
+        while let Some(page) = descent.next_key().and_then(|key| self.store.get(key)) {
+            match descent.step(page, &key) {
+                Some(Ok(index)) => return (Some(&page.list[index]), Some(&page.list[index])),
+                Some(Err(index)) => {
+                    if let Some(entry) = index.checked_sub(1).map(|i| &page.list[i]) {
+                        below = Some(entry);
+                    }
+                    if let Some(entry) = page.list.get(index) {
+                        above = Some(entry);
+                    }
                 }
+                None => break,
+            }
+        }
 
-                // Create left page
-                let left_page = Page {
-                    level,
-                    low: low_child,
-                    list: left_entries,
-                };
+        (below, above)
+    }
 
-                let left_page_key = hash_page(&left_page);
-                self.store.put(left_page_key, left_page);
+    /// Cached number of entries in the subtree under `key`, 0 if it is absent
+    fn subtree_size(&self, key: Option<H::Key>) -> u64 {
+        key.and_then(|key| self.store.get(key))
+            .map_or(0, |page| page.subtree_size)
+    }
 
-                (Some(left_page_key), right_result)
+    /// The smallest entry of the subtree under `root`, or the largest if `last`,
+    /// found by following the outermost pointers down in O(depth)
+    fn edge_entry(&self, root: Option<H::Key>, last: bool) -> Option<&PageData<H::Key, Value>> {
+        let mut descent = Descent::new(root);
+        let mut deepest = None;
+        while let Some(page) = descent.next_key().and_then(|key| self.store.get(key)) {
+            if !descent.enter(page) {
+                break;
             }
+            deepest = Some(page);
+            descent.follow(branch(page, if last { page.list.len() } else { 0 }));
         }
+        let list = &deepest?.list;
+        if last { list.last() } else { list.first() }
     }
 
-    /// Merges this MST with another MST, combining their contents.
-    ///
-    /// This operation creates a new tree that contains all items from both trees,
-    /// properly handling duplicate keys by using the Merge trait to combine values.
-    /// The merge operation preserves the cryptographic properties of both trees.
-    ///
-    /// # Example
-    /// ```
-    /// use mst::{MST, MSTKey};
-    /// use mst::test_utils::TestValue;
-    ///
-    /// let mut mst1: MST<TestValue> = MST::new();
-    /// let mst2: MST<TestValue> = MST::new();
-    /// let (merged_root, merged_store) = mst1.merge(&mst2);
-    /// ```
-    pub fn merge(&mut self, other: &Self) -> (MSTKey, Store<MSTKey, Page<MSTKey, Value>>) {
-        // Create a new empty MST
-        let mut new_mst = MST::new();
+    /// Helper function to search for an entry starting from a specific node,
+    /// guarded against cycles by `Descent`
+    fn locate_from_node(&self, node_key: H::Key, search_key: H::Key) -> Option<(H::Key, usize)> {
+        let mut descent = Descent::new(Some(node_key));
 
-        // Add all items from both trees directly, with proper merging
-        if self.root != MSTKey::default() {
-            self.add_items_to_mst(&mut new_mst);
+        while let Some(current) = descent.next_key() {
+            let page = self.store.get(current)?;
+            if let Ok(index) = descent.step(page, &search_key)? {
+                return Some((current, index));
+            }
         }
 
-        if other.root != MSTKey::default() {
-            other.add_items_to_mst(&mut new_mst);
-        }
+        None
+    }
+}
 
-        (new_mst.root, new_mst.store)
+/// An empty SHA-256 tree, the same as `MST::new`
+impl<
+    Value: AsRef<[u8]>
+        + Hash
+        + Reference<Key = MSTKey>
+        + Clone
+        + std::fmt::Debug
+        + Merge
+        + KeyComparable<Key = MSTKey>,
+> Default for MST<Value>
+{
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Helper function to add all items from this MST to another MST
-    fn add_items_to_mst(&self, target: &mut MST<Value>) {
-        if self.root == MSTKey::default() {
-            return;
+/// Cloning copies the root and the whole store, so the clone is fully independent.
+///
+/// Pages are content-addressed and never modified in place, so a store sharing its
+/// pages through `Arc` could make this cheap in the future.
+impl<Value, H, S> Clone for MST<Value, H, S>
+where
+    Value: Hash + std::fmt::Debug + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root,
+            store: self.store.clone(),
+            len: self.len,
+            level_base: self.level_base,
+            level_mapper: self.level_mapper,
+            merge_policy: self.merge_policy,
+            observer: None,
+            marker: PhantomData,
         }
+    }
+}
 
-        let visitor = |event: TraversalEvent<MSTKey, Value>| {
-            if let TraversalEvent::VisitEntry(_, entry) = event {
-                target.insert(entry.key, entry.value);
-            }
-            TraversalControl::Continue
-        };
+/// Formats the tree as the indented structure returned by `dump`
+///
+/// An empty tree formats as an empty string.
+impl<Value, H, S> std::fmt::Display for MST<Value, H, S>
+where
+    Value: AsRef<[u8]>
+        + Hash
+        + Reference<Key = H::Key>
+        + Clone
+        + std::fmt::Debug
+        + Merge
+        + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.dump())
+    }
+}
 
-        // Use MST-specific traversal order
-        self.traverse_tree(TraversalStrategy::MSTOrder, visitor);
+/// Consumes the tree, yielding its entries in ascending key order
+///
+/// Each page is removed from the store as the walk reaches it, so values are
+/// moved out rather than copied. Pages not reachable from the root are dropped
+/// with the store when the iterator is.
+impl<Value, H, S> IntoIterator for MST<Value, H, S>
+where
+    Value: Hash + std::fmt::Debug + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    type Item = (H::Key, Value);
+    type IntoIter = IntoIter<Value, H, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut iter = IntoIter {
+            store: self.store,
+            stack: Vec::new(),
+        };
+        iter.descend(self.root);
+        iter
     }
+}
 
-    /// Get a specific value by key from the tree
-    ///
-    /// # Arguments
-    ///
-    /// * `search_key`: The key to search for
+/// Defines different traversal strategies for navigating the tree structure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraversalStrategy {
+    /// Depth-first traversal visits nodes before their children, providing a
+    /// comprehensive view of the tree structure in pre-order
+    DepthFirst,
+
+    /// MST Order traverses the tree in key-sorted order, essential for operations
+    /// that need to process keys sequentially.
     ///
-    /// # Returns
+    /// The traversal sequence follows the MST structure:
+    /// 1. Visit low child (keys < first entry)
+    /// 2. Visit the node itself
+    /// 3. For each entry in order:
+    ///    a. Visit the entry
+    ///    b. Visit entry's "next" branch (keys between current and next entry)
+    ///    c. Continue to next entry
     ///
-    /// Option containing the value if found, None otherwise
-    pub fn get_value(&self, search_key: MSTKey) -> Option<Value> {
-        // Start from the root
-        self.get_value_from_node(self.root, search_key)
+    /// This ensures we visit values in strictly ascending key order, which is
+    /// critical for operations like to_list() and merging.
+    MSTOrder,
+}
+
+/// Events that occur during traversal, as passed to the visitor of `MST::traverse`
+pub enum TraversalEvent<'a, K: Hash, V: Hash> {
+    /// A page is reached, with its key
+    VisitNode(K, &'a Page<K, V>),
+    /// An entry is reached, with the key of the page holding it
+    VisitEntry(K, &'a PageData<K, V>),
+    /// All entries and children of the page with this key are done
+    ExitNode(K),
+}
+
+/// A structural change made by a tree, as passed to the observer registered with
+/// `MST::set_observer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MstEvent<K> {
+    /// A page was hashed and stored under this key, at this level
+    PageCreated { key: K, level: u32 },
+    /// An insert or remove left the page with this key unreachable from the root
+    PageOrphaned(K),
+    /// A subtree was split into the keys below `at` and those at or above it
+    Split { at: K },
+}
+
+/// An observer registered with `MST::set_observer`
+struct Observer<K> {
+    callback: Box<dyn FnMut(MstEvent<K>) + Send + Sync>,
+    /// Pages created by the insert or remove in progress, for `report_orphans`
+    created: Option<HashSet<K>>,
+}
+
+/// The closest entries below and above a key, as found by `MST::neighbours`
+type Neighbours<'a, K, V> = (Option<&'a PageData<K, V>>, Option<&'a PageData<K, V>>);
+
+/// A page whose traversal is in progress, standing in for a recursive call
+struct TraversalFrame<'a, K: Hash, V: Hash> {
+    key: K,
+    page: &'a Page<K, V>,
+    /// Index of the next entry to visit, None while the node itself is still to come
+    next_entry: Option<usize>,
+}
+
+/// Controls how traversal should proceed, as returned by the visitor of `MST::traverse`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraversalControl<R = ()> {
+    /// Go on normally
+    Continue,
+    /// Skip the children of the current page, or the subtree after the current entry
+    Skip,
+    /// Stop the traversal, making it return this value
+    Return(R),
+}
+
+/// A key of an MST with its current value, if any, as returned by `MST::entry`
+pub struct Entry<'a, Value, H = Sha256, S = TreeStore<Value, H>>
+where
+    Value: Hash + std::fmt::Debug + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    tree: &'a mut MST<Value, H, S>,
+    key: H::Key,
+    value: Option<Value>,
+}
+
+impl<Value, H, S> Entry<'_, Value, H, S>
+where
+    Value: AsRef<[u8]>
+        + Hash
+        + Reference<Key = H::Key>
+        + Clone
+        + std::fmt::Debug
+        + Merge
+        + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// The key this entry is for
+    pub fn key(&self) -> H::Key {
+        self.key
     }
 
-    /// Helper function to search for a value starting from a specific node
-    fn get_value_from_node(&self, node_key: MSTKey, search_key: MSTKey) -> Option<Value> {
-        // Return None for empty tree
-        if node_key == MSTKey::default() {
-            return None;
-        }
+    /// Returns the current value, first inserting `default` if the key is absent
+    pub fn or_insert(self, default: Value) -> Value {
+        self.or_insert_with(|| default)
+    }
 
-        // Get the page for this node
-        let page = match self.store.get(node_key) {
-            Some(p) => p,
-            None => return None,
-        };
+    /// Returns the current value, first inserting the result of `default` if the
+    /// key is absent. `default` is not called when the key is present.
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, default: F) -> Value {
+        match self.value {
+            Some(value) => value,
+            None => {
+                let value = default();
+                self.tree.insert(self.key, value.clone());
+                value
+            }
+        }
+    }
 
-        // Check low branch if list is empty
-        if page.list.is_empty() {
-            return match page.low {
-                Some(low_key) => self.get_value_from_node(low_key, search_key),
-                None => None,
-            };
+    /// Applies `f` to the current value, if any, and writes the result back
+    ///
+    /// The modified value replaces the current one rather than being merged
+    /// with it. Nothing happens when the key is absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `Value::compare_keys` is not a consistent total order, like `insert`.
+    pub fn and_modify<F: FnOnce(&mut Value)>(mut self, f: F) -> Self {
+        if let Some(value) = self.value.as_mut() {
+            f(value);
+            let modified = value.clone();
+            if let Err(err) = self
+                .tree
+                .insert_resolving(self.key, modified, &|_, new| new)
+            {
+                panic!("insert failed: {}", err);
+            }
         }
+        self
+    }
+}
 
-        // Process the list of entries
-        for i in 0..page.list.len() {
-            let entry = &page.list[i];
+/// A read-only view of a historical root in a tree's store, see `MST::view`
+///
+/// Pages missing from the store, for example because `gc` dropped them, read as
+/// empty subtrees, as they do for the tree itself.
+pub struct MstView<'a, Value, H = Sha256, S = TreeStore<Value, H>>
+where
+    Value: Hash,
+    H: MstHasher,
+{
+    store: &'a S,
+    root: Option<H::Key>,
+    marker: PhantomData<fn() -> Value>,
+}
 
-            match Value::compare_keys(&search_key, &entry.key) {
-                // Found the key
-                Ordering::Equal => return Some(entry.value),
+impl<'a, Value, H, S> MstView<'a, Value, H, S>
+where
+    Value: Hash + Reference<Key = H::Key> + KeyComparable<Key = H::Key> + Clone + Merge,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// Returns the root this view reads, or None for an empty tree
+    pub fn root(&self) -> Option<H::Key> {
+        self.root
+    }
 
-                // Search key is less than current entry, go to low branch
-                Ordering::Less => {
-                    if i == 0 {
-                        // If this is the first entry, check the low branch
-                        return match page.low {
-                            Some(low_key) => self.get_value_from_node(low_key, search_key),
-                            None => None,
-                        };
-                    } else {
-                        // Otherwise, check the previous entry's next branch
-                        return match page.list[i - 1].next {
-                            Some(next_key) => self.get_value_from_node(next_key, search_key),
-                            None => None,
-                        };
-                    }
-                }
+    /// Retrieves the value for a key as of this view's root, skipping tombstones
+    /// like `MST::get_value`
+    pub fn get_value(&self, search_key: H::Key) -> Option<Value> {
+        let mut descent = Descent::new(self.root);
 
-                // Search key is greater, continue to next entry or check this entry's next branch
-                Ordering::Greater => {
-                    if i == page.list.len() - 1 {
-                        // This is the last entry, check its next branch
-                        return match entry.next {
-                            Some(next_key) => self.get_value_from_node(next_key, search_key),
-                            None => None,
-                        };
-                    }
-                    // Otherwise continue to next entry
-                }
+        while let Some(page) = descent.next_key().and_then(|key| self.store.get(key)) {
+            if let Ok(index) = descent.step(page, &search_key)? {
+                let value = &page.list[index].value;
+                return (!value.is_tombstone()).then(|| value.clone());
             }
         }
 
-        // If we reach here, key wasn't found
         None
     }
 
-    /// Debug function to dump the tree structure
-    ///
-    /// # Returns
-    ///
-    /// A string representation of the tree
-    pub fn dump(&self) -> String {
-        if self.root == MSTKey::default() {
-            return String::new();
-        }
-
-        let mut output = String::new();
-        let mut depth_map = HashMap::new();
-        depth_map.insert(self.root, 0);
-
-        let visitor = |event: TraversalEvent<MSTKey, Value>| {
-            match event {
-                TraversalEvent::VisitNode(node_key, page) => {
-                    let depth = depth_map.get(&node_key).copied().unwrap_or(0);
-                    let indent = "  ".repeat(depth);
-                    output.push_str(&format!("{}{:?} ({})\n", indent, node_key, page.level));
+    /// Returns a lazy iterator over the entries of this view in ascending key order
+    pub fn iter(&self) -> Iter<'a, Value, H, S> {
+        Iter::new(self.store, self.root, false)
+    }
 
-                    // Store depths for children
-                    if let Some(low) = page.low {
-                        depth_map.insert(low, depth + 1);
-                    }
+    /// Collects the values of this view in ascending key order, without tombstones
+    pub fn to_list(&self) -> Vec<Value> {
+        self.iter()
+            .filter(|entry| !entry.value.is_tombstone())
+            .map(|entry| entry.value.clone())
+            .collect()
+    }
+}
 
-                    for entry in &page.list {
-                        if let Some(next) = entry.next {
-                            depth_map.insert(next, depth + 1);
-                        }
-                    }
+/// Lazy iterator over the entries of an MST in key order.
+///
+/// Keeps an explicit stack of `(page, position)` pairs instead of recursing,
+/// so iteration can be paused at any point and deep trees cannot overflow
+/// the call stack. The same machinery walks in both directions:
+/// - Ascending: the position is the index of the next entry to yield, and the
+///   entry's `next` subtree is visited right after it
+/// - Descending: the position counts the entries still to yield, and the
+///   subtree left of each entry (previous `next`, or `low`) is visited after it
+pub struct Iter<'a, Value: Hash, H: MstHasher = Sha256, S = TreeStore<Value, H>> {
+    store: &'a S,
+    stack: Vec<(&'a Page<H::Key, Value>, usize)>,
+    reverse: bool,
+    hasher: PhantomData<fn() -> H>,
+}
 
-                    TraversalControl::Continue
-                }
-                TraversalEvent::VisitEntry(node_key, entry) => {
-                    let depth = depth_map.get(&node_key).copied().unwrap_or(0);
-                    let indent = "  ".repeat(depth);
-                    output.push_str(&format!(
-                        "{}- {:?} => {:?}\n",
-                        indent, node_key, entry.value
-                    ));
-                    TraversalControl::Continue
-                }
-                _ => TraversalControl::Continue,
-            }
+impl<'a, Value, H, S> Iter<'a, Value, H, S>
+where
+    Value: Hash + Reference<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// Creates an iterator positioned at the smallest (or, when `reverse`
+    /// is set, the largest) key under `root`
+    fn new(store: &'a S, root: Option<H::Key>, reverse: bool) -> Self {
+        let mut iter = Self {
+            store,
+            stack: Vec::new(),
+            reverse,
+            hasher: PhantomData,
         };
-
-        self.traverse_tree(TraversalStrategy::DepthFirst, visitor);
-        output
+        iter.descend(root);
+        iter
     }
 
-    /// Creates and stores a page, returning its key
-    fn create_and_store_page(
-        &mut self,
-        level: u32,
-        low: Option<MSTKey>,
-        entries: impl IntoIterator<Item = PageData<MSTKey, Value>>,
-    ) -> MSTKey {
-        let list = entries.into_iter().collect();
-        let new_page = Page { level, low, list };
-        let new_page_key = hash_page(&new_page);
-        self.store.put(new_page_key, new_page);
-        new_page_key
+    /// Returns the child pointer left of entry `idx`: `low` for the first
+    /// entry, otherwise the previous entry's `next`
+    fn child(page: &Page<H::Key, Value>, idx: usize) -> Option<H::Key> {
+        match idx {
+            0 => page.low,
+            _ => page.list[idx - 1].next,
+        }
     }
 
-    /// General-purpose tree traversal method that can be used by multiple functions
-    fn traverse_tree<F>(&self, strategy: TraversalStrategy, mut visitor: F)
-    where
-        F: FnMut(TraversalEvent<MSTKey, Value>) -> TraversalControl<()>,
-    {
-        // Start from root
-        let start_key = self.root;
-        let mut visited = HashSet::new();
-
-        // Choose traversal strategy
-        match strategy {
-            TraversalStrategy::DepthFirst => {
-                self.depth_first_traverse(start_key, &mut visitor, &mut visited);
-            }
-            TraversalStrategy::MSTOrder => {
-                self.mst_order_traverse(start_key, &mut visitor, &mut visited);
+    /// Pushes the outermost path of the subtree rooted at `node_key`, which is
+    /// the chain of `low` pointers ascending and of last `next` pointers descending
+    ///
+    /// The page on top of the stack, if any, is the parent of `node_key`, so the
+    /// walk is guarded against cycles by `Descent` from there.
+    fn descend(&mut self, node_key: Option<H::Key>) {
+        let parent_level = self.stack.last().map(|(page, _)| page.level);
+        let mut descent = Descent::below(parent_level, node_key);
+        while let Some(page) = descent.next_key().and_then(|key| self.store.get(key)) {
+            if !descent.enter(page) {
+                break;
             }
+            let position = if self.reverse { page.list.len() } else { 0 };
+            self.stack.push((page, position));
+            descent.follow(Self::child(page, position));
         }
     }
+}
 
-    // And update traversal methods to return ()
-    fn depth_first_traverse<F>(&self, start: MSTKey, visitor: &mut F, visited: &mut HashSet<MSTKey>)
-    where
-        F: FnMut(TraversalEvent<MSTKey, Value>) -> TraversalControl<()>,
-    {
-        if start == MSTKey::default() || visited.contains(&start) {
-            return;
-        }
-
-        visited.insert(start);
-
-        if let Some(page) = self.get(start) {
-            // Visit node
-            match visitor(TraversalEvent::VisitNode(start, page)) {
-                TraversalControl::Return(()) => return,
-                TraversalControl::Skip => {
-                    visitor(TraversalEvent::ExitNode(start));
-                    return;
-                }
-                TraversalControl::Continue => {}
-            }
+impl<'a, Value, H, S> Iter<'a, Value, H, S>
+where
+    Value: Hash + Reference<Key = H::Key> + KeyComparable<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// Creates an ascending iterator positioned at the first key `>= start`
+    fn starting_at(store: &'a S, root: Option<H::Key>, start: H::Key) -> Self {
+        let mut iter = Self {
+            store,
+            stack: Vec::new(),
+            reverse: false,
+            hasher: PhantomData,
+        };
+        let mut descent = Descent::new(root);
 
-            // Process low child
-            if let Some(low_key) = page.low {
-                self.depth_first_traverse(low_key, visitor, visited);
+        // Descend only along the path to `start`, skipping entries before it
+        while let Some(page) = descent.next_key().and_then(|key| store.get(key)) {
+            if !descent.enter(page) {
+                break;
             }
+            let position = page
+                .list
+                .iter()
+                .take_while(|entry| Value::compare_keys(&entry.key, &start) == Ordering::Less)
+                .count();
+            iter.stack.push((page, position));
+            descent.follow(Self::child(page, position));
+        }
+        iter
+    }
+}
 
-            // Process entries
-            for entry in page.list.iter() {
-                // Visit entry
-                match visitor(TraversalEvent::VisitEntry(start, entry)) {
-                    TraversalControl::Return(()) => return,
-                    TraversalControl::Skip => continue,
-                    TraversalControl::Continue => {}
-                }
+impl<'a, Value, H, S> Iterator for Iter<'a, Value, H, S>
+where
+    Value: Hash + Reference<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    type Item = &'a PageData<H::Key, Value>;
 
-                // Process next pointer
-                if let Some(next_key) = entry.next {
-                    self.depth_first_traverse(next_key, visitor, visited);
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((page, position)) = self.stack.pop() {
+            if self.reverse {
+                if position > 0 {
+                    // Resume this page before the entry, but visit the subtree left of it first
+                    self.stack.push((page, position - 1));
+                    self.descend(Self::child(page, position - 1));
+                    return Some(&page.list[position - 1]);
                 }
+            } else if let Some(entry) = page.list.get(position) {
+                // Resume this page after the entry, but visit its next subtree first
+                self.stack.push((page, position + 1));
+                self.descend(entry.next);
+                return Some(entry);
             }
-
-            // Exit node
-            visitor(TraversalEvent::ExitNode(start));
         }
+        None
     }
+}
 
-    /// Specific traversal for MST-ordered values that preserves the sorted order of keys.
-    ///
-    /// Unlike traditional tree traversals, MST Order follows the specific Merkle Search Tree
-    /// structure to visit nodes in strict key order:
-    ///
-    /// ```text
-    ///        [Entry1, Entry2, Entry3]
-    ///        /       |       |      \
-    ///     Low     Next1    Next2   Next3
-    /// ```
-    ///
-    /// Where the traversal visits regions in this sequence:
-    /// - Low: all keys < Entry1
-    /// - Entry1
-    /// - Next1: keys between Entry1 and Entry2
-    /// - Entry2
-    /// - Next2: keys between Entry2 and Entry3
-    /// - Entry3
-    /// - Next3: all keys > Entry3
-    ///
-    /// This ensures keys are visited in strictly ascending order - a fundamental
-    /// requirement for many MST operations.
-    fn mst_order_traverse<F>(&self, start: MSTKey, visitor: &mut F, visited: &mut HashSet<MSTKey>)
-    where
-        F: FnMut(TraversalEvent<MSTKey, Value>) -> TraversalControl<()>,
-    {
-        if start == MSTKey::default() || visited.contains(&start) {
-            return;
-        }
-
-        visited.insert(start);
+/// Owning iterator over the entries of an MST in ascending key order, see
+/// `MST::into_iter`
+///
+/// Works like `Iter`, but each stack frame holds the level and the remaining
+/// entries of a page taken out of the store.
+pub struct IntoIter<Value: Hash, H: MstHasher = Sha256, S = TreeStore<Value, H>> {
+    store: S,
+    stack: Vec<OwnedFrame<H::Key, Value>>,
+}
 
-        if let Some(page) = self.get(start) {
-            // Process according to the original collect_values algorithm
-            // First, visit low child
-            if let Some(low_key) = page.low {
-                self.mst_order_traverse(low_key, visitor, visited);
-            }
+/// The level of a page taken out of the store and its entries not yet yielded
+type OwnedFrame<K, V> = (u32, std::vec::IntoIter<PageData<K, V>>);
 
-            // Visit node
-            match visitor(TraversalEvent::VisitNode(start, page)) {
-                TraversalControl::Return(()) => return,
-                TraversalControl::Skip => return,
-                TraversalControl::Continue => {}
+impl<Value, H, S> IntoIter<Value, H, S>
+where
+    Value: Hash,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    /// Takes the chain of `low` pages of the subtree rooted at `node_key` out of
+    /// the store. A missing page is treated as an empty subtree, and the walk is
+    /// guarded against cycles, like in `Iter`
+    fn descend(&mut self, node_key: Option<H::Key>) {
+        let parent_level = self.stack.last().map(|(level, _)| *level);
+        let mut descent = Descent::below(parent_level, node_key);
+        while let Some(page) = descent.next_key().and_then(|key| self.store.remove(key)) {
+            if !descent.enter(&page) {
+                break;
             }
+            descent.follow(page.low);
+            self.stack.push((page.level, page.list.into_iter()));
+        }
+    }
+}
 
-            // Process entries in order (with their next pointers)
-            for entry in page.list.iter() {
-                // Visit entry
-                match visitor(TraversalEvent::VisitEntry(start, entry)) {
-                    TraversalControl::Return(()) => return,
-                    TraversalControl::Skip => continue,
-                    TraversalControl::Continue => {}
-                }
+impl<Value, H, S> Iterator for IntoIter<Value, H, S>
+where
+    Value: Hash,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    type Item = (H::Key, Value);
 
-                // Process next pointer before moving to next entry
-                if let Some(next_key) = entry.next {
-                    self.mst_order_traverse(next_key, visitor, visited);
-                }
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((level, mut entries)) = self.stack.pop() {
+            if let Some(entry) = entries.next() {
+                // Resume this page after the entry, but visit its next subtree first
+                self.stack.push((level, entries));
+                self.descend(entry.next);
+                return Some((entry.key, entry.value));
             }
-
-            visitor(TraversalEvent::ExitNode(start));
         }
+        None
     }
 }
 
-/// Defines different traversal strategies for navigating the tree structure
-enum TraversalStrategy {
-    /// Depth-first traversal visits nodes before their children, providing a
-    /// comprehensive view of the tree structure in pre-order
-    DepthFirst,
+/// An item on a `DiffCursor` stack, in key order from the top of the stack
+enum DiffItem<'a, K: Hash, V: Hash> {
+    /// A subtree that has not been loaded yet
+    Subtree(K),
+    /// A subtree whose root page has been loaded but not opened
+    Page(K, &'a Page<K, V>),
+    /// A single entry key
+    Entry(K),
+}
 
-    /// MST Order traverses the tree in key-sorted order, essential for operations
-    /// that need to process keys sequentially.
-    ///
-    /// The traversal sequence follows the MST structure:
-    /// 1. Visit low child (keys < first entry)
-    /// 2. Visit the node itself
-    /// 3. For each entry in order:
-    ///    a. Visit the entry
-    ///    b. Visit entry's "next" branch (keys between current and next entry)
-    ///    c. Continue to next entry
-    ///
-    /// This ensures we visit values in strictly ascending key order, which is
-    /// critical for operations like to_list() and merging.
-    MSTOrder,
+impl<K: Hash + Copy, V: Hash> DiffItem<'_, K, V> {
+    fn level(&self) -> Option<u32> {
+        match self {
+            DiffItem::Page(_, page) => Some(page.level),
+            _ => None,
+        }
+    }
 }
 
-/// Events that occur during traversal
-enum TraversalEvent<'a, K: Hash, V: Hash> {
-    VisitNode(K, &'a Page<K, V>),
-    VisitEntry(K, &'a PageData<K, V>),
-    ExitNode(K),
+/// One side of a `diff`: an in-order walk that opens pages only on request
+struct DiffCursor<'a, Value: Hash, H: MstHasher, S> {
+    store: &'a S,
+    stack: Vec<DiffItem<'a, H::Key, Value>>,
 }
 
-/// Controls how traversal should proceed
-#[allow(dead_code)]
-enum TraversalControl<R = ()> {
-    Continue,
-    Skip,      // Skip children of current node
-    Return(R), // Return early with a value
+impl<'a, Value, H, S> DiffCursor<'a, Value, H, S>
+where
+    Value: Hash + Reference<Key = H::Key>,
+    H: MstHasher,
+    S: PageStore<H::Key, Page<H::Key, Value>>,
+{
+    fn new(store: &'a S, root: Option<H::Key>) -> Self {
+        let stack = root.map(DiffItem::Subtree).into_iter().collect();
+        DiffCursor { store, stack }
+    }
+
+    /// Hash of the subtree on top of the stack, if the top is a subtree
+    fn subtree(&self) -> Option<H::Key> {
+        match self.stack.last() {
+            Some(DiffItem::Subtree(key)) | Some(DiffItem::Page(key, _)) => Some(*key),
+            _ => None,
+        }
+    }
+
+    /// Loads the page of an unloaded subtree on top of the stack.
+    /// A missing page is treated as an empty subtree, like in `split`
+    fn load(&mut self, pages_visited: &mut usize) {
+        while let Some(DiffItem::Subtree(key)) = self.stack.last() {
+            let key = *key;
+            self.stack.pop();
+            *pages_visited += 1;
+            if let Some(page) = self.store.get(key) {
+                self.stack.push(DiffItem::Page(key, page));
+                return;
+            }
+        }
+    }
+
+    /// Replaces the loaded page on top of the stack with its children and entries
+    fn expand(&mut self) {
+        if let Some(DiffItem::Page(_, page)) = self.stack.last() {
+            let page = *page;
+            self.stack.pop();
+            for entry in page.list.iter().rev() {
+                if let Some(next) = entry.next {
+                    self.stack.push(DiffItem::Subtree(next));
+                }
+                self.stack.push(DiffItem::Entry(entry.key));
+            }
+            if let Some(low) = page.low {
+                self.stack.push(DiffItem::Subtree(low));
+            }
+        }
+    }
 }
 
 impl<K: Hash, V: Hash> Page<K, V> {
@@ -972,16 +4489,18 @@ impl<K: Hash, V: Hash> Page<K, V> {
     ///
     /// # Returns
     ///
-    /// A new Page instance
+    /// A new Page instance. Its `subtree_size` only counts its own entries; set it
+    /// explicitly if the page has children.
     pub fn new(level: u32, low: Option<K>, entries: Vec<(K, V, Option<K>)>) -> Self {
         // Convert tuple list to PageData list
-        let page_entries = entries
+        let page_entries: Vec<_> = entries
             .into_iter()
             .map(|(key, value, next)| PageData { key, value, next })
             .collect();
 
         Self {
             level,
+            subtree_size: page_entries.len() as u64,
             low,
             list: page_entries,
         }
@@ -992,24 +4511,328 @@ impl<K: Hash, V: Hash> Page<K, V> {
 ///
 /// The hash incorporates all content that defines the page:
 /// - Page level (height in the tree)
+/// - Cached subtree size (number of entries in the page and below it)
 /// - Low child pointer (for keys less than any in this page)
-/// - All entries (keys, values, and next pointers)
+/// - All entries (keys, value hashes, and next pointers)
 ///
 /// This content-based addressing ensures that any change to the page content,
 /// no matter how small, results in a completely different hash - the foundation
 /// of the Merkle tree's ability to verify content integrity.
+///
+/// The bytes hashed are those produced by `encode_page`, except that each value
+/// and its length are replaced by the value's `hash_value`. A page therefore
+/// commits to its values only through their hashes, which is what lets an
+/// `InclusionProof` carry the hashes of the entries it does not prove rather
+/// than the values themselves.
 pub fn hash_page<K: AsRef<[u8]> + Hash, V: AsRef<[u8]> + Hash>(page: &Page<K, V>) -> MSTKey {
-    let mut hasher = Sha256::new();
-    hasher.update(&page.level.to_be_bytes());
-    if let Some(ref low) = page.low {
-        hasher.update(low.as_ref() as &[u8]);
+    hash_page_with::<Sha256, K, V>(page)
+}
+
+/// Generates the key for a page using the hasher `H`, with the same layout as `hash_page`.
+pub fn hash_page_with<H: MstHasher, K: AsRef<[u8]> + Hash, V: AsRef<[u8]> + Hash>(
+    page: &Page<K, V>,
+) -> H::Key {
+    hash_page_content::<H, K>(
+        page.level,
+        page.subtree_size,
+        page.low.as_ref(),
+        page.list.iter().map(|entry| {
+            (
+                &entry.key,
+                hash_value_with::<H, V>(&entry.value),
+                entry.next.as_ref(),
+            )
+        }),
+    )
+}
+
+/// Hashes the content of a page with `H`, each entry given as its key, the hash
+/// of its value and its next pointer
+///
+/// The one layout behind both `hash_page_with` and `ProofPage::hash`.
+pub(crate) fn hash_page_content<'a, H: MstHasher, K: AsRef<[u8]> + 'a>(
+    level: u32,
+    subtree_size: u64,
+    low: Option<&'a K>,
+    entries: impl ExactSizeIterator<Item = (&'a K, H::Key, Option<&'a K>)>,
+) -> H::Key {
+    fn write_optional_key<H: MstHasher, K: AsRef<[u8]>>(hasher: &mut H, key: Option<&K>) {
+        match key {
+            Some(key) => {
+                hasher.write_bytes(&[1]);
+                hasher.write_bytes(key.as_ref());
+            }
+            None => hasher.write_bytes(&[0]),
+        }
+    }
+
+    let mut hasher = H::default();
+    hasher.write_bytes(&level.to_be_bytes());
+    hasher.write_bytes(&subtree_size.to_be_bytes());
+    write_optional_key(&mut hasher, low);
+    hasher.write_bytes(&(entries.len() as u32).to_be_bytes());
+    for (key, value_hash, next) in entries {
+        hasher.write_bytes(key.as_ref());
+        hasher.write_bytes(value_hash.as_ref());
+        write_optional_key(&mut hasher, next);
+    }
+    hasher.finish()
+}
+
+/// Hashes the `AsRef<[u8]>` bytes of a value on their own, with SHA-256 like
+/// `hash_page`
+///
+/// This is the digest a page hash commits to for each of its values, so it can
+/// stand in for a value wherever only the hash chain has to be checked, as in
+/// the pages of an `InclusionProof`. It also lets parties compare large values
+/// without exchanging them.
+pub fn hash_value<V: AsRef<[u8]>>(value: &V) -> MSTKey {
+    hash_value_with::<Sha256, V>(value)
+}
+
+/// Hashes the bytes of a value using the hasher `H`, like `hash_value`
+pub fn hash_value_with<H: MstHasher, V: AsRef<[u8]>>(value: &V) -> H::Key {
+    hash_with::<H, _>(value.as_ref())
+}
+
+/// Produces the canonical bytes of a page, which `hash_page` hashes with each
+/// value replaced by its hash.
+///
+/// The layout is deterministic and self-delimiting, so it can be decoded again:
+/// - Level as a big-endian `u32`
+/// - Subtree size as a big-endian `u64`
+/// - Low pointer as a presence byte (0 or 1), followed by the key if present
+/// - Entry count as a big-endian `u32`
+/// - For each entry: the key, the value's length as a big-endian `u32` followed
+///   by its `AsRef<[u8]>` bytes, and the next pointer encoded like the low pointer
+///
+/// Keys are written as-is, so every key of a page must have the same length.
+pub fn encode_page<K: AsRef<[u8]> + Hash, V: AsRef<[u8]> + Hash>(page: &Page<K, V>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_page(page, |chunk| bytes.extend_from_slice(chunk));
+    bytes
+}
+
+/// Decodes a page produced by `encode_page`.
+///
+/// Keys are read with the length of `K::default()`. Since the encoding only holds a
+/// value's `AsRef<[u8]>` bytes, `decode_value` rebuilds a value from its entry key
+/// and those bytes.
+///
+/// # Returns
+/// The page, or `MstError::InvalidPageEncoding` if the bytes are truncated, have
+/// trailing data, or a value fails to decode
+pub fn decode_page<K, V, F>(bytes: &[u8], mut decode_value: F) -> Result<Page<K, V>, MstError>
+where
+    K: Default + AsMut<[u8]> + Hash,
+    V: Hash,
+    F: FnMut(&K, &[u8]) -> Option<V>,
+{
+    let mut reader = PageReader { bytes };
+
+    let level = reader.read_u32()?;
+    let subtree_size = reader.read_u64()?;
+    let low = reader.read_optional_key()?;
+    let count = reader.read_u32()?;
+
+    let mut list = Vec::new();
+    for _ in 0..count {
+        let key = reader.read_key()?;
+        let length = reader.read_u32()? as usize;
+        let value =
+            decode_value(&key, reader.take(length)?).ok_or(MstError::InvalidPageEncoding)?;
+        let next = reader.read_optional_key()?;
+        list.push(PageData { key, value, next });
+    }
+
+    if !reader.bytes.is_empty() {
+        return Err(MstError::InvalidPageEncoding);
+    }
+
+    Ok(Page {
+        level,
+        subtree_size,
+        low,
+        list,
+    })
+}
+
+/// The default `LevelMapper`, placing every key at its computed level
+fn identity_level(level: u32) -> u32 {
+    level
+}
+
+/// Feeds the canonical encoding of a page to `out`, piece by piece
+fn write_page<K: AsRef<[u8]> + Hash, V: AsRef<[u8]> + Hash>(
+    page: &Page<K, V>,
+    mut out: impl FnMut(&[u8]),
+) {
+    fn write_optional_key<K: AsRef<[u8]>>(key: &Option<K>, out: &mut impl FnMut(&[u8])) {
+        match key {
+            Some(key) => {
+                out(&[1]);
+                out(key.as_ref());
+            }
+            None => out(&[0]),
+        }
     }
+
+    out(&page.level.to_be_bytes());
+    out(&page.subtree_size.to_be_bytes());
+    write_optional_key(&page.low, &mut out);
+    out(&(page.list.len() as u32).to_be_bytes());
     for item in &page.list {
-        hasher.update(item.key.as_ref());
-        hasher.update(item.value.as_ref());
-        if let Some(ref next) = item.next {
-            hasher.update(next.as_ref() as &[u8]);
+        out(item.key.as_ref());
+        let value = item.value.as_ref();
+        out(&(value.len() as u32).to_be_bytes());
+        out(value);
+        write_optional_key(&item.next, &mut out);
+    }
+}
+
+/// Hex of the first four bytes of a key, used to name pages and keys in `to_dot`
+fn short_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take(4)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Opens every file written by `MST::save_to_path`, ahead of the
+/// `LEVEL_FORMAT_VERSION` it was written under
+const FILE_MAGIC: &[u8; 4] = b"MST\0";
+
+/// Cursor over the bytes of an encoded page
+struct PageReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> PageReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], MstError> {
+        if self.bytes.len() < length {
+            return Err(MstError::InvalidPageEncoding);
+        }
+        let (head, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, MstError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, MstError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn read_key<K: Default + AsMut<[u8]>>(&mut self) -> Result<K, MstError> {
+        let mut key = K::default();
+        let length = key.as_mut().len();
+        key.as_mut().copy_from_slice(self.take(length)?);
+        Ok(key)
+    }
+
+    fn read_optional_key<K: Default + AsMut<[u8]>>(&mut self) -> Result<Option<K>, MstError> {
+        match self.take(1)?[0] {
+            0 => Ok(None),
+            1 => self.read_key().map(Some),
+            _ => Err(MstError::InvalidPageEncoding),
         }
     }
-    hasher.finalize()
+}
+
+/// A walk from the root towards one key, visiting one page per level
+///
+/// Every child page sits at a lower level than its parent. A store whose
+/// pointers lead back up to an ancestor, through corruption or malice, breaks
+/// this, so the descent ends at the first page that is not below the one before
+/// it instead of looping forever. It is handed each page rather than a store so
+/// that `MstAsync` can walk through it too.
+pub(crate) struct Descent<K> {
+    next: Option<K>,
+    parent_level: Option<u32>,
+}
+
+impl<K: Copy + Hash> Descent<K> {
+    pub(crate) fn new(root: Option<K>) -> Self {
+        Self::below(None, root)
+    }
+
+    /// Starts a descent at `child`, a child of a page at `parent_level`, or a
+    /// root if that is None
+    pub(crate) fn below(parent_level: Option<u32>, child: Option<K>) -> Self {
+        Descent {
+            next: child,
+            parent_level,
+        }
+    }
+
+    /// The key of the page to visit next, None once the descent has ended
+    pub(crate) fn next_key(&self) -> Option<K> {
+        self.next
+    }
+
+    /// Enters the page stored under `next_key`, returning false and ending the
+    /// descent if it is not below the page entered before it
+    pub(crate) fn enter<V: Hash>(&mut self, page: &Page<K, V>) -> bool {
+        if self.parent_level.is_some_and(|level| page.level >= level) {
+            self.next = None;
+            return false;
+        }
+        self.parent_level = Some(page.level);
+        true
+    }
+
+    /// Continues the descent into `child`, ending it if there is none
+    pub(crate) fn follow(&mut self, child: Option<K>) {
+        self.next = child;
+    }
+
+    /// Enters the page stored under `next_key` and looks `search_key` up in it
+    ///
+    /// # Returns
+    ///
+    /// `Ok` with the index of the matching entry, which ends the descent, or
+    /// `Err` with the number of entries below the key, the descent continuing
+    /// into the branch between them. None if the page ended the descent, as in
+    /// `enter`.
+    pub(crate) fn step<V>(
+        &mut self,
+        page: &Page<K, V>,
+        search_key: &K,
+    ) -> Option<Result<usize, usize>>
+    where
+        V: Hash + KeyComparable<Key = K>,
+    {
+        if !self.enter(page) {
+            return None;
+        }
+        let mut position = Err(page.list.len());
+        for (index, entry) in page.list.iter().enumerate() {
+            match V::compare_keys(search_key, &entry.key) {
+                Ordering::Equal => position = Ok(index),
+                Ordering::Less => position = Err(index),
+                Ordering::Greater => continue,
+            }
+            break;
+        }
+        self.next = match position {
+            Ok(_) => None,
+            Err(index) => branch(page, index),
+        };
+        Some(position)
+    }
+}
+
+/// The child holding the keys between entries `index - 1` and `index` of a
+/// page, the low child for index 0
+pub(crate) fn branch<K: Copy + Hash, V: Hash>(page: &Page<K, V>, index: usize) -> Option<K> {
+    match index {
+        0 => page.low,
+        _ => page.list[index - 1].next,
+    }
 }
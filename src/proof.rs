@@ -0,0 +1,424 @@
+use crate::MSTKey;
+use crate::error::IntegrityError;
+use crate::mst::{hash_page_content, hash_page_with, hash_value_with};
+use crate::store::{Page, PageData};
+use crate::utils::{KeyComparable, MstHasher};
+use sha2::Sha256;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// An entry of a page on a proof path, with its value replaced by its hash
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofEntry<K> {
+    pub key: K,
+    /// `hash_value` of the entry's value
+    pub value_hash: K,
+    pub next: Option<K>,
+}
+
+impl<K: Copy> ProofEntry<K> {
+    /// Replaces the value of an entry with its hash under `H`
+    pub fn from_entry<H, Value>(entry: &PageData<K, Value>) -> Self
+    where
+        H: MstHasher<Key = K>,
+        Value: AsRef<[u8]> + Hash,
+        K: Hash,
+    {
+        ProofEntry {
+            key: entry.key,
+            value_hash: hash_value_with::<H, Value>(&entry.value),
+            next: entry.next,
+        }
+    }
+}
+
+/// A page on a proof path, carrying the hashes of its values rather than the
+/// values themselves
+///
+/// Page hashes commit to values only through their hashes, so this is all that
+/// is needed to recompute the hash of the page it was built from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofPage<K> {
+    pub level: u32,
+    pub subtree_size: u64,
+    pub low: Option<K>,
+    pub list: Vec<ProofEntry<K>>,
+}
+
+impl<K: Copy + AsRef<[u8]>> ProofPage<K> {
+    /// Replaces the values of a page with their hashes under `H`
+    pub fn from_page<H, Value>(page: &Page<K, Value>) -> Self
+    where
+        H: MstHasher<Key = K>,
+        Value: AsRef<[u8]> + Hash,
+        K: Hash,
+    {
+        ProofPage {
+            level: page.level,
+            subtree_size: page.subtree_size,
+            low: page.low,
+            list: page
+                .list
+                .iter()
+                .map(ProofEntry::from_entry::<H, Value>)
+                .collect(),
+        }
+    }
+
+    /// Computes the hash of the page it was built from, as `hash_page_with`
+    pub fn hash<H: MstHasher<Key = K>>(&self) -> K {
+        hash_page_content::<H, K>(
+            self.level,
+            self.subtree_size,
+            self.low.as_ref(),
+            self.list
+                .iter()
+                .map(|entry| (&entry.key, entry.value_hash, entry.next.as_ref())),
+        )
+    }
+}
+
+/// A Merkle proof that an entry is present in a tree with a given root
+///
+/// Holds every page on the path from the root down to the page containing the
+/// entry. Each page carries its level, low pointer and all of its entries with
+/// their values reduced to hashes, which is exactly what is needed to recompute
+/// its hash, and each page references the hash of the next one through the
+/// child pointer the key falls into. Only the proven entry carries its value.
+#[derive(Clone, Debug)]
+pub struct InclusionProof<Value: Hash, K: Hash = MSTKey> {
+    /// Pages from the root (first) to the page holding the entry (last)
+    pub pages: Vec<ProofPage<K>>,
+    /// The proven entry, as stored in the last page
+    pub entry: PageData<K, Value>,
+}
+
+/// Verifies an inclusion proof against a known SHA-256 root
+///
+/// See `verify_proof_with` for the checks performed.
+pub fn verify_proof<Value>(
+    root: Option<MSTKey>,
+    key: MSTKey,
+    value: &Value,
+    proof: &InclusionProof<Value>,
+) -> bool
+where
+    Value: AsRef<[u8]> + Hash + KeyComparable<Key = MSTKey>,
+{
+    verify_proof_with::<Sha256, Value>(root, key, value, proof)
+}
+
+/// Verifies an inclusion proof against a known root using the hasher `H`
+///
+/// Recomputes the page hashes bottom-up with the same layout as `hash_page_with`,
+/// and accepts the proof only if:
+/// - the topmost page hashes to `root`, so nothing is proven for an empty tree
+/// - every page's entries are in strictly ascending key order
+/// - every page references the page below it through the branch `key` falls into,
+///   and that page sits at a lower level
+/// - the last page holds `key`, with the proof's entry and the hash of the
+///   claimed `value`
+pub fn verify_proof_with<H, Value>(
+    root: Option<H::Key>,
+    key: H::Key,
+    value: &Value,
+    proof: &InclusionProof<Value, H::Key>,
+) -> bool
+where
+    H: MstHasher,
+    Value: AsRef<[u8]> + Hash + KeyComparable<Key = H::Key>,
+{
+    // The claimed value must be the one in the proven entry
+    if proof.entry.key != key || proof.entry.value.as_ref() != value.as_ref() {
+        return false;
+    }
+
+    match walk_path::<H, Value>(root, key, &proof.pages) {
+        Some(PathEnd::Found(index)) => {
+            proof.pages[proof.pages.len() - 1].list[index]
+                == ProofEntry::from_entry::<H, Value>(&proof.entry)
+        }
+        _ => false,
+    }
+}
+
+/// A Merkle proof that a key is absent from a tree with a given root
+///
+/// Holds the search path for the key from the root down to the page where the
+/// search runs out of children, together with the entries immediately before and
+/// after the key. Since the search path of a missing key passes through both of
+/// its neighbours, the verifier can check they really are adjacent. As in an
+/// `InclusionProof`, only the neighbours carry their values.
+#[derive(Clone, Debug)]
+pub struct AbsenceProof<Value: Hash, K: Hash = MSTKey> {
+    /// Pages from the root (first) to the page where the search ends (last),
+    /// empty for an empty tree
+    pub pages: Vec<ProofPage<K>>,
+    /// The greatest entry smaller than the key, None if the key is below every entry
+    pub predecessor: Option<PageData<K, Value>>,
+    /// The smallest entry greater than the key, None if the key is above every entry
+    pub successor: Option<PageData<K, Value>>,
+}
+
+/// Verifies an absence proof against a known SHA-256 root
+///
+/// See `verify_absence_proof_with` for the checks performed.
+pub fn verify_absence_proof<Value>(
+    root: Option<MSTKey>,
+    key: MSTKey,
+    proof: &AbsenceProof<Value>,
+) -> bool
+where
+    Value: AsRef<[u8]> + Hash + KeyComparable<Key = MSTKey>,
+{
+    verify_absence_proof_with::<Sha256, Value>(root, key, proof)
+}
+
+/// Verifies an absence proof against a known root using the hasher `H`
+///
+/// Accepts the proof only if the pages form a valid search path for `key` under
+/// the same rules as `verify_proof_with`, `key` matches no entry on it, the search
+/// ends at a missing child, and the claimed predecessor and successor are the
+/// closest entries on the path below and above `key`. An empty tree (a root of
+/// None) is proven by a proof with no pages and no neighbours.
+pub fn verify_absence_proof_with<H, Value>(
+    root: Option<H::Key>,
+    key: H::Key,
+    proof: &AbsenceProof<Value, H::Key>,
+) -> bool
+where
+    H: MstHasher,
+    Value: AsRef<[u8]> + Hash + KeyComparable<Key = H::Key>,
+{
+    if proof.pages.is_empty() {
+        return root.is_none() && proof.predecessor.is_none() && proof.successor.is_none();
+    }
+
+    if walk_path::<H, Value>(root, key, &proof.pages) != Some(PathEnd::Branch(None)) {
+        return false;
+    }
+
+    // The neighbours of a missing key are the closest entries on its search path
+    let mut predecessor: Option<&ProofEntry<H::Key>> = None;
+    let mut successor: Option<&ProofEntry<H::Key>> = None;
+    for entry in proof.pages.iter().flat_map(|page| page.list.iter()) {
+        match Value::compare_keys(&entry.key, &key) {
+            Ordering::Less => {
+                if predecessor
+                    .is_none_or(|p| Value::compare_keys(&p.key, &entry.key) == Ordering::Less)
+                {
+                    predecessor = Some(entry);
+                }
+            }
+            Ordering::Greater => {
+                if successor
+                    .is_none_or(|s| Value::compare_keys(&entry.key, &s.key) == Ordering::Less)
+                {
+                    successor = Some(entry);
+                }
+            }
+            Ordering::Equal => return false,
+        }
+    }
+
+    let matches = |expected: Option<&ProofEntry<H::Key>>,
+                   claimed: &Option<PageData<H::Key, Value>>| match (
+        expected, claimed,
+    ) {
+        (None, None) => true,
+        (Some(expected), Some(claimed)) => *expected == ProofEntry::from_entry::<H, Value>(claimed),
+        _ => false,
+    };
+    matches(predecessor, &proof.predecessor) && matches(successor, &proof.successor)
+}
+
+/// Verifies pages streamed from a sync peer as they arrive, in any order
+///
+/// Each fed page is checked against the key it came with immediately, so a peer
+/// sending a tampered page is caught at that page rather than after the whole
+/// transfer. The verifier also tracks completeness incrementally: a page is
+/// complete once it and every page below it have arrived, and `verified(root)`
+/// answers whether that holds for the root. Only keys are kept, not the pages
+/// themselves.
+pub struct StreamVerifier<H: MstHasher = Sha256> {
+    /// Keys of every page fed so far
+    received: HashSet<H::Key>,
+    /// Received pages whose whole subtree has arrived
+    complete: HashSet<H::Key>,
+    /// For received pages that are not complete, how many children are not
+    pending: HashMap<H::Key, usize>,
+    /// For each child that is not complete, the received pages waiting on it
+    waiting: HashMap<H::Key, Vec<H::Key>>,
+    /// Children referenced by a received page that have not arrived yet
+    outstanding: HashSet<H::Key>,
+}
+
+impl<H: MstHasher> StreamVerifier<H> {
+    /// Creates a verifier that has not received any pages
+    pub fn new() -> Self {
+        StreamVerifier {
+            received: HashSet::new(),
+            complete: HashSet::new(),
+            pending: HashMap::new(),
+            waiting: HashMap::new(),
+            outstanding: HashSet::new(),
+        }
+    }
+
+    /// Feeds one received page, checking that it hashes to `key`
+    ///
+    /// Feeding a page that already arrived has no further effect.
+    ///
+    /// # Returns
+    ///
+    /// `IntegrityError::HashMismatch(key)` if the page does not hash to `key`, in
+    /// which case it is not recorded as received
+    pub fn feed<Value: AsRef<[u8]> + Hash>(
+        &mut self,
+        key: H::Key,
+        page: &Page<H::Key, Value>,
+    ) -> Result<(), IntegrityError<H::Key>> {
+        if hash_page_with::<H, _, _>(page) != key {
+            return Err(IntegrityError::HashMismatch(key));
+        }
+        if !self.received.insert(key) {
+            return Ok(());
+        }
+        self.outstanding.remove(&key);
+
+        let children: HashSet<H::Key> = std::iter::once(page.low)
+            .chain(page.list.iter().map(|entry| entry.next))
+            .flatten()
+            .collect();
+        let mut pending = 0;
+        for child in children {
+            if !self.received.contains(&child) {
+                self.outstanding.insert(child);
+            }
+            if !self.complete.contains(&child) {
+                pending += 1;
+                self.waiting.entry(child).or_default().push(key);
+            }
+        }
+
+        if pending == 0 {
+            self.mark_complete(key);
+        } else {
+            self.pending.insert(key, pending);
+        }
+        Ok(())
+    }
+
+    /// Returns true once `root` and every page reachable from it have arrived
+    ///
+    /// Pages that reference themselves, directly or through others, never
+    /// complete, so a cyclic stream is never verified.
+    pub fn verified(&self, root: H::Key) -> bool {
+        self.complete.contains(&root)
+    }
+
+    /// Returns the children referenced by received pages that have not arrived
+    ///
+    /// This is the incremental counterpart of `PageStore::missing_set`, except
+    /// that it covers every received page rather than those below one root, and
+    /// never includes a root nothing references.
+    pub fn outstanding(&self) -> &HashSet<H::Key> {
+        &self.outstanding
+    }
+
+    /// Marks a page complete, along with every waiting ancestor this completes
+    fn mark_complete(&mut self, key: H::Key) {
+        let mut stack = vec![key];
+        while let Some(key) = stack.pop() {
+            self.complete.insert(key);
+            for parent in self.waiting.remove(&key).unwrap_or_default() {
+                let pending = self.pending.get_mut(&parent).unwrap();
+                *pending -= 1;
+                if *pending == 0 {
+                    self.pending.remove(&parent);
+                    stack.push(parent);
+                }
+            }
+        }
+    }
+}
+
+impl<H: MstHasher> Default for StreamVerifier<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where the search for a key ends within a single page
+#[derive(Debug, PartialEq)]
+enum PathEnd<K> {
+    /// The key is the entry at this index
+    Found(usize),
+    /// The key falls into this child branch
+    Branch(Option<K>),
+}
+
+/// Searches a single page with the same branch selection as `MST::contains_key`
+fn search_page<K: Copy, Value>(page: &ProofPage<K>, key: &K) -> PathEnd<K>
+where
+    Value: KeyComparable<Key = K>,
+{
+    let mut branch = page.low;
+    for (index, entry) in page.list.iter().enumerate() {
+        match Value::compare_keys(key, &entry.key) {
+            Ordering::Equal => return PathEnd::Found(index),
+            Ordering::Less => break,
+            Ordering::Greater => branch = entry.next,
+        }
+    }
+    PathEnd::Branch(branch)
+}
+
+/// Checks that `pages` is the search path for `key` in the tree rooted at `root`.
+///
+/// Recomputes the hashes bottom-up, checking that each page is sorted, that the
+/// key is not found above the last page, and that each page links to the one
+/// below it through the key's branch at a lower level.
+///
+/// # Returns
+/// Where the search ends in the last page, or None if the path is invalid
+fn walk_path<H, Value>(
+    root: Option<H::Key>,
+    key: H::Key,
+    pages: &[ProofPage<H::Key>],
+) -> Option<PathEnd<H::Key>>
+where
+    H: MstHasher,
+    Value: KeyComparable<Key = H::Key>,
+{
+    let mut end = None;
+    let mut child: Option<(H::Key, u32)> = None;
+
+    for page in pages.iter().rev() {
+        let sorted = page
+            .list
+            .windows(2)
+            .all(|pair| Value::compare_keys(&pair[0].key, &pair[1].key) == Ordering::Less);
+        if !sorted {
+            return None;
+        }
+
+        let search = search_page::<H::Key, Value>(page, &key);
+        match child {
+            None => end = Some(search),
+            Some((child_hash, child_level)) => {
+                if search != PathEnd::Branch(Some(child_hash)) || child_level >= page.level {
+                    return None;
+                }
+            }
+        }
+
+        child = Some((page.hash::<H>(), page.level));
+    }
+
+    match child {
+        Some((hash, _)) if Some(hash) == root => end,
+        _ => None,
+    }
+}
@@ -4,6 +4,7 @@ use std::convert::AsRef;
 use std::hash::Hash;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PageData<Key: Hash, Value: Hash> {
     pub key: Key,
     pub value: Value,
@@ -11,8 +12,15 @@ pub struct PageData<Key: Hash, Value: Hash> {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Page<Key: Hash, Value: Hash> {
     pub level: u32,
+    /// Number of entries in this page and all pages below it.
+    ///
+    /// It is part of the hashed content, so a page's hash also commits to the size
+    /// of its subtree. Root hashes therefore differ from trees built before this
+    /// field was added, even for the same contents.
+    pub subtree_size: u64,
     pub low: Option<Key>,
     pub list: Vec<PageData<Key, Value>>,
 }
@@ -21,6 +29,35 @@ pub struct Store<Key: AsRef<[u8]>, Value> {
     pages: HashMap<Key, Value>,
 }
 
+/// Serializes the pages as a sequence of `(key, page)` pairs, so formats whose
+/// maps only take string keys can hold a store too
+#[cfg(feature = "serde")]
+impl<Key, Value> serde::Serialize for Store<Key, Value>
+where
+    Key: AsRef<[u8]> + serde::Serialize,
+    Value: serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.pages)
+    }
+}
+
+/// Reads the `(key, page)` pairs written by `Serialize`. Pages are taken as they
+/// are, so check them with `MST::verify_integrity` if the source is untrusted.
+#[cfg(feature = "serde")]
+impl<'de, Key, Value> serde::Deserialize<'de> for Store<Key, Value>
+where
+    Key: AsRef<[u8]> + Eq + Hash + serde::Deserialize<'de>,
+    Value: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pages = Vec::<(Key, Value)>::deserialize(deserializer)?;
+        Ok(Store {
+            pages: pages.into_iter().collect(),
+        })
+    }
+}
+
 impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Store<Key, Value> {
     pub fn new() -> Self {
         Store {
@@ -28,11 +65,37 @@ impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Store<Key
         }
     }
 
+    /// Creates an empty store with room for at least `capacity` pages
+    ///
+    /// A bulk load that knows roughly how many pages it will write can reserve
+    /// them up front instead of growing the map, and rehashing it, as it goes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Store {
+            pages: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of pages the store can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.pages.capacity()
+    }
+
+    /// Releases room reserved beyond the pages currently stored, e.g. after `gc`
+    pub fn shrink_to_fit(&mut self) {
+        self.pages.shrink_to_fit();
+    }
+
     pub fn put(&mut self, key: Key, value: Value) -> Key {
         self.pages.insert(key, value);
         key
     }
 
+    /// Stores a batch of pages, such as those `collect_pages` returned for a
+    /// sync. A key given more than once keeps its last page.
+    pub fn put_many(&mut self, pages: impl IntoIterator<Item = (Key, Value)>) {
+        self.pages.extend(pages);
+    }
+
     pub fn get(&self, key: Key) -> Option<&Value> {
         self.pages.get(&key)
     }
@@ -45,7 +108,115 @@ impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Store<Key
         self.pages.remove(&key)
     }
 
+    /// Returns the number of stored pages, reachable or not
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Returns true if the store holds no pages
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Removes every page, keeping the allocated capacity for reuse
+    pub fn clear(&mut self) {
+        self.pages.clear();
+    }
+
     pub fn missing_set(&self, root: Key) -> HashSet<Key> {
+        PageStore::missing_set(self, root)
+    }
+
+    pub fn reachable_set(&self, root: Key) -> HashSet<Key> {
+        PageStore::reachable_set(self, root)
+    }
+
+    pub fn collect_pages(&self, keys: &HashSet<Key>) -> Vec<(Key, Value)>
+    where
+        Value: Clone,
+    {
+        PageStore::collect_pages(self, keys)
+    }
+
+    pub fn collect_pages_except(
+        &self,
+        keys: &HashSet<Key>,
+        have: &HashSet<Key>,
+    ) -> Vec<(Key, Value)>
+    where
+        Value: Clone,
+    {
+        PageStore::collect_pages_except(self, keys, have)
+    }
+
+    /// Provides an iterator over the key-value pairs in the store
+    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, Key, Value> {
+        self.pages.iter()
+    }
+}
+
+/// A storage backend for the pages of a tree
+///
+/// `Store` is the in-memory implementation `MST` uses by default. Implement this
+/// trait to keep pages elsewhere, such as on disk. Since `get` hands out borrows,
+/// a backend that cannot hold every page in memory needs a cache to serve them.
+pub trait PageStore<Key, Value> {
+    /// Iterator over the stored pages and their keys
+    type Iter<'a>: Iterator<Item = (&'a Key, &'a Value)>
+    where
+        Self: 'a,
+        Key: 'a,
+        Value: 'a;
+
+    /// Stores a page under its key, returning the key
+    fn put(&mut self, key: Key, value: Value) -> Key;
+
+    /// Stores a batch of pages, the counterpart of `collect_pages`
+    ///
+    /// A key given more than once keeps its last page. The default puts the
+    /// pages one at a time; a backend that can write a batch at once, such as a
+    /// disk-backed one, should override it.
+    fn put_many(&mut self, pages: impl IntoIterator<Item = (Key, Value)>) {
+        for (key, value) in pages {
+            self.put(key, value);
+        }
+    }
+
+    /// Retrieves a page by its key
+    fn get(&self, key: Key) -> Option<&Value>;
+
+    /// Checks whether a page is stored under the key
+    fn has(&self, key: Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes a page, returning it if it was present
+    fn remove(&mut self, key: Key) -> Option<Value>;
+
+    /// Iterates over every stored page, in no particular order
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Removes every stored page
+    ///
+    /// The default removes the pages one at a time; `Store` empties its map in
+    /// place instead, keeping the allocation.
+    fn clear(&mut self)
+    where
+        Key: Copy,
+    {
+        let keys: Vec<Key> = self.iter().map(|(key, _)| *key).collect();
+        for key in keys {
+            self.remove(key);
+        }
+    }
+
+    /// Collects the keys referenced from `root`, directly or transitively,
+    /// that are not in the store. `root` itself is included if it is missing.
+    fn missing_set(&self, root: Key) -> HashSet<Key>
+    where
+        Key: Eq + Hash + Copy,
+        Value: Reference<Key = Key>,
+    {
         let mut result = HashSet::new();
         let mut to_visit = Vec::new(); // Stack for DFS
         let mut visited = HashSet::new(); // Track visited nodes
@@ -60,7 +231,7 @@ impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Store<Key
                 continue;
             }
 
-            match self.pages.get(&hash) {
+            match self.get(hash) {
                 None => {
                     // This hash is missing - add to result
                     result.insert(hash);
@@ -79,12 +250,323 @@ impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Store<Key
         result
     }
 
-    /// Provides an iterator over the key-value pairs in the store
-    pub fn iter(&self) -> std::collections::hash_map::Iter<Key, Value> {
-        self.pages.iter()
+    /// Collects the keys of the pages present in the store and reachable from
+    /// `root`, including `root` itself. The inverse of `missing_set`.
+    ///
+    /// Besides `refs`, this follows `value_refs`, so content a value points at,
+    /// such as the root of another tree in the same store, counts as reachable
+    /// and is kept by `MST::gc`.
+    fn reachable_set(&self, root: Key) -> HashSet<Key>
+    where
+        Key: Eq + Hash + Copy,
+        Value: Reference<Key = Key>,
+    {
+        let mut result = HashSet::new();
+        let mut to_visit = vec![root]; // Stack for DFS
+
+        while let Some(hash) = to_visit.pop() {
+            // Each page is expanded at most once, so cycles terminate
+            if result.contains(&hash) {
+                continue;
+            }
+
+            if let Some(page) = self.get(hash) {
+                result.insert(hash);
+                for ref_hash in page.refs().into_iter().chain(page.value_refs()) {
+                    if !result.contains(&ref_hash) {
+                        to_visit.push(ref_hash);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Serves the pages a peer asked for, typically its `missing_set`
+    ///
+    /// Returns the requested pages together with all of their transitive
+    /// children, each page once, so the peer can be brought up to date in one
+    /// round trip. Keys not in this store are skipped.
+    fn collect_pages(&self, keys: &HashSet<Key>) -> Vec<(Key, Value)>
+    where
+        Key: Eq + Hash + Copy,
+        Value: Reference<Key = Key> + Clone,
+    {
+        self.collect_pages_except(keys, &HashSet::new())
+    }
+
+    /// Like `collect_pages`, but leaves out the pages in `have` and everything
+    /// below them, for a peer known to hold those subtrees already
+    fn collect_pages_except(&self, keys: &HashSet<Key>, have: &HashSet<Key>) -> Vec<(Key, Value)>
+    where
+        Key: Eq + Hash + Copy,
+        Value: Reference<Key = Key> + Clone,
+    {
+        let mut result = Vec::new();
+        let mut visited = HashSet::new();
+        let mut to_visit: Vec<Key> = keys.iter().copied().collect();
+
+        while let Some(hash) = to_visit.pop() {
+            if have.contains(&hash) || !visited.insert(hash) {
+                continue;
+            }
+
+            if let Some(page) = self.get(hash) {
+                to_visit.extend(page.refs());
+                result.push((hash, page.clone()));
+            }
+        }
+
+        result
+    }
+}
+
+impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> PageStore<Key, Value>
+    for Store<Key, Value>
+{
+    type Iter<'a>
+        = std::collections::hash_map::Iter<'a, Key, Value>
+    where
+        Self: 'a,
+        Key: 'a,
+        Value: 'a;
+
+    fn put(&mut self, key: Key, value: Value) -> Key {
+        Store::put(self, key, value)
+    }
+
+    fn put_many(&mut self, pages: impl IntoIterator<Item = (Key, Value)>) {
+        Store::put_many(self, pages)
+    }
+
+    fn get(&self, key: Key) -> Option<&Value> {
+        Store::get(self, key)
+    }
+
+    fn has(&self, key: Key) -> bool {
+        Store::has(self, key)
+    }
+
+    fn remove(&mut self, key: Key) -> Option<Value> {
+        Store::remove(self, key)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        Store::iter(self)
+    }
+
+    fn clear(&mut self) {
+        Store::clear(self)
+    }
+}
+
+/// A throwaway layer over a borrowed store, for running tree operations
+/// without touching it
+///
+/// Reads fall through to `base` and writes stay in the layer. Removing a page
+/// of `base` is a no-op: pages are content-addressed, so one left visible is
+/// still correct, merely unreachable.
+pub(crate) struct ScratchStore<'a, Key: AsRef<[u8]>, Value, S> {
+    base: &'a S,
+    pages: Store<Key, Value>,
+}
+
+impl<'a, Key, Value, S> ScratchStore<'a, Key, Value, S>
+where
+    Key: AsRef<[u8]> + Eq + Hash + Copy,
+    Value: Reference<Key = Key>,
+{
+    pub(crate) fn new(base: &'a S) -> Self {
+        ScratchStore {
+            base,
+            pages: Store::new(),
+        }
+    }
+}
+
+impl<Key, Value, S> PageStore<Key, Value> for ScratchStore<'_, Key, Value, S>
+where
+    Key: AsRef<[u8]> + Eq + Hash + Copy,
+    Value: Reference<Key = Key>,
+    S: PageStore<Key, Value>,
+{
+    /// Pages of the layer, then those of `base`; a page in both is yielded twice
+    type Iter<'b>
+        = std::iter::Chain<std::collections::hash_map::Iter<'b, Key, Value>, S::Iter<'b>>
+    where
+        Self: 'b,
+        Key: 'b,
+        Value: 'b;
+
+    fn put(&mut self, key: Key, value: Value) -> Key {
+        self.pages.put(key, value)
+    }
+
+    fn get(&self, key: Key) -> Option<&Value> {
+        self.pages.get(key).or_else(|| self.base.get(key))
+    }
+
+    fn remove(&mut self, key: Key) -> Option<Value> {
+        self.pages.remove(key)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.pages.iter().chain(self.base.iter())
+    }
+}
+
+/// A copy-on-write layer over an owned store, for running a sequence of tree
+/// operations as a transaction
+///
+/// Reads fall through to `base` and writes go to the layer. Removing a page of
+/// `base` only hides it until `commit`. Starting a transaction copies nothing:
+/// `commit` applies the layer to `base` and `discard` drops it, leaving `base`
+/// exactly as it was.
+///
+/// # Example
+/// ```
+/// use mst::{MST, OverlayStore, Page, MSTKey, TreeStore};
+/// use mst::test_utils::{TestValue, create_key};
+/// use sha2::Sha256;
+///
+/// let mut tree: MST<TestValue> = MST::new();
+/// let key = create_key(b"kept");
+/// tree.insert(key, TestValue { key, data: [1, 0, 0, 0] });
+/// let before = tree.root;
+///
+/// type Overlay = OverlayStore<MSTKey, Page<MSTKey, TestValue>, TreeStore<TestValue>>;
+/// let mut tx = MST::<TestValue, Sha256, Overlay>::with_store_and_hasher(
+///     tree.root,
+///     OverlayStore::new(tree.store),
+/// );
+/// let key = create_key(b"speculative");
+/// tx.insert(key, TestValue { key, data: [2, 0, 0, 0] });
+/// assert_eq!(tx.len(), 2);
+///
+/// // Throw the insert away and reopen the tree at its old root
+/// tx.store.discard();
+/// let tree = MST::with_store(before, tx.store.into_base());
+/// assert_eq!(tree.len(), 1);
+/// ```
+pub struct OverlayStore<Key: AsRef<[u8]>, Value, S = Store<Key, Value>> {
+    base: S,
+    pages: HashMap<Key, Value>,
+    /// Pages of `base` removed in the layer
+    removed: HashSet<Key>,
+}
+
+impl<Key, Value, S> OverlayStore<Key, Value, S>
+where
+    Key: AsRef<[u8]> + Eq + Hash + Copy,
+    S: PageStore<Key, Value>,
+{
+    /// Starts a transaction over `base`
+    pub fn new(base: S) -> Self {
+        OverlayStore {
+            base,
+            pages: HashMap::new(),
+            removed: HashSet::new(),
+        }
+    }
+
+    /// Returns the underlying store, without the changes in the layer
+    pub fn base(&self) -> &S {
+        &self.base
+    }
+
+    /// Applies the layer to `base`: pages written are stored in it and pages
+    /// removed are removed from it. The layer is empty afterwards.
+    pub fn commit(&mut self) {
+        for key in self.removed.drain() {
+            self.base.remove(key);
+        }
+        self.base.put_many(self.pages.drain());
+    }
+
+    /// Drops every change in the layer, leaving `base` untouched
+    pub fn discard(&mut self) {
+        self.pages.clear();
+        self.removed.clear();
+    }
+
+    /// Returns the underlying store, dropping any change not committed
+    pub fn into_base(self) -> S {
+        self.base
     }
 }
 
+impl<Key, Value, S> PageStore<Key, Value> for OverlayStore<Key, Value, S>
+where
+    Key: AsRef<[u8]> + Eq + Hash + Copy,
+    Value: Clone,
+    S: PageStore<Key, Value>,
+{
+    /// Pages of the layer, then those of `base` the layer neither replaces nor
+    /// removes
+    type Iter<'a>
+        = Box<dyn Iterator<Item = (&'a Key, &'a Value)> + 'a>
+    where
+        Self: 'a,
+        Key: 'a,
+        Value: 'a;
+
+    fn put(&mut self, key: Key, value: Value) -> Key {
+        self.removed.remove(&key);
+        self.pages.insert(key, value);
+        key
+    }
+
+    fn get(&self, key: Key) -> Option<&Value> {
+        match self.pages.get(&key) {
+            Some(value) => Some(value),
+            None if self.removed.contains(&key) => None,
+            None => self.base.get(key),
+        }
+    }
+
+    /// Returns the page if it was visible, a copy of it when it comes from `base`
+    fn remove(&mut self, key: Key) -> Option<Value> {
+        let in_base = !self.removed.contains(&key) && self.base.has(key);
+        if in_base {
+            self.removed.insert(key);
+        }
+        match self.pages.remove(&key) {
+            Some(value) => Some(value),
+            None if in_base => self.base.get(key).cloned(),
+            None => None,
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        Box::new(
+            self.pages.iter().chain(
+                self.base.iter().filter(|(key, _)| {
+                    !self.pages.contains_key(key) && !self.removed.contains(key)
+                }),
+            ),
+        )
+    }
+}
+
+impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Extend<(Key, Value)>
+    for Store<Key, Value>
+{
+    fn extend<I: IntoIterator<Item = (Key, Value)>>(&mut self, pages: I) {
+        self.put_many(pages)
+    }
+}
+
+impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Reference<Key = Key>> Default
+    for Store<Key, Value>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A page references its children. What its values reference is reported
+/// separately by `value_refs`.
 impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Hash + Reference<Key = Key>> Reference
     for Page<Key, Value>
 {
@@ -101,6 +583,14 @@ impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Hash + Reference<Key = Key>> Re
         }
         refs
     }
+
+    /// Everything the values of the page reference, in entry order
+    fn value_refs(&self) -> Vec<Self::Key> {
+        self.list
+            .iter()
+            .flat_map(|page_data| page_data.value.refs())
+            .collect()
+    }
 }
 
 impl<Key: AsRef<[u8]> + Eq + Hash + Copy, Value: Clone> Clone for Store<Key, Value> {
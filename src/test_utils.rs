@@ -10,6 +10,7 @@ pub fn create_key(input: &[u8]) -> MSTKey {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TestValue {
     pub key: MSTKey,
     pub data: [u8; 4],
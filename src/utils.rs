@@ -1,12 +1,44 @@
-use sha2::digest::consts::U32;
-use sha2::digest::generic_array::GenericArray;
+use sha2::digest::typenum::Unsigned;
+use sha2::digest::{Output, OutputSizeUser};
 use sha2::{Digest, Sha256};
 use std::cmp::Ordering;
 use std::hash::Hash;
 
 use crate::Reference;
 
-pub type MSTKey = GenericArray<u8, U32>;
+pub type MSTKey = Output<Sha256>;
+
+/// A hash function a tree can be built on.
+///
+/// Pages are addressed by the hasher's output and `calc_level` counts the leading
+/// zero digits of it, so the output type doubles as the key type of the tree.
+/// Every fixed-output `Digest` (SHA-256, SHA-512, Blake3, ...) is an `MstHasher`,
+/// with `Output<D>` as its key.
+pub trait MstHasher: Default {
+    /// The digest output, used as the tree's key type
+    type Key: Copy + Default + Eq + Ord + Hash + AsRef<[u8]> + std::fmt::Debug;
+
+    /// Feeds bytes into the hasher
+    fn write_bytes(&mut self, bytes: &[u8]);
+
+    /// Consumes the hasher and returns the digest
+    fn finish(self) -> Self::Key;
+}
+
+impl<D: Digest + Default> MstHasher for D
+where
+    Output<D>: Copy,
+{
+    type Key = Output<D>;
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        Digest::update(self, bytes);
+    }
+
+    fn finish(self) -> Self::Key {
+        self.finalize()
+    }
+}
 
 pub fn compare<Key: Ord>(key: Key, key2: Key) -> std::cmp::Ordering {
     std::cmp::Ord::cmp(&key, &key2)
@@ -15,6 +47,21 @@ pub fn compare<Key: Ord>(key: Key, key2: Key) -> std::cmp::Ordering {
 // Define the Merge trait
 pub trait Merge {
     fn merge(self, other: Self) -> Self;
+
+    /// Whether this value marks its key as deleted, false unless overridden
+    ///
+    /// `remove` only takes a key out of one replica, so merging with a replica
+    /// that still holds the key brings it back. A replicated delete is instead
+    /// written as a tombstone: a value for which this returns true, inserted like
+    /// any other and made to win over live values in `merge`, for example by
+    /// comparing versions or timestamps. To the tree a tombstone is an ordinary
+    /// entry. It is hashed into the root, so replicas that have seen the same
+    /// deletes converge to the same root, and it is counted by `len` and yielded by
+    /// `iter`. `get_value`, `contains_key`, `get_many` and `to_list` skip it, so
+    /// the key reads as absent.
+    fn is_tombstone(&self) -> bool {
+        false
+    }
 }
 
 pub fn hash<Key: AsRef<[u8]>>(key: Key) -> impl Hash + IntoIterator<Item = u8> {
@@ -23,20 +70,154 @@ pub fn hash<Key: AsRef<[u8]>>(key: Key) -> impl Hash + IntoIterator<Item = u8> {
     hasher.finalize()
 }
 
+/// A hasher producing fixed-length `[u8; N]` keys from the first `N` bytes of `D`'s digest.
+///
+/// This lets a tree use a key space shorter than the digest (e.g. 20 bytes over
+/// SHA-256). Arrays order lexicographically just like `MSTKey`. `N` must not
+/// exceed `D`'s output size.
+#[derive(Default)]
+pub struct Truncated<D, const N: usize>(D);
+
+/// A fixed-length key, as produced by `Truncated`
+pub type FixedKey<const N: usize> = [u8; N];
+
+impl<D: Digest + Default, const N: usize> MstHasher for Truncated<D, N>
+where
+    [u8; N]: Default,
+{
+    type Key = FixedKey<N>;
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        Digest::update(&mut self.0, bytes);
+    }
+
+    fn finish(self) -> Self::Key {
+        const { assert!(N <= <D as OutputSizeUser>::OutputSize::USIZE) };
+        let digest = self.0.finalize();
+        let mut key = [0u8; N];
+        key.copy_from_slice(&digest[..N]);
+        key
+    }
+}
+
+/// Hashes a key with the given hasher
+pub fn hash_with<H: MstHasher, Key: AsRef<[u8]>>(key: Key) -> H::Key {
+    let mut hasher = H::default();
+    hasher.write_bytes(key.as_ref());
+    hasher.finish()
+}
+
+/// Longest raw key that fits in an `MSTKey`, see `raw_key`
+pub const MAX_RAW_KEY_LEN: usize = 31;
+
+/// Builds a key that keeps the original key bytes instead of hashing them.
+///
+/// By default a tree is keyed by the hash of the user's key, which scatters
+/// related keys. A raw key holds the bytes themselves, zero-padded, followed by
+/// their length plus one. Compared byte-wise (as `MSTKey`'s `Ord` and `TestValue`
+/// do), raw keys sort lexicographically by the original bytes, which makes range
+/// and prefix queries meaningful. Placement is unaffected: `calc_level` still
+/// hashes the key, so the tree stays balanced. The length byte is never zero, so
+/// no raw key is the all-zero key.
+///
+/// Raw and hashed keys order differently, so a tree should use one kind only.
+///
+/// # Returns
+///
+/// The key, or None if `bytes` is longer than `MAX_RAW_KEY_LEN`
+pub fn raw_key(bytes: &[u8]) -> Option<MSTKey> {
+    if bytes.len() > MAX_RAW_KEY_LEN {
+        return None;
+    }
+    let mut key = MSTKey::default();
+    key[..bytes.len()].copy_from_slice(bytes);
+    key[MAX_RAW_KEY_LEN] = bytes.len() as u8 + 1;
+    Some(key)
+}
+
+/// Recovers the original bytes of a key built by `raw_key`
+///
+/// # Returns
+///
+/// The bytes, or None if the key cannot have come from `raw_key`
+pub fn raw_key_bytes(key: &MSTKey) -> Option<&[u8]> {
+    let length = (key[MAX_RAW_KEY_LEN] as usize).checked_sub(1)?;
+    let (bytes, padding) = key[..MAX_RAW_KEY_LEN].split_at_checked(length)?;
+    padding.iter().all(|byte| *byte == 0).then_some(bytes)
+}
+
+/// Computes the level of a key from the leading zero bits of its SHA-256 hash,
+/// the same as `calc_level_base(key, 2)`
+///
+/// The level rule is versioned by `LEVEL_FORMAT_VERSION`
 pub fn calc_level<Key: AsRef<[u8]>>(key: Key) -> u32 {
-    let hash = hash(key);
-    let mut count = 0;
-    for byte in hash.into_iter() {
-        let string = &format!("0{:b} ", byte);
-        for c in string.chars() {
-            if c == '0' {
-                count += 1;
-            } else {
-                break;
-            }
+    calc_level_with::<Sha256, Key>(key)
+}
+
+/// Computes the level of a key from the leading zero bits of its hash under `H`
+pub fn calc_level_with<H: MstHasher, Key: AsRef<[u8]>>(key: Key) -> u32 {
+    calc_level_base_with::<H, Key>(key, DEFAULT_LEVEL_BASE)
+}
+
+/// The base of `calc_level`, giving pages two entries on average
+pub const DEFAULT_LEVEL_BASE: u32 = 2;
+
+/// Version of the rules that assign keys to levels and hash pages
+///
+/// Levels decide which page every entry lives in and page hashes decide every
+/// store key, so changing either rule changes the root hash of every tree.
+/// Version 1 counted zeros in a formatted binary string, which also counted its
+/// padding and separators; version 2 counts the hash's actual leading zero bits,
+/// version 3 caps the result at `MAX_LEVEL`, and version 4 hashes pages over the
+/// hashes of their values instead of the values themselves. Roots and stores
+/// written under one version cannot be reopened under another. Persist this next to a root and check it before reopening.
+pub const LEVEL_FORMAT_VERSION: u32 = 4;
+
+/// Highest level `calc_level` and its variants assign
+///
+/// A 32-byte hash has up to 256 leading zero bits, and a degenerate hasher or a
+/// crafted key can produce all of them. Levels above the cap would be no more
+/// useful for balance, since at base 2 even this one is reached with probability
+/// 2^-64, so keys beyond it share the top level instead. `Page.level` therefore
+/// never exceeds this value.
+pub const MAX_LEVEL: u32 = 64;
+
+/// Computes the level of a key as the number of leading zero base-`base` digits
+/// of its SHA-256 hash, capped at `MAX_LEVEL`
+///
+/// A key reaches level `n` with probability `base^-n`, so pages hold `base`
+/// entries on average: higher bases give shallower, wider trees. The base only
+/// changes where keys are placed, not what the tree holds, but placement is part
+/// of every page and so of the root hash. Trees over the same entries built with
+/// different bases have different roots, and a tree must keep the base it was
+/// built with.
+///
+/// # Panics
+///
+/// Panics if `base` is not a power of two of at least 2, so that a digit is a
+/// whole number of bits
+pub fn calc_level_base<Key: AsRef<[u8]>>(key: Key, base: u32) -> u32 {
+    calc_level_base_with::<Sha256, Key>(key, base)
+}
+
+/// Computes the level of a key like `calc_level_base`, hashing it with `H`
+pub fn calc_level_base_with<H: MstHasher, Key: AsRef<[u8]>>(key: Key, base: u32) -> u32 {
+    assert!(
+        base >= 2 && base.is_power_of_two(),
+        "level base must be a power of two of at least 2, got {}",
+        base
+    );
+    let bits_per_digit = base.trailing_zeros();
+
+    let hash = hash_with::<H, Key>(key);
+    let mut zero_bits = 0;
+    for &byte in hash.as_ref() {
+        zero_bits += byte.leading_zeros();
+        if byte != 0 {
+            break;
         }
     }
-    count
+    (zero_bits / bits_per_digit).min(MAX_LEVEL)
 }
 
 // Add this newtype wrapper
@@ -49,6 +230,12 @@ impl Event {
     }
 }
 
+impl Default for Event {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AsRef<[u8]> for Event {
     fn as_ref(&self) -> &[u8] {
         static TRUE_BYTES: [u8; 1] = [1];
@@ -69,7 +256,18 @@ impl Merge for Event {
     }
 }
 
-// Add this new trait to utils.rs
+/// The order a tree keeps its keys in, chosen by the value type.
+///
+/// Every operation that depends on key order goes through `compare_keys`: `insert`,
+/// `remove`, `get_value`, `split_at`, `join`, `merge`, `from_sorted`, iteration,
+/// the range and order-statistic queries, `diff` and proof verification. Placement
+/// does not: `calc_level` and page hashes always use the key bytes, so the tree
+/// stays balanced on the hash whatever the order. A different order yields a
+/// different (but equally canonical) tree for the same entries.
+///
+/// `compare_keys` must be a total order that returns `Equal` only for identical
+/// keys. Keys are the tree's hashes, so an order over a domain key the hash was
+/// computed from needs that key to be recoverable from the hash alone.
 pub trait KeyComparable {
     type Key;
 
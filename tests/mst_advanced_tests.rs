@@ -1 +1,1890 @@
- 
\ No newline at end of file
+use mst::MST;
+use mst::test_utils::{TestValue, create_key};
+
+/// Builds a tree holding the values for the given integer keys
+fn build_tree(keys: impl IntoIterator<Item = u32>) -> MST<TestValue> {
+    let mut tree = MST::new();
+    for i in keys {
+        let key = create_key(&i.to_be_bytes());
+        tree.insert(
+            key,
+            TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            },
+        );
+    }
+    tree
+}
+
+/// # Diff Tests
+///
+/// These tests verify that `diff` finds exactly the keys present in one tree
+/// but not the other, while skipping identical subtrees.
+mod diff_tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_only_differing_keys() {
+        // This test verifies that two 1000-key trees differing by 3 keys are
+        // diffed correctly while visiting only a handful of pages
+        let left = build_tree((0..1000).filter(|i| *i != 500));
+        let right = build_tree((0..1000).filter(|i| *i != 17 && *i != 901));
+
+        let (only_left, only_right, pages_visited) = left.diff_with_stats(&right);
+
+        let mut expected_left = vec![
+            create_key(&17u32.to_be_bytes()),
+            create_key(&901u32.to_be_bytes()),
+        ];
+        expected_left.sort();
+        assert_eq!(
+            only_left, expected_left,
+            "Keys missing from right should be reported"
+        );
+        assert_eq!(
+            only_right,
+            vec![create_key(&500u32.to_be_bytes())],
+            "Keys missing from left should be reported"
+        );
+
+        // Each of the 3 keys needs at most one root-to-leaf path on either side
+        let total_pages = left.store.iter().count() + right.store.iter().count();
+        let path_pages = 3 * (left.height() + right.height()) as usize;
+        assert!(
+            pages_visited <= path_pages,
+            "Diff should only open pages on differing paths, opened {} of {} (bound {})",
+            pages_visited,
+            total_pages,
+            path_pages
+        );
+    }
+
+    #[test]
+    fn test_diff_identical_and_empty_trees() {
+        // This test verifies that identical trees diff to nothing without
+        // opening a page, and that an empty tree differs by every key
+        let left = build_tree(0..100);
+        let right = build_tree(0..100);
+        assert_eq!(
+            left.diff_with_stats(&right),
+            (vec![], vec![], 0),
+            "Identical trees should be skipped at the root"
+        );
+
+        let empty = MST::new();
+        let (only_left, only_right) = left.diff(&empty);
+        assert_eq!(
+            only_left.len(),
+            100,
+            "Every key should be reported against an empty tree"
+        );
+        assert!(only_right.is_empty(), "Empty tree has no keys of its own");
+        let mut sorted = only_left.clone();
+        sorted.sort();
+        assert_eq!(only_left, sorted, "Diff keys should be in ascending order");
+    }
+
+    #[test]
+    fn test_first_divergence_pinpoints_the_changed_page() {
+        // This test verifies that the divergence point of a tree and a copy
+        // with one more key is the page on the new key's path that gained it
+        let base = build_tree(0..300);
+        assert_eq!(base.first_divergence(&build_tree(0..300)), None);
+        assert_eq!(MST::<TestValue>::new().first_divergence(&MST::new()), None);
+
+        let mut new_pages = 0;
+        for i in 300..340u32 {
+            let key = create_key(&i.to_be_bytes());
+            let grown = build_tree((0..300).chain([i]));
+
+            let (left, right) = base.first_divergence(&grown).unwrap();
+            let right_page = grown.store.get(right.unwrap()).unwrap();
+            assert!(
+                right_page.list.iter().any(|entry| entry.key == key),
+                "The grown side should be the page that gained key {}",
+                i
+            );
+            match left {
+                Some(left) => assert!(
+                    base.get_path(key)
+                        .iter()
+                        .any(|(page_key, _)| *page_key == left),
+                    "The other side should lie on the key's path"
+                ),
+                None => {
+                    // The key filled an empty child with a page of its own
+                    assert_eq!(right_page.list.len(), 1);
+                    new_pages += 1;
+                }
+            }
+        }
+        assert!(new_pages > 0, "Some keys should land in an empty child");
+    }
+
+    #[test]
+    fn test_first_divergence_reports_missing_subtrees() {
+        // This test verifies that a subtree present on one side only is
+        // reported with None on the other side
+        let tree = build_tree(0..50);
+        let empty = MST::new();
+        assert_eq!(tree.first_divergence(&empty), Some((tree.root, None)));
+        assert_eq!(empty.first_divergence(&tree), Some((None, tree.root)));
+
+        // Changing a value diverges at the page holding it, on both sides
+        let key = create_key(&7u32.to_be_bytes());
+        let mut changed = build_tree(0..50);
+        changed.insert(
+            key,
+            TestValue {
+                key,
+                data: [99, 0, 0, 0],
+            },
+        );
+        let holder =
+            |tree: &MST<TestValue>| tree.get_path(key).last().map(|(page_key, _)| *page_key);
+        assert_eq!(
+            tree.first_divergence(&changed),
+            Some((holder(&tree), holder(&changed)))
+        );
+    }
+}
+
+/// # Set Operation Tests
+///
+/// These tests verify that `union`, `intersection` and `difference` build the
+/// canonical tree of the resulting key set.
+mod set_operation_tests {
+    use super::*;
+
+    /// Builds a tree like `build_tree`, tagging every value with `tag`
+    fn tagged_tree(keys: impl IntoIterator<Item = u32>, tag: u8) -> MST<TestValue> {
+        let mut tree = MST::new();
+        for i in keys {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, tag, 0, 0],
+                },
+            );
+        }
+        tree
+    }
+
+    #[test]
+    fn test_set_operations_are_canonical() {
+        // This test verifies that each operation yields the same tree as
+        // inserting the resulting keys directly, with merged values on shared keys
+        let in_left = |i: &u32| !i.is_multiple_of(10);
+        let in_right = |i: &u32| i % 10 != 5;
+        let left = tagged_tree((0..1000).filter(in_left), 1);
+        let right = tagged_tree((0..1000).filter(in_right), 2);
+
+        // TestValue's merge keeps the second value, so shared keys carry tag 2
+        let mut expected_union = tagged_tree((0..1000).filter(in_left), 1);
+        for i in (0..1000).filter(in_right) {
+            let key = create_key(&i.to_be_bytes());
+            expected_union.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 2, 0, 0],
+                },
+            );
+        }
+        let expected_intersection = tagged_tree((0..1000).filter(|i| in_left(i) && in_right(i)), 2);
+        let expected_difference = tagged_tree((0..1000).filter(|i| in_left(i) && !in_right(i)), 1);
+
+        for (name, result, expected) in [
+            ("union", left.union(&right), &expected_union),
+            (
+                "intersection",
+                left.intersection(&right),
+                &expected_intersection,
+            ),
+            ("difference", left.difference(&right), &expected_difference),
+        ] {
+            assert_eq!(result.root, expected.root, "{} should be canonical", name);
+            assert_eq!(result.to_list(), expected.to_list(), "{} entries", name);
+            assert_eq!(result.len(), expected.len(), "{} length", name);
+        }
+        assert_eq!(left.len(), 900, "Operands should be left alone");
+    }
+
+    #[test]
+    fn test_set_operations_on_identical_and_empty_trees() {
+        // This test verifies the edge cases where one side is empty or both
+        // sides are the same tree
+        let tree = build_tree(0..200);
+        let empty = MST::<TestValue>::new();
+
+        assert_eq!(tree.intersection(&tree).root, tree.root);
+        assert!(tree.difference(&tree).is_empty());
+        assert_eq!(tree.union(&tree).root, tree.root);
+
+        assert_eq!(tree.union(&empty).root, tree.root);
+        assert_eq!(empty.union(&tree).root, tree.root);
+        assert!(tree.intersection(&empty).is_empty());
+        assert!(empty.intersection(&tree).is_empty());
+        assert_eq!(tree.difference(&empty).root, tree.root);
+        assert!(empty.difference(&tree).is_empty());
+    }
+}
+
+/// # Proof Tests
+///
+/// These tests verify that inclusion proofs record a hash-linked path from the
+/// root to the proven entry.
+mod proof_tests {
+    use super::*;
+    use mst::{
+        InclusionProof, MSTKey, Page, PageData, ProofPage, hash_page, hash_value,
+        verify_absence_proof, verify_proof,
+    };
+    use sha2::Sha256;
+
+    #[test]
+    fn test_get_path() {
+        // This test verifies that get_path follows linked pages from the root to
+        // the page holding the key, ends where an absent key's search stops, and
+        // is empty for an empty tree
+        let tree = build_tree(0..500);
+        let links = |page: &Page<MSTKey, TestValue>| -> Vec<MSTKey> {
+            page.low
+                .into_iter()
+                .chain(page.list.iter().filter_map(|entry| entry.next))
+                .collect()
+        };
+
+        for i in (0..1000u32).step_by(13) {
+            let key = create_key(&i.to_be_bytes());
+            let path = tree.get_path(key);
+            assert_eq!(
+                Some(path[0].0),
+                tree.root,
+                "The path should start at the root"
+            );
+            for pair in path.windows(2) {
+                assert!(
+                    links(pair[0].1).contains(&pair[1].0),
+                    "Each page should be a child of the one before it"
+                );
+            }
+
+            let (_, last) = path[path.len() - 1];
+            let holds_key = last.list.iter().any(|entry| entry.key == key);
+            assert_eq!(holds_key, i < 500, "Only present keys end on their page");
+            if i >= 500 {
+                assert_eq!(
+                    path.len(),
+                    tree.prove_absence(key).unwrap().pages.len(),
+                    "An absent key's path should match its absence proof"
+                );
+            }
+        }
+
+        assert!(
+            MST::<TestValue>::new()
+                .get_path(create_key(b"any"))
+                .is_empty(),
+            "An empty tree has no path"
+        );
+    }
+
+    #[test]
+    fn test_prove_records_hash_linked_path() {
+        // This test verifies that each proof starts at the root, that every page
+        // is referenced by its parent, and that the last page holds the entry
+        let tree = build_tree(0..500);
+
+        for i in (0..500u32).step_by(7) {
+            let key = create_key(&i.to_be_bytes());
+            let proof = tree.prove(key).expect("Present key should have a proof");
+
+            assert_eq!(
+                proof.entry.key, key,
+                "Proof should be for the requested key"
+            );
+            assert_eq!(
+                Some(proof.entry.value),
+                tree.get_value(key),
+                "Proof should carry the stored value"
+            );
+            assert_eq!(
+                Some(proof.pages[0].hash::<Sha256>()),
+                tree.root,
+                "Proof should start at the root page"
+            );
+
+            for pair in proof.pages.windows(2) {
+                let child = pair[1].hash::<Sha256>();
+                let parent = &pair[0];
+                let referenced = parent.low == Some(child)
+                    || parent.list.iter().any(|entry| entry.next == Some(child));
+                assert!(
+                    referenced,
+                    "Each page should be a child of the previous one"
+                );
+            }
+
+            let last = proof.pages.last().unwrap();
+            assert!(
+                last.list.iter().any(|entry| entry.key == key),
+                "Last page should contain the proven entry"
+            );
+        }
+    }
+
+    #[test]
+    fn test_prove_absent_key() {
+        // This test verifies that absent keys and empty trees have no proof
+        let tree = build_tree(0..100);
+        assert!(
+            tree.prove(create_key(b"absent")).is_none(),
+            "Absent key should have no proof"
+        );
+        assert!(
+            MST::<TestValue>::new().prove(create_key(b"any")).is_none(),
+            "Empty tree should have no proofs"
+        );
+    }
+
+    #[test]
+    fn test_verify_proof_accepts_valid_proofs() {
+        // This test verifies that proofs produced by prove verify against the root
+        let tree = build_tree(0..500);
+
+        for i in (0..500u32).step_by(11) {
+            let key = create_key(&i.to_be_bytes());
+            let value = tree.get_value(key).unwrap();
+            let proof = tree.prove(key).unwrap();
+            assert!(
+                verify_proof(tree.root, key, &value, &proof),
+                "Valid proof for key {} should verify",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_proof_pages_carry_value_hashes() {
+        // This test verifies that hash_value is the SHA-256 of the value bytes,
+        // that every entry on a proof path carries the hash of its stored value
+        // rather than the value, and that those pages hash like the stored ones
+        let tree = build_tree(0..500);
+        let key = create_key(&42u32.to_be_bytes());
+        assert_eq!(
+            hash_value(&tree.get_value(key).unwrap()),
+            create_key(&[42, 0, 0, 0]),
+            "The value hash should be the SHA-256 of the value bytes"
+        );
+
+        let proof = tree.prove(key).unwrap();
+        for (page, (_, stored)) in proof.pages.iter().zip(tree.get_path(key)) {
+            assert_eq!(
+                page.hash::<Sha256>(),
+                hash_page(stored),
+                "A proof page should hash like the page it was built from"
+            );
+            for (entry, stored) in page.list.iter().zip(&stored.list) {
+                assert_eq!(entry.key, stored.key);
+                assert_eq!(
+                    entry.value_hash,
+                    hash_value(&stored.value),
+                    "Each entry should carry the hash of its value"
+                );
+            }
+        }
+        assert!(verify_proof(tree.root, key, &proof.entry.value, &proof));
+    }
+
+    #[test]
+    fn test_verify_proof_rejects_tampering() {
+        // This test verifies that wrong roots, keys, values, or tampered pages
+        // cause verification to fail
+        let tree = build_tree(0..500);
+        let key = create_key(&42u32.to_be_bytes());
+        let value = tree.get_value(key).unwrap();
+        let proof = tree.prove(key).unwrap();
+
+        let other_root = build_tree(0..499).root;
+        assert!(
+            !verify_proof(other_root, key, &value, &proof),
+            "Proof should not verify against another root"
+        );
+
+        let other_key = create_key(&43u32.to_be_bytes());
+        assert!(
+            !verify_proof(tree.root, other_key, &value, &proof),
+            "Proof should not verify for another key"
+        );
+
+        let mut wrong_value = value;
+        wrong_value.data[3] ^= 0xff;
+        assert!(
+            !verify_proof(tree.root, key, &wrong_value, &proof),
+            "Proof should not verify for another value"
+        );
+
+        // Changing a value hash anywhere on the path breaks the hash chain
+        for depth in 0..proof.pages.len() {
+            let mut tampered = proof.clone();
+            tampered.pages[depth].list[0].value_hash[3] ^= 0xff;
+            assert!(
+                !verify_proof(tree.root, key, &value, &tampered),
+                "Tampered page at depth {} should be rejected",
+                depth
+            );
+        }
+
+        let empty = InclusionProof {
+            pages: vec![],
+            entry: proof.entry.clone(),
+        };
+        assert!(
+            !verify_proof(tree.root, key, &value, &empty),
+            "Proof without pages should be rejected"
+        );
+    }
+
+    #[test]
+    fn test_verify_proof_rejects_misordered_siblings() {
+        // This test verifies that a page whose entries are out of order is
+        // rejected even when its hash matches the claimed root
+        let (low_key, high_key) = {
+            let a = create_key(&1u32.to_be_bytes());
+            let b = create_key(&2u32.to_be_bytes());
+            if a < b { (a, b) } else { (b, a) }
+        };
+        let entry = |key| PageData {
+            key,
+            value: TestValue { key, data: [0; 4] },
+            next: None,
+        };
+        let page = Page {
+            level: 32,
+            subtree_size: 2,
+            low: None,
+            list: vec![entry(high_key), entry(low_key)],
+        };
+        let root = hash_page(&page);
+        let proof = InclusionProof {
+            pages: vec![ProofPage::from_page::<Sha256, _>(&page)],
+            entry: entry(low_key),
+        };
+
+        assert!(
+            !verify_proof(Some(root), low_key, &proof.entry.value, &proof),
+            "Misordered siblings should be rejected"
+        );
+    }
+
+    #[test]
+    fn test_prove_absence_between_keys() {
+        // This test verifies that an absent key is proven missing with its
+        // neighbours as the flanking entries
+        let tree = build_tree((0..500).filter(|i| i % 5 != 0));
+        let keys: Vec<MSTKey> = tree.iter().map(|entry| entry.key).collect();
+
+        for i in (0..500u32).step_by(5) {
+            let key = create_key(&i.to_be_bytes());
+            let proof = tree
+                .prove_absence(key)
+                .expect("Absent key should have a proof");
+            assert!(
+                verify_absence_proof(tree.root, key, &proof),
+                "Absence proof for key {} should verify",
+                i
+            );
+
+            let position = keys.partition_point(|k| *k < key);
+            assert_eq!(
+                proof.predecessor.as_ref().map(|entry| entry.key),
+                position.checked_sub(1).map(|p| keys[p]),
+                "Predecessor should be the next smaller key"
+            );
+            assert_eq!(
+                proof.successor.as_ref().map(|entry| entry.key),
+                keys.get(position).copied(),
+                "Successor should be the next larger key"
+            );
+        }
+    }
+
+    #[test]
+    fn test_prove_absence_edges() {
+        // This test verifies absence proofs below the smallest key, above the
+        // largest key, in an empty tree, and that present keys have none
+        let tree = build_tree(0..200);
+        let below = MSTKey::clone_from_slice(&[0; 32]);
+        let above = MSTKey::clone_from_slice(&[0xff; 32]);
+
+        let proof = tree.prove_absence(below).unwrap();
+        assert!(
+            proof.predecessor.is_none(),
+            "Nothing should precede the smallest key"
+        );
+        assert!(proof.successor.is_some(), "Smallest key should follow");
+        assert!(
+            verify_absence_proof(tree.root, below, &proof),
+            "Proof below range should verify"
+        );
+
+        let proof = tree.prove_absence(above).unwrap();
+        assert!(proof.predecessor.is_some(), "Largest key should precede");
+        assert!(
+            proof.successor.is_none(),
+            "Nothing should follow the largest key"
+        );
+        assert!(
+            verify_absence_proof(tree.root, above, &proof),
+            "Proof above range should verify"
+        );
+
+        let empty = MST::<TestValue>::new();
+        let proof = empty.prove_absence(below).unwrap();
+        assert!(
+            verify_absence_proof(empty.root, below, &proof),
+            "Any key should be provably absent from an empty tree"
+        );
+
+        let present = create_key(&7u32.to_be_bytes());
+        assert!(
+            tree.prove_absence(present).is_none(),
+            "Present key should have no absence proof"
+        );
+    }
+
+    #[test]
+    fn test_verify_absence_proof_rejects_forgeries() {
+        // This test verifies that absence proofs cannot be reused for other keys,
+        // other roots, or with altered neighbours
+        let tree = build_tree((0..300).filter(|i| *i != 100));
+        let key = create_key(&100u32.to_be_bytes());
+        let proof = tree.prove_absence(key).unwrap();
+
+        let present = create_key(&101u32.to_be_bytes());
+        assert!(
+            !verify_absence_proof(tree.root, present, &proof),
+            "Proof should not show a present key to be absent"
+        );
+        assert!(
+            !verify_absence_proof(build_tree(0..300).root, key, &proof),
+            "Proof should not verify against a tree containing the key"
+        );
+
+        let mut forged = proof.clone();
+        forged.predecessor = None;
+        assert!(
+            !verify_absence_proof(tree.root, key, &forged),
+            "Dropping a neighbour should be rejected"
+        );
+
+        let mut forged = proof.clone();
+        forged.pages.pop();
+        assert!(
+            !verify_absence_proof(tree.root, key, &forged),
+            "Truncated search path should be rejected"
+        );
+    }
+}
+
+/// # Encoding Tests
+///
+/// These tests verify that `hash_page` hashes the `encode_page` layout with
+/// each value replaced by its hash, and that `decode_page` inverts the encoding.
+mod encoding_tests {
+    use super::*;
+    use mst::{
+        LEVEL_FORMAT_VERSION, MSTKey, MstError, Page, decode_page, encode_page, hash_page,
+        hash_value,
+    };
+    use sha2::{Digest, Sha256};
+
+    fn decode_test_value(key: &MSTKey, bytes: &[u8]) -> Option<TestValue> {
+        Some(TestValue {
+            key: *key,
+            data: bytes.try_into().ok()?,
+        })
+    }
+
+    #[test]
+    fn test_encoding_is_hashed_and_round_trips() {
+        // This test verifies that every page of a tree hashes to the SHA-256 of
+        // its encoding with values replaced by their hashes, and decodes back to
+        // a page with the same hash
+        let tree = build_tree(0..300);
+
+        fn push_optional_key(bytes: &mut Vec<u8>, key: Option<&MSTKey>) {
+            match key {
+                Some(key) => {
+                    bytes.push(1);
+                    bytes.extend_from_slice(key);
+                }
+                None => bytes.push(0),
+            }
+        }
+
+        for (key, page) in tree.store.iter() {
+            let mut hashed = Vec::new();
+            hashed.extend_from_slice(&page.level.to_be_bytes());
+            hashed.extend_from_slice(&page.subtree_size.to_be_bytes());
+            push_optional_key(&mut hashed, page.low.as_ref());
+            hashed.extend_from_slice(&(page.list.len() as u32).to_be_bytes());
+            for entry in &page.list {
+                hashed.extend_from_slice(&entry.key);
+                hashed.extend_from_slice(&hash_value(&entry.value));
+                push_optional_key(&mut hashed, entry.next.as_ref());
+            }
+            assert_eq!(
+                hash_page(page),
+                Sha256::digest(&hashed),
+                "hash_page should hash the encoding over value hashes"
+            );
+            assert_eq!(*key, hash_page(page), "Stored key should match the hash");
+
+            let bytes = encode_page(page);
+
+            let decoded: Page<MSTKey, TestValue> =
+                decode_page(&bytes, decode_test_value).expect("Encoded page should decode");
+            assert_eq!(
+                hash_page(&decoded),
+                hash_page(page),
+                "Round trip should keep the hash"
+            );
+            assert_eq!(
+                encode_page(&decoded),
+                bytes,
+                "Round trip should keep the bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_bytes() {
+        // This test verifies that truncated, padded, or corrupted encodings are
+        // reported as errors
+        let tree = build_tree(0..50);
+        let page = tree.get(tree.root.unwrap()).unwrap();
+        let bytes = encode_page(page);
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert_eq!(
+            decode_page::<MSTKey, TestValue, _>(truncated, decode_test_value).err(),
+            Some(MstError::InvalidPageEncoding),
+            "Truncated encoding should be rejected"
+        );
+
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(
+            decode_page::<MSTKey, TestValue, _>(&padded, decode_test_value).err(),
+            Some(MstError::InvalidPageEncoding),
+            "Trailing bytes should be rejected"
+        );
+
+        // Byte 12 is the low pointer's presence flag, after the level and size
+        let mut corrupted = bytes.clone();
+        corrupted[12] = 7;
+        assert_eq!(
+            decode_page::<MSTKey, TestValue, _>(&corrupted, decode_test_value).err(),
+            Some(MstError::InvalidPageEncoding),
+            "Invalid presence flag should be rejected"
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        // This test verifies that a saved tree loads with the same root, entries
+        // and level base, that orphaned pages are not written, and that a
+        // corrupted file is rejected
+        let path = std::env::temp_dir().join(format!("mst-save-{}.bin", std::process::id()));
+        let mut tree = build_tree(0..300).with_level_base(4);
+        for i in 300..320u32 {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(key, TestValue { key, data: [1; 4] });
+        }
+        assert!(
+            tree.store.len() > tree.stats().pages,
+            "Inserts leave orphans"
+        );
+        tree.save_to_path(&path).unwrap();
+
+        let loaded = MST::<TestValue>::load_from_path(&path, decode_test_value).unwrap();
+        assert_eq!(loaded.root, tree.root);
+        assert_eq!(loaded.to_list(), tree.to_list());
+        assert_eq!(loaded.level_base(), 4);
+        assert_eq!(
+            loaded.store.len(),
+            tree.stats().pages,
+            "Only reachable pages"
+        );
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 10;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        let err = MST::<TestValue>::load_from_path(&path, decode_test_value).err();
+        assert_eq!(
+            err.map(|err| err.kind()),
+            Some(std::io::ErrorKind::InvalidData),
+            "A corrupted page should be rejected"
+        );
+
+        MST::<TestValue>::new().save_to_path(&path).unwrap();
+        let empty = MST::<TestValue>::load_from_path(&path, decode_test_value).unwrap();
+        assert!(empty.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_rejects_other_files_and_versions() {
+        // This test verifies that a saved tree starts with the magic number and
+        // the level format version, and that a file lacking the magic or saved
+        // under another version is rejected
+        let path = std::env::temp_dir().join(format!("mst-header-{}.bin", std::process::id()));
+        build_tree(0..50).save_to_path(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[..4], b"MST\0");
+        assert_eq!(&bytes[4..8], &LEVEL_FORMAT_VERSION.to_be_bytes());
+
+        let load = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            MST::<TestValue>::load_from_path(&path, decode_test_value)
+                .err()
+                .map(|err| err.kind())
+        };
+        let mut other_version = bytes.clone();
+        other_version[4..8].copy_from_slice(&(LEVEL_FORMAT_VERSION - 1).to_be_bytes());
+        assert_eq!(
+            load(&other_version),
+            Some(std::io::ErrorKind::InvalidData),
+            "Another level format version should be rejected"
+        );
+        assert_eq!(
+            load(&bytes[4..]),
+            Some(std::io::ErrorKind::InvalidData),
+            "A file without the magic should be rejected"
+        );
+        assert_eq!(load(&bytes), None, "The original file still loads");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+/// # Bloom Filter Tests
+///
+/// These tests verify that `bloom_summary` never rules out a present key and
+/// that the filter survives a round trip through its encoding.
+mod bloom_tests {
+    use super::*;
+    use mst::{BloomFilter, MstError};
+
+    #[test]
+    fn test_bloom_summary_has_no_false_negatives() {
+        // This test verifies that every key of the tree may be contained, while
+        // most absent keys are ruled out at about 10 bits per key
+        let tree = build_tree(0..1000);
+        let filter = tree.bloom_summary(10_000, 7);
+
+        for i in 0..1000u32 {
+            assert!(
+                filter.maybe_contains(create_key(&i.to_be_bytes())),
+                "Key {} should be reported as maybe present",
+                i
+            );
+        }
+
+        let false_positives = (1000..11_000u32)
+            .filter(|i| filter.maybe_contains(create_key(&i.to_be_bytes())))
+            .count();
+        assert!(
+            false_positives < 300,
+            "Expected roughly 1% false positives, got {} in 10000",
+            false_positives
+        );
+
+        let empty = MST::<TestValue>::new().bloom_summary(64, 3);
+        assert!(!empty.maybe_contains(create_key(&[0])));
+    }
+
+    #[test]
+    fn test_bloom_filter_round_trip() {
+        // This test verifies that an encoded filter decodes to the same filter,
+        // and that malformed encodings are rejected
+        let filter = build_tree(0..100).bloom_summary(1001, 5);
+        let bytes = filter.to_bytes();
+        let decoded = BloomFilter::from_bytes(&bytes).expect("Encoding should decode");
+        assert_eq!(decoded, filter);
+        assert_eq!((decoded.bits(), decoded.hashes()), (1001, 5));
+
+        assert_eq!(
+            BloomFilter::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(MstError::InvalidBloomEncoding),
+            "Truncated encoding should be rejected"
+        );
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert_eq!(
+            BloomFilter::from_bytes(&padded),
+            Err(MstError::InvalidBloomEncoding),
+            "Trailing bytes should be rejected"
+        );
+        assert_eq!(
+            BloomFilter::from_bytes(&[0; 9]),
+            Err(MstError::InvalidBloomEncoding),
+            "An empty filter should be rejected"
+        );
+    }
+}
+
+/// # Garbage Collection Tests
+///
+/// These tests verify that `gc` frees unreachable pages while keeping every
+/// page of the live roots.
+mod gc_tests {
+    use super::*;
+    use mst::MSTKey;
+
+    /// A value pointing at the root of another tree in the same store, or at nothing
+    #[derive(Debug, Copy, Clone, PartialEq, Hash)]
+    struct LinkValue {
+        key: MSTKey,
+        target: Option<MSTKey>,
+    }
+
+    impl AsRef<[u8]> for LinkValue {
+        fn as_ref(&self) -> &[u8] {
+            self.target.as_ref().map_or(&[], |target| target.as_slice())
+        }
+    }
+
+    impl mst::Reference for LinkValue {
+        type Key = MSTKey;
+        fn refs(&self) -> Vec<Self::Key> {
+            self.target.into_iter().collect()
+        }
+    }
+
+    impl mst::Merge for LinkValue {
+        fn merge(self, other: Self) -> Self {
+            other
+        }
+    }
+
+    impl mst::KeyComparable for LinkValue {
+        type Key = MSTKey;
+
+        fn compare_keys(key1: &Self::Key, key2: &Self::Key) -> std::cmp::Ordering {
+            key1.cmp(key2)
+        }
+    }
+
+    #[test]
+    fn test_gc_follows_value_references() {
+        // This test verifies that gc keeps a tree whose root a live value refers
+        // to, and frees it once no value does
+        let mut blob: MST<LinkValue> = MST::new();
+        for i in 0..100u32 {
+            let key = create_key(&i.to_be_bytes());
+            blob.insert(key, LinkValue { key, target: None });
+        }
+        let blob_root = blob.root.unwrap();
+        let blob_pages = blob.store.reachable_set(blob_root);
+
+        let mut tree = MST::with_store(None, blob.store);
+        let key = create_key(b"link");
+        tree.insert(
+            key,
+            LinkValue {
+                key,
+                target: Some(blob_root),
+            },
+        );
+        tree.gc(&[]);
+        assert!(
+            blob_pages.iter().all(|page| tree.store.has(*page)),
+            "The referenced tree should be kept"
+        );
+        assert_eq!(
+            MST::with_store(Some(blob_root), tree.store.clone()).len(),
+            100
+        );
+
+        tree.insert(key, LinkValue { key, target: None });
+        tree.gc(&[]);
+        assert!(
+            blob_pages.iter().all(|page| !tree.store.has(*page)),
+            "An unreferenced tree should be freed"
+        );
+    }
+
+    #[test]
+    fn test_tree_walks_ignore_value_references() {
+        // This test verifies that walks of the tree itself do not treat what a
+        // value references as one of the tree's pages
+        let mut tree: MST<LinkValue> = MST::new();
+        let key = create_key(b"link");
+        let target = create_key(b"not a page");
+        tree.insert(
+            key,
+            LinkValue {
+                key,
+                target: Some(target),
+            },
+        );
+        let root = tree.root.unwrap();
+        assert!(
+            tree.store.missing_set(root).is_empty(),
+            "A dangling value reference is not a missing page"
+        );
+        assert_eq!(tree.page_keys().collect::<Vec<_>>(), vec![root]);
+        assert_eq!(tree.stats().pages, 1);
+    }
+
+    #[test]
+    fn test_gc_keeps_live_roots() {
+        // This test verifies that gc frees pages, keeps every present page of
+        // the live roots, and always keeps the current root
+        let mut tree = build_tree(0..200);
+        let old_root = tree.root.unwrap();
+        for i in 200..400u32 {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        let old_pages = tree.store.reachable_set(old_root);
+        let current_pages = tree.store.reachable_set(tree.root.unwrap());
+        let before = tree.store.iter().count();
+
+        let freed = tree.gc(&[old_root]);
+        let after = tree.store.iter().count();
+
+        assert!(freed > 0, "Abandoned pages should be freed");
+        assert_eq!(
+            before - after,
+            freed,
+            "Freed count should match the store shrinking"
+        );
+        assert!(
+            old_pages.iter().all(|key| tree.store.has(*key)),
+            "Pages of a live historical root should be kept"
+        );
+        assert!(
+            current_pages.iter().all(|key| tree.store.has(*key)),
+            "Pages of the current root should be kept"
+        );
+        assert_eq!(
+            after,
+            old_pages.union(&current_pages).count(),
+            "Only pages of live roots should remain"
+        );
+    }
+
+    #[test]
+    fn test_gc_without_live_roots_keeps_current_tree() {
+        // This test verifies that gc with no live roots still keeps the whole
+        // current tree readable
+        let mut tree = build_tree(0..300);
+        let expected = tree.to_list();
+
+        tree.gc(&[]);
+
+        assert_eq!(
+            tree.to_list(),
+            expected,
+            "Current tree should be intact after gc"
+        );
+        assert!(
+            tree.store.missing_set(tree.root.unwrap()).is_empty(),
+            "No page of the current tree should be missing"
+        );
+        assert_eq!(tree.gc(&[]), 0, "A second gc should have nothing to free");
+    }
+
+    #[test]
+    fn test_page_keys_enumerates_reachable_pages() {
+        // This test verifies that page_keys yields each reachable page once,
+        // root first, and terminates on a store with a cycle
+        let mut tree = build_tree(0..400);
+        let keys: Vec<_> = tree.page_keys().collect();
+        let reachable = tree.store.reachable_set(tree.root.unwrap());
+        assert_eq!(keys.first(), tree.root.as_ref(), "The root comes first");
+        assert_eq!(keys.len(), reachable.len(), "No page should repeat");
+        assert_eq!(
+            keys.iter()
+                .copied()
+                .collect::<std::collections::HashSet<_>>(),
+            reachable
+        );
+        assert_eq!(keys.len(), tree.stats().pages);
+
+        // Point a leaf back at the root
+        let leaf = *keys.last().unwrap();
+        let mut page = tree.store.get(leaf).cloned().unwrap();
+        page.low = tree.root;
+        tree.store.put(leaf, page);
+        assert_eq!(
+            tree.page_keys().count(),
+            keys.len(),
+            "A cycle should not loop"
+        );
+
+        assert_eq!(MST::<TestValue>::new().page_keys().count(), 0);
+    }
+
+    #[test]
+    fn test_compact_leaves_only_the_canonical_tree() {
+        // This test verifies that compact keeps the entries, ends at the
+        // canonical root for them, and leaves only that tree's pages
+        let mut tree = build_tree((0..400).rev());
+        for i in (0..400u32).step_by(3) {
+            tree.remove(create_key(&i.to_be_bytes()));
+        }
+        let expected = tree.to_list();
+        let canonical = build_tree((0..400).filter(|i| i % 3 != 0));
+
+        tree.compact();
+
+        assert_eq!(
+            tree.root, canonical.root,
+            "Compacted root should be canonical"
+        );
+        assert_eq!(tree.to_list(), expected);
+        assert_eq!(tree.len(), expected.len());
+        assert_eq!(
+            tree.store.iter().count(),
+            tree.store.reachable_set(tree.root.unwrap()).len(),
+            "Only pages of the compacted tree should remain"
+        );
+
+        let mut empty = MST::<TestValue>::new();
+        empty.compact();
+        assert!(empty.is_empty());
+    }
+}
+
+/// # Snapshot Tests
+///
+/// These tests verify that a snapshot keeps reading the version of the tree it
+/// was taken from while the live tree changes.
+mod snapshot_tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_is_unaffected_by_later_writes() {
+        // This test verifies that inserts and removals on the live tree leave
+        // what an earlier snapshot reads unchanged, including after gc
+        let mut tree = build_tree(0..200);
+        let snapshot = tree.snapshot();
+        let expected = tree.to_list();
+
+        for i in 200..400u32 {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        let removed = create_key(&7u32.to_be_bytes());
+        assert!(tree.remove(removed).is_some());
+
+        let view = tree.view(snapshot);
+        assert_eq!(view.root(), snapshot);
+        assert_eq!(
+            view.to_list(),
+            expected,
+            "Snapshot should read its own version"
+        );
+        assert_eq!(view.iter().count(), 200);
+        assert!(
+            view.get_value(removed).is_some(),
+            "Removed later, still in the snapshot"
+        );
+        assert_eq!(view.get_value(create_key(&300u32.to_be_bytes())), None);
+        assert_eq!(tree.get_value(removed), None, "Live tree should not see it");
+
+        tree.gc(&[snapshot.unwrap()]);
+        assert_eq!(
+            tree.view(snapshot).to_list(),
+            expected,
+            "gc should keep live snapshots"
+        );
+        assert_eq!(tree.view(tree.snapshot()).to_list(), tree.to_list());
+        assert!(tree.view(None).to_list().is_empty());
+    }
+}
+
+/// # Stats Tests
+///
+/// These tests verify that `stats`, `byte_size` and `entries_at_level` describe
+/// the pages reachable from the root.
+mod stats_tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_counts_reachable_pages() {
+        // This test verifies that stats covers every entry, counts only
+        // reachable pages even when old versions linger in the store, and
+        // keeps its fan-out figures consistent
+        let tree = build_tree(0..400);
+        let stats = tree.stats();
+
+        assert_eq!(stats.entries, 400, "Every entry should be counted once");
+        assert_eq!(
+            stats.pages,
+            tree.store.reachable_set(tree.root.unwrap()).len(),
+            "Only reachable pages should be counted"
+        );
+        assert!(
+            stats.pages < tree.store.iter().count(),
+            "Orphaned pages should exist but not be counted"
+        );
+        assert!(stats.min_fanout >= 1 && stats.min_fanout <= stats.max_fanout);
+        assert_eq!(
+            stats.average_fanout,
+            stats.entries as f64 / stats.pages as f64,
+            "Average fan-out should be entries per page"
+        );
+        assert!(stats.distinct_levels >= 1);
+        assert_eq!(
+            stats.max_level,
+            tree.store.get(tree.root.unwrap()).unwrap().level,
+            "The root page should have the highest level"
+        );
+
+        assert_eq!(
+            MST::<TestValue>::new().stats(),
+            mst::TreeStats::default(),
+            "An empty tree has all-zero stats"
+        );
+    }
+
+    #[test]
+    fn test_byte_size_sums_reachable_encodings() {
+        // This test verifies that byte_size is the length of the encodings of the
+        // reachable pages, each counted once, and 0 for an empty tree
+        let tree = build_tree(0..400);
+        let expected: usize = tree
+            .store
+            .reachable_set(tree.root.unwrap())
+            .into_iter()
+            .map(|key| mst::encode_page(tree.store.get(key).unwrap()).len())
+            .sum();
+        assert_eq!(tree.byte_size(), expected);
+
+        // Orphans left by an update are not counted
+        let mut updated = tree.clone();
+        let key = create_key(&7u32.to_be_bytes());
+        updated.insert(
+            key,
+            TestValue {
+                key,
+                data: [7, 7, 0, 0],
+            },
+        );
+        assert!(updated.store.len() > updated.stats().pages);
+        assert_eq!(updated.byte_size(), tree.byte_size());
+
+        assert_eq!(MST::<TestValue>::new().byte_size(), 0);
+    }
+
+    #[test]
+    fn test_entries_at_level_partitions_entries() {
+        // This test verifies that entries_at_level returns the entries whose keys
+        // have that level, in key order, together covering the whole tree once,
+        // and nothing for a level no page occupies
+        let tree = build_tree(0..400);
+        let max_level = tree.stats().max_level;
+        let mut total = 0;
+        for level in 0..=max_level {
+            let entries = tree.entries_at_level(level);
+            assert!(
+                entries
+                    .iter()
+                    .all(|entry| mst::calc_level(entry.key) == level)
+            );
+            assert!(entries.windows(2).all(|pair| pair[0].key < pair[1].key));
+            total += entries.len();
+        }
+        assert_eq!(total, tree.len(), "Every entry is at exactly one level");
+
+        assert!(tree.entries_at_level(max_level + 1).is_empty());
+        assert!(MST::<TestValue>::new().entries_at_level(0).is_empty());
+    }
+}
+
+/// # Observer Tests
+///
+/// These tests verify that the observer registered with `set_observer` hears
+/// about every page written and replaced.
+mod observer_tests {
+    use super::*;
+    use mst::{MSTKey, MstEvent};
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    /// Registers an observer collecting the events of `tree`
+    fn record(tree: &mut MST<TestValue>) -> Arc<Mutex<Vec<MstEvent<MSTKey>>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        tree.set_observer(Box::new(move |event| sink.lock().unwrap().push(event)));
+        events
+    }
+
+    fn reachable(tree: &MST<TestValue>) -> HashSet<MSTKey> {
+        tree.root
+            .map(|root| tree.store.reachable_set(root))
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_observer_reports_created_and_orphaned_pages() {
+        // This test verifies that each insert and remove reports every page it
+        // adds to the tree as created and exactly the pages it drops as orphaned
+        let mut tree = build_tree(0..300);
+        let events = record(&mut tree);
+        let mut splits = 0;
+
+        for i in 250..350u32 {
+            let before = reachable(&tree);
+            let key = create_key(&i.to_be_bytes());
+            if i % 3 == 0 {
+                tree.remove(key);
+            } else {
+                tree.insert(
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, 1, 0, 0],
+                    },
+                );
+            }
+            let after = reachable(&tree);
+
+            let mut created = HashSet::new();
+            let mut orphaned = HashSet::new();
+            for event in events.lock().unwrap().drain(..) {
+                match event {
+                    MstEvent::PageCreated { key, level } => {
+                        assert_eq!(tree.store.get(key).unwrap().level, level);
+                        created.insert(key);
+                    }
+                    MstEvent::PageOrphaned(key) => assert!(orphaned.insert(key)),
+                    MstEvent::Split { .. } => splits += 1,
+                }
+            }
+            assert_eq!(
+                orphaned,
+                before.difference(&after).copied().collect(),
+                "Exactly the dropped pages should be orphaned at {}",
+                i
+            );
+            assert!(
+                after.difference(&before).all(|key| created.contains(key)),
+                "Every new page should have been created at {}",
+                i
+            );
+        }
+        assert!(splits > 0, "Some inserts should split subtrees");
+    }
+
+    #[test]
+    fn test_observer_covers_merges_until_cleared() {
+        // This test verifies that a merge reports the pages it creates, and that
+        // nothing is reported once the observer is cleared
+        let mut left = build_tree(0..200);
+        let right = build_tree(100..300);
+        let events = record(&mut left);
+
+        let (root, store) = left.merge(&right).unwrap();
+        let merged = MST::with_store(root, store);
+        let created: Vec<_> = events
+            .lock()
+            .unwrap()
+            .drain(..)
+            .filter_map(|event| match event {
+                MstEvent::PageCreated { key, .. } => Some(key),
+                _ => None,
+            })
+            .collect();
+        assert!(!created.is_empty());
+        assert!(
+            created.contains(&root.unwrap()),
+            "The merged root is created"
+        );
+        assert!(created.iter().all(|key| merged.store.has(*key)));
+
+        left.clear_observer();
+        let key = create_key(b"unobserved");
+        left.insert(key, TestValue { key, data: [0; 4] });
+        assert!(events.lock().unwrap().is_empty());
+    }
+}
+
+/// # Partition Tests
+///
+/// These tests verify that trees can be partitioned by key range and put back
+/// together without changing the root.
+mod partition_tests {
+    use super::*;
+    use mst::MstError;
+
+    #[test]
+    fn test_split_with_missing_child_keeps_source_pages() {
+        // This test verifies that splitting through a page whose child is missing
+        // removes nothing from the store, so the original tree is whole again
+        // once the child arrives
+        let mut tree = build_tree(0..500);
+        let root = tree.root.unwrap();
+        let root_page = tree.store.get(root).cloned().unwrap();
+        let child = root_page.low.unwrap();
+        let child_page = tree.store.remove(child).unwrap();
+        let before: Vec<_> = tree.store.iter().map(|(key, _)| *key).collect();
+
+        // Splitting at the root's first entry descends into its missing low child
+        let (mut low, _) = tree.split_at(root_page.list[0].key);
+        assert!(
+            before.iter().all(|key| low.store.has(*key)),
+            "No page of the source should be removed"
+        );
+        assert!(low.store.get(root).is_some(), "The root is still there");
+
+        low.store.put(child, child_page);
+        let restored = MST::with_store(Some(root), low.store);
+        assert_eq!(restored.to_list(), build_tree(0..500).to_list());
+    }
+
+    #[test]
+    fn test_random_split_and_join_restores_root() {
+        // This test verifies that splitting random trees at random keys, present
+        // or not, and joining the halves gives back the original root, with the
+        // debug check in split guarding every split along the way
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        for _ in 0..40 {
+            let count = rng.gen_range(0..400u32);
+            let keys: Vec<u32> = (0..count).map(|_| rng.gen_range(0..1000)).collect();
+            let tree = build_tree(keys.iter().copied());
+            let original_root = tree.root;
+            let original_len = tree.len();
+
+            let split_key = create_key(&rng.gen_range(0..1000u32).to_be_bytes());
+            let (low, high) = tree.split_at(split_key);
+            assert!(low.iter().all(|entry| entry.key < split_key));
+            assert!(high.iter().all(|entry| entry.key >= split_key));
+            assert_eq!(low.len() + high.len(), original_len, "No entry lost");
+
+            let joined = MST::join(low, high).expect("Halves should not overlap");
+            assert_eq!(
+                joined.root, original_root,
+                "Joining should restore the root of {:?} split at {:?}",
+                keys, split_key
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_at_partitions_keys() {
+        // This test verifies that split_at yields canonical halves below and at or
+        // above the key, for present and absent keys, and that merging them
+        // restores the original root
+        let keys: Vec<u32> = (0..300).collect();
+        for split in [0u32, 1, 150, 299, 300, 5000] {
+            let tree = build_tree(keys.iter().copied());
+            let original_root = tree.root;
+            let split_key = create_key(&split.to_be_bytes());
+            let (low, high) = tree.split_at(split_key);
+
+            let below = |i: &u32| create_key(&i.to_be_bytes()) < split_key;
+            let expected_low = build_tree(keys.iter().copied().filter(below));
+            let expected_high = build_tree(keys.iter().copied().filter(|i| !below(i)));
+            for (half, expected) in [(&low, &expected_low), (&high, &expected_high)] {
+                assert_eq!(
+                    half.root, expected.root,
+                    "Each half should be the canonical tree of its keys at {}",
+                    split
+                );
+                assert_eq!(half.len(), expected.len(), "Half lengths should match");
+                assert_eq!(
+                    half.to_list(),
+                    expected.to_list(),
+                    "Half should be readable"
+                );
+            }
+
+            let mut low = low;
+            let (merged_root, _) = low.merge(&high).unwrap();
+            assert_eq!(
+                merged_root, original_root,
+                "Merging the halves at {} should restore the original root",
+                split
+            );
+        }
+
+        let (low, high) = build_tree(0..50).split_at(mst::MSTKey::default());
+        assert!(low.is_empty(), "Nothing is below the smallest possible key");
+        assert_eq!(high.len(), 50, "Everything is at or above it");
+    }
+
+    #[test]
+    fn test_join_disjoint_trees() {
+        // This test verifies that joining the halves of a split gives back the
+        // canonical tree of all keys, and that empty sides are accepted
+        for split in [0u32, 77, 150, 5000] {
+            let tree = build_tree(0..300);
+            let original_root = tree.root;
+            let (low, high) = tree.split_at(create_key(&split.to_be_bytes()));
+
+            let joined = MST::join(low, high).unwrap();
+            assert_eq!(
+                joined.root, original_root,
+                "Joining the halves at {} should restore the original root",
+                split
+            );
+            assert_eq!(joined.len(), 300, "Joined tree should count every entry");
+            assert_eq!(
+                joined.to_list(),
+                build_tree(0..300).to_list(),
+                "Joined tree should be readable"
+            );
+        }
+
+        let tree = build_tree(0..20);
+        let root = tree.root;
+        let joined = MST::join(tree, MST::new()).unwrap();
+        assert_eq!(joined.root, root, "Joining an empty right tree is a no-op");
+        let joined = MST::join(MST::new(), joined).unwrap();
+        assert_eq!(joined.root, root, "Joining an empty left tree is a no-op");
+    }
+
+    #[test]
+    fn test_join_rejects_overlapping_ranges() {
+        // This test verifies that join refuses trees whose key ranges overlap,
+        // including trees sharing a key
+        assert_eq!(
+            MST::join(build_tree(0..100), build_tree(50..150)).err(),
+            Some(MstError::RangesOverlap),
+            "Overlapping trees should be rejected"
+        );
+        assert_eq!(
+            MST::join(build_tree([7]), build_tree([7])).err(),
+            Some(MstError::RangesOverlap),
+            "Trees sharing a key should be rejected"
+        );
+    }
+}
+
+/// # Integrity Tests
+///
+/// These tests verify that `verify_integrity` accepts intact trees and names
+/// the first corrupted or missing page.
+mod integrity_tests {
+    use super::*;
+    use mst::{IntegrityError, MSTKey, Page, StreamVerifier, hash_page};
+
+    /// Returns the key of some child page of the root
+    fn child_of_root(tree: &MST<TestValue>) -> MSTKey {
+        let root = tree.store.get(tree.root.unwrap()).unwrap();
+        root.low
+            .or_else(|| root.list.iter().find_map(|entry| entry.next))
+            .expect("Root should have a child")
+    }
+
+    #[test]
+    fn test_verify_integrity_accepts_intact_trees() {
+        // This test verifies that intact and empty trees pass the check
+        assert_eq!(build_tree(0..500).verify_integrity(), Ok(()));
+        assert_eq!(MST::<TestValue>::new().verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_integrity_detects_corruption() {
+        // This test verifies that a page whose content no longer matches its key,
+        // and a referenced page missing from the store, are both reported
+        let mut tree = build_tree(0..500);
+        let child = child_of_root(&tree);
+        let mut page = tree.store.get(child).cloned().unwrap();
+        page.list[0].value.data[3] ^= 0xff;
+        tree.store.put(child, page);
+        assert_eq!(
+            tree.verify_integrity(),
+            Err(IntegrityError::HashMismatch(child)),
+            "A modified page should be reported by its key"
+        );
+
+        let mut tree = build_tree(0..500);
+        let child = child_of_root(&tree);
+        tree.store.remove(child);
+        assert_eq!(
+            tree.verify_integrity(),
+            Err(IntegrityError::MissingPage(child)),
+            "A missing page should be reported by its key"
+        );
+    }
+
+    #[test]
+    fn test_structurally_equal_requires_complete_stores() {
+        // This test verifies that trees with the same root are structurally equal
+        // only while both stores hold every reachable page
+        let tree = build_tree(0..500);
+        let mut copy = build_tree((0..500).rev());
+        assert!(tree.structurally_equal(&copy));
+        assert!(!tree.structurally_equal(&build_tree(0..499)));
+
+        let child = child_of_root(&copy);
+        copy.store.remove(child);
+        assert_eq!(copy.root, tree.root, "The roots still match");
+        assert!(!tree.structurally_equal(&copy));
+        assert!(!copy.structurally_equal(&tree));
+
+        assert!(MST::<TestValue>::new().structurally_equal(&MST::new()));
+    }
+
+    #[test]
+    fn test_lookups_stop_at_cycles() {
+        // This test verifies that a store whose pointers lead back to an ancestor
+        // makes lookups below the cycle return nothing instead of recursing forever
+        let mut tree = build_tree(0..500);
+        let root = tree.root.unwrap();
+        let child = child_of_root(&tree);
+        let smallest = tree.min_key().unwrap();
+        let mut page = tree.store.get(child).cloned().unwrap();
+        page.low = Some(root);
+        page.list
+            .iter_mut()
+            .for_each(|entry| entry.next = Some(root));
+        tree.store.put(child, page);
+
+        assert_eq!(tree.get_value(smallest), None);
+        assert!(!tree.contains_key(smallest));
+        assert!(tree.locate(create_key(b"absent")).is_none());
+
+        // A page pointing at itself
+        let mut page = tree.store.get(root).cloned().unwrap();
+        page.low = Some(root);
+        tree.store.put(root, page);
+        assert_eq!(tree.get_value(smallest), None);
+        assert!(!tree.contains_key(smallest));
+    }
+
+    #[test]
+    fn test_every_descent_stops_at_a_self_referencing_page() {
+        // This test verifies that each root-to-leaf walk gives up at a page that
+        // is its own child instead of looping
+        let mut tree = build_tree(0..500);
+        let root = tree.root.unwrap();
+        let smallest = tree.min_key().unwrap();
+        let mut page = tree.store.get(root).cloned().unwrap();
+        page.low = Some(root);
+        tree.store.put(root, page);
+
+        assert_eq!(tree.try_get_value(smallest), Ok(None));
+        assert!(tree.prove(smallest).is_none());
+        assert!(tree.prove_absence(smallest).is_none());
+        assert_eq!(tree.get_path(smallest).len(), 1);
+        assert_eq!(tree.view(Some(root)).get_value(smallest), None);
+        assert_eq!(tree.rank(smallest), 0);
+        assert!(tree.select(0).is_none());
+        assert!(tree.floor(smallest).is_none());
+        assert!(tree.first().is_some());
+    }
+
+    #[test]
+    fn test_opening_a_cyclic_store_terminates() {
+        // This test verifies that a tree opened over a store whose root page is
+        // its own low child can be counted, iterated, consumed, batch-read and
+        // rehashed without looping or overflowing the stack
+        let source = build_tree(0..500);
+        let root = source.root.unwrap();
+        let smallest = source.min_key().unwrap();
+        let largest = source.max_key().unwrap();
+        let mut store = source.store.clone();
+        let mut page = store.get(root).cloned().unwrap();
+        page.low = Some(root);
+        store.put(root, page);
+
+        let mut tree = MST::with_store(Some(root), store);
+        assert!(tree.len() < 500, "The looping subtree reads as empty");
+        assert_eq!(tree.iter().count(), tree.len());
+        assert_eq!(tree.iter_rev().count(), tree.len());
+        assert_eq!(tree.range(Some(smallest), None).count(), tree.len());
+        assert_eq!(
+            tree.get_many(&[smallest, largest]),
+            vec![None, source.get_value(largest)]
+        );
+        assert_eq!(tree.clone().into_iter().count(), tree.len());
+        assert_eq!(tree.recompute_root(), Err(IntegrityError::Cycle(root)));
+        assert_eq!(tree.root, Some(root), "A failed rehash leaves the root");
+    }
+
+    #[test]
+    fn test_recompute_root_after_page_edits() {
+        // This test verifies that rehashing after a page is edited in place gives
+        // the root of inserting the edit normally, keeps an untouched tree's root,
+        // and reports a dangling child pointer
+        let mut tree = build_tree(0..500);
+        let original = tree.root;
+        assert_eq!(tree.recompute_root(), Ok(original), "Nothing was edited");
+
+        // Edit the first entry of the leftmost leaf in place
+        let mut leaf = tree.root.unwrap();
+        while let Some(low) = tree.store.get(leaf).unwrap().low {
+            leaf = low;
+        }
+        let mut page = tree.store.get(leaf).cloned().unwrap();
+        page.list[0].value.data = [9, 9, 9, 9];
+        let edited = page.list[0].value;
+        tree.store.put(leaf, page);
+        assert!(
+            tree.verify_integrity().is_err(),
+            "The edit leaves stale hashes"
+        );
+
+        let mut expected = build_tree(0..500);
+        expected.insert(edited.key, edited);
+        assert_eq!(tree.recompute_root(), Ok(expected.root));
+        assert_eq!(tree.verify_integrity(), Ok(()));
+        assert_eq!(tree.to_list(), expected.to_list());
+        assert_eq!(tree.len(), 500);
+
+        let mut tree = build_tree(0..500);
+        let child = child_of_root(&tree);
+        tree.store.remove(child);
+        assert_eq!(
+            tree.recompute_root(),
+            Err(IntegrityError::MissingPage(child)),
+            "A dangling reference should be reported"
+        );
+        assert_eq!(tree.root, original, "The root should be left alone");
+    }
+
+    #[test]
+    fn test_merge_reports_missing_pages() {
+        // This test verifies that merging with a replica missing a page fails
+        // with that page's key, through merge and merge_all alike, while
+        // merge_infallible drops the entries below it
+        let mut complete = build_tree(0..300);
+        let mut partial = build_tree(200..500);
+        let child = child_of_root(&partial);
+        partial.store.remove(child);
+
+        assert!(
+            matches!(
+                complete.merge(&partial),
+                Err(IntegrityError::MissingPage(key)) if key == child
+            ),
+            "The missing page of other should be named"
+        );
+        assert!(
+            matches!(
+                partial.merge(&complete),
+                Err(IntegrityError::MissingPage(key)) if key == child
+            ),
+            "The missing page of self should be named"
+        );
+        assert!(
+            matches!(
+                MST::merge_all(&[&complete, &partial]),
+                Err(IntegrityError::MissingPage(key)) if key == child
+            ),
+            "merge_all should check every tree"
+        );
+
+        let (root, store) = complete.merge_infallible(&partial);
+        let merged = MST::with_store(root, store);
+        assert!(
+            merged.len() < 500,
+            "Entries below the missing page are lost"
+        );
+        assert_eq!(merged.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_import_validated() {
+        // This test verifies that pages that hash to their keys and cover the
+        // whole tree are imported, and that a forged or missing page rejects
+        // the import
+        let tree = build_tree(0..500);
+        let pages = tree
+            .store
+            .collect_pages(&std::iter::once(tree.root.unwrap()).collect());
+
+        let imported = MST::<TestValue>::import_validated(tree.root, pages.clone()).unwrap();
+        assert_eq!(imported.to_list(), tree.to_list());
+        assert_eq!(imported.len(), tree.len());
+
+        let child = child_of_root(&tree);
+        let mut forged = pages.clone();
+        let (_, page) = forged.iter_mut().find(|(key, _)| *key == child).unwrap();
+        page.list[0].value.data[3] ^= 0xff;
+        assert!(matches!(
+            MST::<TestValue>::import_validated(tree.root, forged),
+            Err(IntegrityError::HashMismatch(key)) if key == child
+        ));
+
+        let incomplete = pages.into_iter().filter(|(key, _)| *key != child);
+        assert!(matches!(
+            MST::<TestValue>::import_validated(tree.root, incomplete),
+            Err(IntegrityError::MissingPage(key)) if key == child
+        ));
+
+        let empty = MST::<TestValue>::import_validated(None, []).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_pages_are_rejected() {
+        // This test verifies that import_validated and verify_integrity both
+        // reject pages that hash to their keys but miscount their subtree, hold
+        // unsorted entries, or have a child that is not below them
+        let tree = build_tree(0..500);
+        let root = tree.root.unwrap();
+        let pages = tree.store.collect_pages(&tree.store.reachable_set(root));
+
+        // Rewrites a child of the root with several entries and the root with it,
+        // rehashing both
+        let root_page = tree.store.get(root).unwrap();
+        let child = root_page
+            .low
+            .into_iter()
+            .chain(root_page.list.iter().filter_map(|entry| entry.next))
+            .find(|child| tree.store.get(*child).unwrap().list.len() > 1)
+            .expect("Root should have a child with several entries");
+        let forge = |edit_child: &dyn Fn(&mut Page<MSTKey, TestValue>),
+                     edit_root: &dyn Fn(&mut Page<MSTKey, TestValue>)| {
+            let mut child_page = tree.store.get(child).cloned().unwrap();
+            edit_child(&mut child_page);
+            let forged_child = hash_page(&child_page);
+
+            let mut root_page = tree.store.get(root).cloned().unwrap();
+            edit_root(&mut root_page);
+            for link in std::iter::once(&mut root_page.low)
+                .chain(root_page.list.iter_mut().map(|entry| &mut entry.next))
+            {
+                if *link == Some(child) {
+                    *link = Some(forged_child);
+                }
+            }
+            let forged_root = hash_page(&root_page);
+
+            let mut forged = pages.clone();
+            forged.push((forged_child, child_page));
+            forged.push((forged_root, root_page));
+            let mut stored = MST::<TestValue>::new();
+            stored.store.put_many(forged.clone());
+            stored.root = Some(forged_root);
+            assert_eq!(
+                stored.verify_integrity().err(),
+                MST::<TestValue>::import_validated(Some(forged_root), forged).err(),
+                "Both checks should report the same page"
+            );
+            (stored.verify_integrity().err(), forged_child)
+        };
+
+        let (error, child) = forge(&|page| page.subtree_size += 1, &|page| {
+            page.subtree_size += 1
+        });
+        assert_eq!(
+            error,
+            Some(IntegrityError::Malformed(child)),
+            "A page miscounting its subtree should be rejected"
+        );
+
+        let (error, child) = forge(&|page| page.list.swap(0, 1), &|_| {});
+        assert_eq!(
+            error,
+            Some(IntegrityError::Malformed(child)),
+            "A page with unsorted entries should be rejected"
+        );
+
+        let root_level = tree.store.get(root).unwrap().level;
+        let (error, child) = forge(&|page| page.level = root_level, &|_| {});
+        assert_eq!(
+            error,
+            Some(IntegrityError::Cycle(child)),
+            "A child at its parent's level should be rejected"
+        );
+    }
+
+    #[test]
+    fn test_stream_verifier_accepts_pages_out_of_order() {
+        // This test verifies that the root is verified exactly when the last page
+        // of its tree arrives, whether pages come root first or leaves first, and
+        // that the outstanding set names the pages still to come
+        let tree = build_tree(0..500);
+        let root = tree.root.unwrap();
+        let mut pages = tree.store.collect_pages(&tree.store.reachable_set(root));
+
+        // Root first, then the rest in key order, which scatters the levels
+        pages.sort_by_key(|(key, _)| (*key != root, *key));
+        let mut verifier = StreamVerifier::<sha2::Sha256>::new();
+        for (i, (key, page)) in pages.iter().enumerate() {
+            assert!(!verifier.verified(root), "Verified after {} pages", i);
+            verifier.feed(*key, page).unwrap();
+            assert!(
+                verifier
+                    .outstanding()
+                    .iter()
+                    .all(|key| pages[i + 1..].iter().any(|(later, _)| later == key)),
+                "Only pages yet to be fed should be outstanding"
+            );
+        }
+        assert!(verifier.verified(root));
+        assert!(verifier.outstanding().is_empty());
+
+        // Leaves first, so subtrees complete bottom-up
+        pages.sort_by_key(|(key, page)| (page.level, *key));
+        let mut verifier = StreamVerifier::<sha2::Sha256>::default();
+        for (key, page) in &pages {
+            assert!(!verifier.verified(root));
+            verifier.feed(*key, page).unwrap();
+        }
+        assert!(verifier.verified(root));
+
+        // Feeding a page again changes nothing
+        verifier.feed(root, tree.store.get(root).unwrap()).unwrap();
+        assert!(verifier.verified(root));
+    }
+
+    #[test]
+    fn test_stream_verifier_rejects_tampered_page() {
+        // This test verifies that a page not matching its key is rejected as it
+        // arrives and, never counting as received, keeps the root unverified
+        let tree = build_tree(0..500);
+        let root = tree.root.unwrap();
+        let child = child_of_root(&tree);
+        let pages = tree.store.collect_pages(&tree.store.reachable_set(root));
+
+        let mut verifier = StreamVerifier::<sha2::Sha256>::new();
+        for (key, page) in &pages {
+            let mut page = page.clone();
+            if *key == child {
+                page.list[0].value.data[3] ^= 0xff;
+                assert_eq!(
+                    verifier.feed(*key, &page),
+                    Err(IntegrityError::HashMismatch(child)),
+                    "A tampered page should be rejected"
+                );
+            } else {
+                verifier.feed(*key, &page).unwrap();
+            }
+        }
+        assert!(!verifier.verified(root));
+        assert_eq!(
+            verifier.outstanding(),
+            &std::iter::once(child).collect(),
+            "Only the rejected page should be outstanding"
+        );
+    }
+}
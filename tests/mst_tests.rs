@@ -10,6 +10,134 @@ use std::collections::{HashMap, HashSet};
 mod tree_structure_tests {
     use super::*;
 
+    #[test]
+    fn test_from_sorted_matches_individual_inserts() {
+        // This test verifies that bulk-loading sorted items produces the same
+        // root and entries as inserting them one by one, merging duplicates
+        let mut items: Vec<_> = (0..1000u32)
+            .map(|i| {
+                let key = create_key(&i.to_be_bytes());
+                (
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, (i >> 8) as u8, 0, 0],
+                    },
+                )
+            })
+            .collect();
+        items.sort_by(|a, b| TestValue::compare_keys(&a.0, &b.0));
+
+        let mut inserted = MST::new();
+        for (key, value) in &items {
+            inserted.insert(*key, *value);
+        }
+
+        let bulk = MST::<TestValue>::from_sorted(items.clone()).expect("Sorted input should load");
+        assert_eq!(bulk.root, inserted.root, "Bulk load should match inserts");
+        assert_eq!(
+            bulk.to_list(),
+            inserted.to_list(),
+            "Bulk load should hold every entry"
+        );
+        assert_eq!(
+            bulk.len(),
+            items.len(),
+            "Bulk load should count every entry"
+        );
+
+        // A repeated key is merged into the entry before it
+        let (key, mut value) = items[10];
+        value.data = [9; 4];
+        let mut with_duplicate = items.clone();
+        with_duplicate.insert(11, (key, value));
+        inserted.insert(key, value);
+        let bulk = MST::<TestValue>::from_sorted(with_duplicate).unwrap();
+        assert_eq!(
+            bulk.root, inserted.root,
+            "Duplicates should merge like inserts"
+        );
+        assert_eq!(bulk.len(), items.len(), "Duplicates should not add entries");
+
+        let empty = MST::<TestValue>::from_sorted(Vec::new()).unwrap();
+        assert_eq!(
+            empty.root,
+            MST::<TestValue>::new().root,
+            "Empty input gives an empty tree"
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_from_sorted_parallel_matches_sequential() {
+        // This test verifies that the parallel bulk load builds the same tree as
+        // the sequential one on random datasets and thread counts, and still
+        // rejects unsorted input
+        use rand::Rng;
+
+        let mut rng = thread_rng();
+        for round in 0..10 {
+            let size = rng.gen_range(0..3000u32);
+            let mut items: Vec<_> = (0..size)
+                .map(|_| {
+                    let i = rng.gen_range(0..5000u32);
+                    let key = create_key(&i.to_be_bytes());
+                    (
+                        key,
+                        TestValue {
+                            key,
+                            data: [i as u8, round, 0, 0],
+                        },
+                    )
+                })
+                .collect();
+            items.sort_by(|a, b| TestValue::compare_keys(&a.0, &b.0));
+
+            let sequential = MST::<TestValue>::from_sorted(items.clone()).unwrap();
+            for threads in [0, 1, 3, 8] {
+                let parallel =
+                    MST::<TestValue>::from_sorted_parallel(items.clone(), threads).unwrap();
+                assert_eq!(
+                    parallel.root, sequential.root,
+                    "Parallel build with {} threads should match the sequential one",
+                    threads
+                );
+                assert_eq!(parallel.len(), sequential.len(), "Lengths should match");
+                assert_eq!(parallel.to_list(), sequential.to_list());
+            }
+
+            if items.len() > 1 && items[0].0 != items[items.len() - 1].0 {
+                let last = items.len() - 1;
+                items.swap(0, last);
+                assert_eq!(
+                    MST::<TestValue>::from_sorted_parallel(items, 4).err(),
+                    Some(mst::MstError::UnsortedInput),
+                    "Unsorted input should be rejected"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_sorted_rejects_unsorted_input() {
+        // This test verifies that out-of-order input is reported rather than
+        // building a corrupt tree
+        let mut items: Vec<_> = (0..10u32)
+            .map(|i| {
+                let key = create_key(&i.to_be_bytes());
+                (key, TestValue { key, data: [0; 4] })
+            })
+            .collect();
+        items.sort_by(|a, b| TestValue::compare_keys(&a.0, &b.0));
+        items.swap(3, 7);
+
+        assert_eq!(
+            MST::<TestValue>::from_sorted(items).err(),
+            Some(mst::MstError::UnsortedInput),
+            "Unsorted input should be rejected"
+        );
+    }
+
     #[test]
     fn test_tree_determinism() {
         // This test verifies that the tree structure is deterministic
@@ -108,6 +236,72 @@ mod tree_structure_tests {
             );
         }
     }
+
+    #[test]
+    fn test_insert_into_missing_children() {
+        // This test verifies that an item below a page with no low child gets a
+        // new page in that position, and that a higher-level item then becomes
+        // the new root above both
+        let keys: Vec<_> = (0..5000u32).map(|i| create_key(&i.to_be_bytes())).collect();
+        let top = *keys.iter().max_by_key(|key| calc_level(*key)).unwrap();
+        let top_level = calc_level(top);
+        let (middle, low) = keys
+            .iter()
+            .filter(|key| calc_level(*key) < top_level)
+            .find_map(|middle| {
+                keys.iter()
+                    .find(|low| *low < middle && calc_level(*low) < calc_level(middle))
+                    .map(|low| (*middle, *low))
+            })
+            .expect("Keys over three levels should exist");
+        let value = |key| TestValue { key, data: [0; 4] };
+
+        let mut mst = MST::new();
+        mst.insert(middle, value(middle));
+        assert_eq!(mst.get(mst.root.unwrap()).unwrap().low, None);
+
+        mst.insert(low, value(low));
+        let root = mst.get(mst.root.unwrap()).unwrap();
+        assert_eq!(
+            root.list[0].key, middle,
+            "The middle key should stay on top"
+        );
+        let low_page = mst
+            .get(root.low.expect("A low child should be created"))
+            .unwrap();
+        assert_eq!(
+            low_page.list[0].key, low,
+            "The lower key belongs in the low child"
+        );
+        assert_eq!(
+            root.list[0].next, None,
+            "Nothing should be placed above middle"
+        );
+
+        mst.insert(top, value(top));
+        let root = mst.get(mst.root.unwrap()).unwrap();
+        assert_eq!(
+            root.level, top_level,
+            "The highest item should become the root"
+        );
+        assert_eq!(root.list.len(), 1);
+        for key in [low, middle, top] {
+            assert_eq!(
+                mst.get_value(key),
+                Some(value(key)),
+                "Every key should be found"
+            );
+        }
+
+        let mut sorted = vec![
+            (low, value(low)),
+            (middle, value(middle)),
+            (top, value(top)),
+        ];
+        sorted.sort_by(|a, b| TestValue::compare_keys(&a.0, &b.0));
+        let expected = MST::<TestValue>::from_sorted(sorted).unwrap();
+        assert_eq!(mst.root, expected.root, "The tree should be canonical");
+    }
 }
 
 /// # Stress Tests
@@ -117,6 +311,51 @@ mod tree_structure_tests {
 mod stress_tests {
     use super::*;
 
+    #[test]
+    fn test_traversal_of_pathologically_deep_tree() {
+        // This test verifies that a chain of 100,000 single-entry pages, each
+        // the low child of the one above, can be traversed and merged without
+        // overflowing the call stack
+        let depth = 100_000u32;
+        let mut store = mst::Store::new();
+        let mut low = None;
+        for i in 0..depth {
+            let key = create_key(&i.to_be_bytes());
+            let page = mst::Page {
+                level: i + 1,
+                subtree_size: i as u64 + 1,
+                low,
+                list: vec![mst::PageData {
+                    key,
+                    value: TestValue {
+                        key,
+                        data: [i as u8, 0, 0, 0],
+                    },
+                    next: None,
+                }],
+            };
+            let page_key = mst::hash_page(&page);
+            store.put(page_key, page);
+            low = Some(page_key);
+        }
+
+        let mut tree = MST::with_store(low, store);
+        assert_eq!(
+            tree.to_list().len(),
+            depth as usize,
+            "Every entry should be listed"
+        );
+
+        // Merging copies the pages through the MST-order traversal
+        let (merged_root, merged_store) = tree.merge(&MST::new()).unwrap();
+        let merged = MST::with_store(merged_root, merged_store);
+        assert_eq!(
+            merged_root, tree.root,
+            "Merging with an empty tree keeps the root"
+        );
+        assert_eq!(merged.len(), depth as usize, "Every page should be copied");
+    }
+
     #[test]
     fn test_many_sequential_inserts() {
         // This test verifies the tree can handle a large number of sequential inserts
@@ -182,10 +421,10 @@ mod stress_tests {
     fn test_random_access_after_inserts() {
         // This test verifies random access patterns after inserting a set of items
         let mut mst = MST::new();
-        let count = 150;
+        let count: u32 = 150;
 
         // Insert sequential items
-        for i in 0..count as u32 {
+        for i in 0..count {
             let key = create_key(&i.to_be_bytes());
             let value = TestValue {
                 key,
@@ -322,6 +561,317 @@ mod basic_tests {
         );
     }
 
+    #[test]
+    fn test_calc_level_base() {
+        // This test verifies that base 2 is calc_level, and that a base of 2^k
+        // counts whole k-bit digits of the same leading zeros
+        for i in 0..2000u32 {
+            let key = create_key(&i.to_be_bytes());
+            let bits = calc_level(key);
+            assert_eq!(mst::calc_level_base(key, 2), bits);
+            assert_eq!(mst::calc_level_base(key, 16), bits / 4);
+            assert_eq!(mst::calc_level_base(key, 256), bits / 8);
+        }
+    }
+
+    #[test]
+    fn test_calc_level_of_known_hash() {
+        // This test verifies that the level is the hash's leading zero bits,
+        // counted across byte boundaries and nowhere else
+        // SHA-256(00 01 fa 08) = 00 00 41 16 ..., so 8 + 8 + 1 zero bits
+        let input = 129544u32.to_be_bytes();
+        assert_eq!(&create_key(&input)[..3], &[0x00, 0x00, 0x41]);
+        assert_eq!(calc_level(input), 17);
+        assert_eq!(mst::calc_level_base(input, 4), 8);
+        assert_eq!(mst::calc_level_base(input, 16), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "power of two")]
+    fn test_calc_level_base_rejects_other_bases() {
+        mst::calc_level_base(b"key", 10);
+    }
+
+    #[test]
+    fn test_level_base_shapes_the_tree() {
+        // This test verifies that a wider base gives a shallower tree with the
+        // same entries, a different root, and the same root in any insert order
+        let items: Vec<_> = (0..1000u32)
+            .map(|i| {
+                let key = create_key(&i.to_be_bytes());
+                (
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, 0, 0, 0],
+                    },
+                )
+            })
+            .collect();
+        let build = |base: u32, items: &[(mst::MSTKey, TestValue)]| {
+            let mut mst = MST::new().with_level_base(base);
+            for (key, value) in items {
+                mst.insert(*key, *value);
+            }
+            mst
+        };
+
+        let narrow = build(2, &items);
+        let wide = build(16, &items);
+        assert_eq!(wide.level_base(), 16);
+        assert_eq!(wide.to_list(), narrow.to_list(), "Same entries either way");
+        assert_ne!(wide.root, narrow.root, "Placement is part of the root hash");
+        assert!(
+            wide.height() < narrow.height(),
+            "Base 16 should be shallower: {} vs {}",
+            wide.height(),
+            narrow.height()
+        );
+
+        let reversed: Vec<_> = items.iter().rev().copied().collect();
+        assert_eq!(
+            build(16, &reversed).root,
+            wide.root,
+            "Base 16 trees should still be canonical"
+        );
+    }
+
+    #[test]
+    fn test_level_mapper_shapes_the_tree() {
+        // This test verifies that halving levels gives a shallower canonical tree
+        // with the same entries and a different root, that the identity mapper
+        // changes nothing, and that merges keep the mapper
+        let items: Vec<_> = (0..1000u32)
+            .map(|i| {
+                let key = create_key(&i.to_be_bytes());
+                (
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, 0, 0, 0],
+                    },
+                )
+            })
+            .collect();
+        let build = |mapper: mst::LevelMapper, items: &[(mst::MSTKey, TestValue)]| {
+            let mut mst = MST::new().with_level_mapper(mapper);
+            for (key, value) in items {
+                mst.insert(*key, *value);
+            }
+            mst
+        };
+
+        let plain = build(|level| level, &items);
+        let mut default = MST::new();
+        for (key, value) in &items {
+            default.insert(*key, *value);
+        }
+        assert_eq!(plain.root, default.root, "Identity is the default mapper");
+
+        let halved = build(|level| level / 2, &items);
+        assert_eq!(halved.to_list(), plain.to_list(), "Same entries either way");
+        assert_ne!(
+            halved.root, plain.root,
+            "Placement is part of the root hash"
+        );
+        assert!(
+            halved.height() < plain.height(),
+            "Halved levels should be shallower: {} vs {}",
+            halved.height(),
+            plain.height()
+        );
+
+        let reversed: Vec<_> = items.iter().rev().copied().collect();
+        assert_eq!(
+            build(|level| level / 2, &reversed).root,
+            halved.root,
+            "Mapped trees should still be canonical"
+        );
+
+        let mut low_half = build(|level| level / 2, &items[..500]);
+        let high_half = build(|level| level / 2, &items[500..]);
+        let (merged, _) = low_half.merge(&high_half).unwrap();
+        assert_eq!(
+            merged, halved.root,
+            "Merging should place keys like inserts"
+        );
+    }
+
+    /// A heap-allocated value, which is `Clone` but not `Copy`
+    #[derive(Debug, Clone, PartialEq, Hash)]
+    struct TextValue {
+        key: mst::MSTKey,
+        text: String,
+    }
+
+    impl AsRef<[u8]> for TextValue {
+        fn as_ref(&self) -> &[u8] {
+            self.text.as_bytes()
+        }
+    }
+
+    impl mst::Reference for TextValue {
+        type Key = mst::MSTKey;
+        fn refs(&self) -> Vec<Self::Key> {
+            vec![]
+        }
+    }
+
+    impl mst::Merge for TextValue {
+        fn merge(mut self, other: Self) -> Self {
+            self.text.push_str(&other.text);
+            self
+        }
+    }
+
+    impl KeyComparable for TextValue {
+        type Key = mst::MSTKey;
+
+        fn compare_keys(key1: &Self::Key, key2: &Self::Key) -> std::cmp::Ordering {
+            key1.cmp(key2)
+        }
+    }
+
+    #[test]
+    fn test_non_copy_values() {
+        // This test verifies that values which are only Clone go through insert,
+        // merging, lookups, removal, splitting and joining like Copy values do
+        let text = |key, text: &str| TextValue {
+            key,
+            text: text.to_string(),
+        };
+        let keys: Vec<_> = (0..300u32).map(|i| create_key(&i.to_be_bytes())).collect();
+
+        let mut mst = MST::new();
+        for (i, key) in keys.iter().enumerate() {
+            mst.insert(*key, text(*key, &format!("value {}", i)));
+        }
+        mst.insert(keys[7], text(keys[7], " and more"));
+        assert_eq!(
+            mst.get_value(keys[7]).unwrap().text,
+            "value 7 and more",
+            "Inserting an existing key should merge the strings"
+        );
+        assert_eq!(mst.remove(keys[8]).unwrap().text, "value 8");
+        assert_eq!(mst.get_value(keys[8]), None);
+
+        let mut other = MST::new();
+        other.insert(keys[9], text(keys[9], "!"));
+        let (root, store) = mst.merge(&other).unwrap();
+        let merged = MST::with_store(root, store);
+        assert_eq!(merged.get_value(keys[9]).unwrap().text, "value 9!");
+        assert_eq!(merged.len(), 299);
+
+        let mut sorted = keys.clone();
+        sorted.sort();
+        let (low, high) = merged.clone().split_at(sorted[150]);
+        assert_eq!(low.len() + high.len(), 299);
+        let joined = MST::join(low, high).unwrap();
+        assert_eq!(joined.root, merged.root, "Joining should restore the tree");
+        assert_eq!(joined.to_list(), merged.to_list());
+    }
+
+    #[test]
+    fn test_insert_merge_direction() {
+        // This test verifies which side wins with a non-commutative Merge:
+        // TestValue keeps the argument, so insert keeps the new value and
+        // insert_merging_existing_into_new keeps the existing one
+        let key = create_key(b"contested");
+        let old = TestValue {
+            key,
+            data: [1, 0, 0, 0],
+        };
+        let new = TestValue {
+            key,
+            data: [2, 0, 0, 0],
+        };
+
+        let mut tree = MST::new();
+        tree.insert(key, old);
+        tree.insert(key, new);
+        assert_eq!(
+            tree.get_value(key),
+            Some(new),
+            "insert merges as old.merge(new)"
+        );
+
+        let mut reversed = MST::new();
+        reversed.insert(key, old);
+        reversed.insert_merging_existing_into_new(key, new);
+        assert_eq!(
+            reversed.get_value(key),
+            Some(old),
+            "new.merge(old) keeps old"
+        );
+        assert_eq!(reversed.len(), 1);
+
+        let absent = create_key(b"absent");
+        let value = TestValue {
+            key: absent,
+            data: [3, 0, 0, 0],
+        };
+        reversed.insert_merging_existing_into_new(absent, value);
+        assert_eq!(
+            reversed.get_value(absent),
+            Some(value),
+            "A new key is just inserted"
+        );
+        assert_eq!(reversed.len(), 2);
+    }
+
+    #[test]
+    fn test_default_is_empty() {
+        // This test verifies that Default gives the same empty tree as new(),
+        // so structs holding a tree can derive it
+        #[derive(Default)]
+        struct Replica {
+            tree: MST<TestValue>,
+        }
+
+        let replica = Replica::default();
+        assert!(replica.tree.is_empty());
+        assert_eq!(replica.tree.root, MST::<TestValue>::new().root);
+        assert_eq!(replica.tree.level_base(), mst::DEFAULT_LEVEL_BASE);
+    }
+
+    #[test]
+    fn test_clear() {
+        // This test verifies that clear empties the tree and its store while
+        // keeping the store's capacity, and that the tree can be refilled
+        let mut mst = MST::new().with_level_base(4);
+        let fill = |mst: &mut MST<TestValue>| {
+            for i in 0..200u32 {
+                let key = create_key(&i.to_be_bytes());
+                mst.insert(
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, 0, 0, 0],
+                    },
+                );
+            }
+        };
+        fill(&mut mst);
+        let root = mst.root;
+        let capacity = mst.store.capacity();
+
+        mst.clear();
+        assert!(mst.is_empty());
+        assert!(mst.to_list().is_empty());
+        assert_eq!(mst.root, None);
+        assert_eq!(mst.store.iter().count(), 0, "Every page should be removed");
+        assert_eq!(
+            mst.store.capacity(),
+            capacity,
+            "The allocation should be kept"
+        );
+        assert_eq!(mst.level_base(), 4, "The level base should be kept");
+
+        fill(&mut mst);
+        assert_eq!(mst.root, root, "A refilled tree should match the original");
+        assert_eq!(mst.len(), 200);
+    }
+
     #[test]
     fn test_empty_tree() {
         // This test verifies that an empty MST correctly handles
@@ -350,7 +900,7 @@ mod basic_tests {
         };
         non_empty.insert(key, value);
 
-        let (merged_root_key, merged_store) = empty_tree.merge(&non_empty);
+        let (merged_root_key, merged_store) = empty_tree.merge(&non_empty).unwrap();
         let merged_tree = MST::with_store(merged_root_key, merged_store);
 
         // Merging with empty tree should preserve non-empty tree's contents
@@ -372,7 +922,7 @@ mod basic_tests {
         let mut mst = MST::new();
 
         // Insert a sequence of items in alphabetical order
-        let test_keys = vec![
+        let test_keys = [
             b"apple"[..].to_vec(),
             b"banana"[..].to_vec(),
             b"cherry"[..].to_vec(),
@@ -453,60 +1003,415 @@ mod basic_tests {
         let missing = mst.get_value(nonexistent_key);
         assert!(missing.is_none(), "Should return None for nonexistent key");
     }
-}
-
-/// # Edge Case Tests
-///
-/// These tests verify the MST handles unusual or extreme situations correctly.
-mod edge_case_tests {
-    use super::*;
 
     #[test]
-    fn test_duplicate_keys() {
-        // This test verifies that the tree correctly handles duplicate key insertions
-        // by replacing the existing value
+    fn test_get_value_ref() {
+        // This test verifies that get_value_ref borrows the stored value
+        // and agrees with get_value for present and absent keys
         let mut mst = MST::new();
+        for i in 0..50u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
 
-        let key = create_key(b"duplicate");
-        let value1 = TestValue {
-            key,
-            data: [1, 2, 3, 4],
-        };
-        let value2 = TestValue {
-            key,
-            data: [5, 6, 7, 8],
+        for i in 0..50u32 {
+            let key = create_key(&i.to_be_bytes());
+            let value_ref = mst
+                .get_value_ref(key)
+                .expect("Inserted key should be found");
+            assert_eq!(
+                value_ref.data[0], i as u8,
+                "Reference should point at the stored value"
+            );
+            assert_eq!(
+                Some(*value_ref),
+                mst.get_value(key),
+                "get_value should copy the referenced value"
+            );
+        }
+
+        assert!(
+            mst.get_value_ref(create_key(b"absent")).is_none(),
+            "Absent key should not be found"
+        );
+    }
+
+    #[test]
+    fn test_insert_with_old() {
+        // This test verifies that insert_with_old returns None for new keys and
+        // the value from before the merge for existing keys, at every level
+        let mut mst = MST::new();
+        let value = |i: u32, tag: u8| {
+            let key = create_key(&i.to_be_bytes());
+            (
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, tag, 0, 0],
+                },
+            )
         };
 
-        // Insert same key twice with different values
-        mst.insert(key, value1);
-        mst.insert(key, value2);
+        for i in 0..100u32 {
+            let (key, v) = value(i, 1);
+            let (root, old) = mst.insert_with_old(key, v);
+            assert_eq!(old, None, "A new key should have no previous value");
+            assert_eq!(Some(root), mst.root, "The new root should be returned");
+        }
+        for i in 0..100u32 {
+            let (key, v) = value(i, 2);
+            let (_, old) = mst.insert_with_old(key, v);
+            assert_eq!(
+                old,
+                Some(value(i, 1).1),
+                "Overwriting key {} should return its previous value",
+                i
+            );
+            assert_eq!(
+                mst.get_value(key),
+                Some(v),
+                "The new value should be stored"
+            );
+        }
+        assert_eq!(mst.len(), 100, "Overwrites should not grow the tree");
+    }
 
-        // Tree should maintain only one entry for the duplicate key
+    #[test]
+    fn test_entry() {
+        // This test verifies that or_insert_with only runs its closure for absent
+        // keys, and that and_modify rewrites present values and skips absent ones
+        let mut mst = MST::<TestValue>::new();
+        let counter = |i: u32| {
+            let key = create_key(&i.to_be_bytes());
+            (key, TestValue { key, data: [0; 4] })
+        };
+        let calls = std::cell::Cell::new(0);
+
+        for round in 0..3u8 {
+            for i in 0..50u32 {
+                let (key, zero) = counter(i);
+                let value = mst
+                    .entry(key)
+                    .and_modify(|value| value.data[0] += 1)
+                    .or_insert_with(|| {
+                        calls.set(calls.get() + 1);
+                        zero
+                    });
+                assert_eq!(value.data[0], round, "Each round should count once");
+            }
+        }
         assert_eq!(
-            mst.to_list().len(),
-            1,
-            "Tree should contain only one entry for duplicate key"
+            calls.get(),
+            50,
+            "The default should only be built for new keys"
         );
+        assert_eq!(mst.len(), 50);
 
-        // The second value should override the first (per our merge implementation)
-        let stored_value = mst.get_value(key).unwrap();
+        let mut expected = MST::new();
+        for i in 0..50u32 {
+            let (key, mut value) = counter(i);
+            value.data[0] = 2;
+            expected.insert(key, value);
+        }
         assert_eq!(
-            stored_value.data, value2.data,
-            "Duplicate key should store most recent value"
+            mst.root, expected.root,
+            "Entries should write canonical pages"
         );
+
+        let (absent, value) = counter(1000);
+        let entry = mst
+            .entry(absent)
+            .and_modify(|_| panic!("Nothing to modify"));
+        assert_eq!(entry.key(), absent);
+        assert_eq!(entry.or_insert(value), value);
+        assert_eq!(mst.get_value(absent), Some(value));
     }
 
     #[test]
-    fn test_long_and_short_keys() {
-        // This test verifies that the tree correctly handles keys of
-        // significantly different lengths
+    fn test_insert_if_changed() {
+        // This test verifies that insert_if_changed returns the new root for new
+        // keys and changed values, and None for an identical duplicate insert
         let mut mst = MST::new();
+        for i in 0..200u32 {
+            let key = create_key(&i.to_be_bytes());
+            let value = TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            };
+            let root = mst.insert_if_changed(key, value);
+            assert!(root.is_some(), "A new key should change the root");
+            assert_eq!(root, mst.root);
+        }
 
-        // Insert very short key (1 byte)
-        let short_key = create_key(b"a");
-        let short_value = TestValue {
-            key: short_key,
-            data: [1, 0, 0, 0],
+        let key = create_key(&7u32.to_be_bytes());
+        let same = TestValue {
+            key,
+            data: [7, 0, 0, 0],
+        };
+        let before = mst.root;
+        assert_eq!(mst.insert_if_changed(key, same), None);
+        assert_eq!(mst.root, before, "A no-op insert keeps the root");
+
+        let changed = TestValue {
+            key,
+            data: [7, 1, 0, 0],
+        };
+        assert_eq!(mst.insert_if_changed(key, changed), mst.root);
+        assert_ne!(mst.root, before);
+    }
+
+    #[test]
+    fn test_preview_insert() {
+        // This test verifies that preview_insert predicts the root of a real insert
+        // while leaving the tree and its store untouched
+        let mut mst = MST::new();
+        for i in 0..200u32 {
+            let key = create_key(&i.to_be_bytes());
+            let value = TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            };
+
+            let pages_before = mst.store.iter().count();
+            let root_before = mst.root;
+            let preview = mst.preview_insert(key, value);
+            assert_eq!(mst.root, root_before, "Preview should not move the root");
+            assert_eq!(
+                mst.store.iter().count(),
+                pages_before,
+                "Preview should not add pages to the store"
+            );
+
+            assert_eq!(
+                mst.insert(key, value),
+                preview,
+                "Preview should match the real insert"
+            );
+            assert_eq!(
+                Some(mst.preview_insert(key, value)),
+                mst.root,
+                "Re-inserting the same value should be a no-op"
+            );
+        }
+    }
+
+    #[test]
+    fn test_clone() {
+        // This test verifies that a cloned tree has the same root and contents,
+        // and that changing the clone leaves the original untouched
+        let mut mst = MST::new();
+        for i in 0..100u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        let mut cloned = mst.clone();
+        assert_eq!(cloned.root, mst.root, "Clone should have the same root");
+        assert_eq!(
+            cloned.to_list(),
+            mst.to_list(),
+            "Clone should list the same values"
+        );
+        assert_eq!(cloned.len(), mst.len(), "Clone should have the same length");
+
+        let original_root = mst.root;
+        let original_list = mst.to_list();
+        cloned.remove(create_key(&7u32.to_be_bytes()));
+        assert_eq!(mst.root, original_root, "Original root should be unchanged");
+        assert_eq!(
+            mst.to_list(),
+            original_list,
+            "Original values should be unchanged"
+        );
+    }
+
+    #[test]
+    fn test_len_tracking() {
+        // This test verifies that len() counts only genuinely new keys,
+        // ignores merges of existing keys, and is recomputed by with_store
+        let mut mst = MST::new();
+        assert_eq!(mst.len(), 0, "New tree should be empty");
+        assert!(mst.is_empty(), "New tree should report is_empty");
+
+        for i in 0..100u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        assert_eq!(mst.len(), 100, "Each new key should increment len");
+
+        // Re-inserting existing keys goes through the merge path
+        for i in 0..50u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [0, i as u8, 0, 0],
+                },
+            );
+        }
+        assert_eq!(
+            mst.len(),
+            100,
+            "Merging into existing keys should not change len"
+        );
+
+        for i in 0..10u32 {
+            mst.remove(create_key(&i.to_be_bytes()));
+        }
+        mst.remove(create_key(b"absent"));
+        assert_eq!(
+            mst.len(),
+            90,
+            "Only successful removals should decrement len"
+        );
+        assert_eq!(mst.len(), mst.to_list().len(), "len should match to_list");
+
+        // An externally supplied store is counted on construction
+        let rebuilt = MST::with_store(mst.root, mst.store.clone());
+        assert_eq!(rebuilt.len(), 90, "with_store should recompute the count");
+        assert!(!rebuilt.is_empty(), "Rebuilt tree should not be empty");
+    }
+}
+
+/// # Edge Case Tests
+///
+/// These tests verify the MST handles unusual or extreme situations correctly.
+mod edge_case_tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_subtrees_are_each_traversed() {
+        // This test verifies that a page referenced from two positions is listed
+        // and dumped at both, while a page referencing itself does not loop
+        let entry = |i: u32| {
+            let key = create_key(&i.to_be_bytes());
+            mst::PageData {
+                key,
+                value: TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+                next: None,
+            }
+        };
+
+        let shared = mst::Page {
+            level: 1,
+            subtree_size: 2,
+            low: None,
+            list: vec![entry(1), entry(2)],
+        };
+        let shared_key = mst::hash_page(&shared);
+        let mut root_entry = entry(3);
+        root_entry.next = Some(shared_key);
+        let root = mst::Page {
+            level: 2,
+            subtree_size: 5,
+            low: Some(shared_key),
+            list: vec![root_entry],
+        };
+        let root_key = mst::hash_page(&root);
+
+        let mut store = mst::Store::new();
+        store.put(shared_key, shared);
+        store.put(root_key, root);
+        let tree = MST::with_store(Some(root_key), store);
+
+        let data: Vec<u8> = tree.to_list().iter().map(|value| value.data[0]).collect();
+        assert_eq!(
+            data,
+            vec![1, 2, 3, 1, 2],
+            "Both copies of the subtree should be listed"
+        );
+        assert_eq!(
+            tree.dump().matches(" => ").count(),
+            5,
+            "Both copies of the subtree should be dumped"
+        );
+
+        // A page that is its own low child only breaks the cycle
+        let mut looping = MST::<TestValue>::new();
+        let key = create_key(b"loop");
+        let page = mst::Page {
+            level: 1,
+            subtree_size: 1,
+            low: Some(key),
+            list: vec![entry(4)],
+        };
+        looping.store.put(key, page);
+        looping.root = Some(key);
+        assert_eq!(
+            looping.dump().matches(" => ").count(),
+            1,
+            "A cycle should be traversed once"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        // This test verifies that the tree correctly handles duplicate key insertions
+        // by replacing the existing value
+        let mut mst = MST::new();
+
+        let key = create_key(b"duplicate");
+        let value1 = TestValue {
+            key,
+            data: [1, 2, 3, 4],
+        };
+        let value2 = TestValue {
+            key,
+            data: [5, 6, 7, 8],
+        };
+
+        // Insert same key twice with different values
+        mst.insert(key, value1);
+        mst.insert(key, value2);
+
+        // Tree should maintain only one entry for the duplicate key
+        assert_eq!(
+            mst.to_list().len(),
+            1,
+            "Tree should contain only one entry for duplicate key"
+        );
+
+        // The second value should override the first (per our merge implementation)
+        let stored_value = mst.get_value(key).unwrap();
+        assert_eq!(
+            stored_value.data, value2.data,
+            "Duplicate key should store most recent value"
+        );
+    }
+
+    #[test]
+    fn test_long_and_short_keys() {
+        // This test verifies that the tree correctly handles keys of
+        // significantly different lengths
+        let mut mst = MST::new();
+
+        // Insert very short key (1 byte)
+        let short_key = create_key(b"a");
+        let short_value = TestValue {
+            key: short_key,
+            data: [1, 0, 0, 0],
         };
         mst.insert(short_key, short_value);
 
@@ -573,24 +1478,20 @@ mod edge_case_tests {
         let result_large = mst.get_value(too_large);
         println!("Looking up key greater than all: {:?}", result_large);
     }
-}
-
-/// # Merge Operation Tests
-///
-/// These tests verify the MST's merge functionality works correctly.
-mod merge_tests {
-    use super::*;
 
     #[test]
-    fn test_merging_disjoint_trees() {
-        // This test verifies merging two trees with no overlapping keys
-        let mut tree_a = MST::new();
-        let mut tree_b = MST::new();
+    fn test_contains_key() {
+        // This test verifies that contains_key agrees with get_value for
+        // present keys, absent keys, and the empty tree
+        let mut mst = MST::new();
+        assert!(
+            !mst.contains_key(create_key(b"anything")),
+            "Empty tree should not contain any key"
+        );
 
-        // Populate first tree with items 1-5
-        for i in 1u32..=5u32 {
+        for i in 0..100u32 {
             let key = create_key(&i.to_be_bytes());
-            tree_a.insert(
+            mst.insert(
                 key,
                 TestValue {
                     key,
@@ -599,442 +1500,2656 @@ mod merge_tests {
             );
         }
 
-        // Populate second tree with items 6-10
-        for i in 6u32..=10u32 {
+        for i in 0..200u32 {
             let key = create_key(&i.to_be_bytes());
-            tree_b.insert(
-                key,
-                TestValue {
-                    key,
-                    data: [i as u8, 0, 0, 0],
-                },
+            assert_eq!(
+                mst.contains_key(key),
+                mst.get_value(key).is_some(),
+                "contains_key and get_value should agree on key {}",
+                i
             );
         }
 
-        // Merge the trees
-        let (merged_root_key, merged_store) = tree_a.merge(&tree_b);
-        let merged_tree = MST::with_store(merged_root_key, merged_store);
-
-        // Verify merged tree contains all items
-        assert_eq!(
-            merged_tree.to_list().len(),
-            10,
-            "Merged tree should contain all items from both trees"
+        // Keys beyond either end fall off a None child pointer
+        let smallest = mst::MSTKey::clone_from_slice(&[0; 32]);
+        let largest = mst::MSTKey::clone_from_slice(&[255; 32]);
+        assert!(
+            !mst.contains_key(smallest),
+            "Key below all entries is absent"
+        );
+        assert!(
+            !mst.contains_key(largest),
+            "Key above all entries is absent"
         );
+    }
+}
 
-        // Verify all values are retrievable
-        for i in 1u32..=10u32 {
-            let key = create_key(&i.to_be_bytes());
-            assert!(
-                merged_tree.get_value(key).is_some(),
-                "Merged tree should contain value for key {}",
-                i
-            );
+/// # Merge Operation Tests
+///
+/// These tests verify the MST's merge functionality works correctly.
+mod merge_tests {
+    use super::*;
+    use mst::MergePolicy;
+
+    /// A last-writer-wins value: version, deleted flag and payload, in that order
+    #[derive(Debug, Copy, Clone, PartialEq, Hash)]
+    struct LwwValue {
+        key: mst::MSTKey,
+        bytes: [u8; 10],
+    }
+
+    impl LwwValue {
+        fn new(key: mst::MSTKey, version: u64, deleted: bool, payload: u8) -> Self {
+            let mut bytes = [0; 10];
+            bytes[..8].copy_from_slice(&version.to_be_bytes());
+            bytes[8] = deleted as u8;
+            bytes[9] = payload;
+            LwwValue { key, bytes }
         }
     }
 
-    #[test]
-    fn test_merging_overlapping_trees() {
-        // This test verifies merging trees with overlapping keys
-        // (keys present in both trees)
-        let mut tree_a = MST::new();
-        let mut tree_b = MST::new();
+    impl AsRef<[u8]> for LwwValue {
+        fn as_ref(&self) -> &[u8] {
+            &self.bytes
+        }
+    }
 
-        // Insert overlapping items with different values
-        for i in 1u32..=5u32 {
-            let key = create_key(&i.to_be_bytes());
+    impl mst::Reference for LwwValue {
+        type Key = mst::MSTKey;
+        fn refs(&self) -> Vec<Self::Key> {
+            vec![]
+        }
+    }
 
-            // Tree A has simple values
-            tree_a.insert(
-                key,
-                TestValue {
-                    key,
-                    data: [i as u8, 0, 0, 0],
-                },
-            );
+    impl mst::Merge for LwwValue {
+        fn merge(self, other: Self) -> Self {
+            // The later version wins, and a delete wins a tie, in either order
+            if other.bytes[..9] > self.bytes[..9] {
+                other
+            } else {
+                self
+            }
+        }
 
-            // Tree B has different values for same keys
-            tree_b.insert(
-                key,
-                TestValue {
-                    key,
-                    data: [i as u8, i as u8, i as u8, i as u8],
-                },
-            );
+        fn is_tombstone(&self) -> bool {
+            self.bytes[8] == 1
         }
+    }
 
-        // Merge the trees
-        let (merged_root_key, merged_store) = tree_a.merge(&tree_b);
-        let merged_tree = MST::with_store(merged_root_key, merged_store);
+    impl KeyComparable for LwwValue {
+        type Key = mst::MSTKey;
 
-        // Verify merged tree has correct number of items (no duplicates)
+        fn compare_keys(key1: &Self::Key, key2: &Self::Key) -> std::cmp::Ordering {
+            key1.cmp(key2)
+        }
+    }
+
+    #[test]
+    fn test_merge_all_is_order_independent() {
+        // This test verifies that merging several replicas at once gives the same
+        // root for every order they are passed in, the same as merging them pair
+        // by pair, when values merge commutatively
+        let replicas: Vec<MST<LwwValue>> = (0..3u32)
+            .map(|j| {
+                let mut replica = MST::new();
+                for i in (0..300u32).filter(|i| i % (j + 2) != 0) {
+                    let key = create_key(&i.to_be_bytes());
+                    replica.insert(key, LwwValue::new(key, j as u64, false, j as u8));
+                }
+                replica
+            })
+            .collect();
+        let [a, b, c] = [&replicas[0], &replicas[1], &replicas[2]];
+
+        let (root, store) = MST::merge_all(&[a, b, c]).unwrap();
+        for order in [[a, c, b], [b, a, c], [b, c, a], [c, a, b], [c, b, a]] {
+            assert_eq!(
+                MST::merge_all(&order).unwrap().0,
+                root,
+                "Order should not matter"
+            );
+        }
         assert_eq!(
-            merged_tree.to_list().len(),
-            5,
-            "Merged tree should contain one entry per unique key"
+            MST::merge_all(&[a, b, b, c]).unwrap().0,
+            root,
+            "A repeated replica should change nothing"
         );
 
-        // Verify values from tree_b took precedence (per our merge implementation)
-        for i in 1u32..=5u32 {
+        let (ab_root, ab_store) = a.clone().merge(b).unwrap();
+        let (pairwise, _) = MST::with_store(ab_root, ab_store).merge(c).unwrap();
+        assert_eq!(pairwise, root, "Should match merging pair by pair");
+
+        let merged = MST::with_store(root, store);
+        for i in 0..300u32 {
             let key = create_key(&i.to_be_bytes());
-            let value = merged_tree.get_value(key).unwrap();
+            let newest = (0..3u32).rev().find(|j| i % (j + 2) != 0);
             assert_eq!(
-                value.data,
-                [i as u8, i as u8, i as u8, i as u8],
-                "Merged tree should contain values from tree_b for key {}",
+                merged.get_value(key).map(|value| value.bytes[9] as u32),
+                newest,
+                "Key {} should hold the newest replica's value",
                 i
             );
         }
+
+        assert_eq!(MST::<LwwValue>::merge_all(&[]).unwrap().0, None);
     }
-}
 
-/// # Performance Tests
-///
-/// These tests verify the MST performs well with larger datasets.
-mod performance_tests {
-    use super::*;
-    use rand::seq::SliceRandom;
-    use rand::thread_rng;
+    #[test]
+    fn test_merge_policy_replace_skips_merge() {
+        // This test verifies that the Replace policy keeps the newest value where
+        // Merge combines them, both on insert and when merging or combining trees,
+        // and that it matches the default for a value whose merge already replaces
+        let key = create_key(b"key");
+        let newer = LwwValue::new(key, 2, false, 1);
+        let older = LwwValue::new(key, 1, false, 2);
+
+        let mut merging: MST<LwwValue> = MST::new();
+        let mut replacing = MST::new().with_merge_policy(MergePolicy::Replace);
+        assert_eq!(replacing.merge_policy(), MergePolicy::Replace);
+        for tree in [&mut merging, &mut replacing] {
+            tree.insert(key, newer);
+            tree.insert(key, older);
+        }
+        assert_eq!(
+            merging.get_value(key),
+            Some(newer),
+            "Merge keeps the later version"
+        );
+        assert_eq!(
+            replacing.get_value(key),
+            Some(older),
+            "Replace keeps the last insert"
+        );
+
+        // Merging the existing value into the new one does not change that
+        replacing.insert_merging_existing_into_new(key, newer);
+        assert_eq!(
+            replacing.get_value(key),
+            Some(newer),
+            "Replace keeps the new value"
+        );
+        merging.insert_merging_existing_into_new(key, older);
+        assert_eq!(
+            merging.get_value(key),
+            Some(newer),
+            "Merge keeps the later version"
+        );
+
+        let mut other: MST<LwwValue> = MST::new();
+        other.insert(key, older);
+        let mut base = MST::new().with_merge_policy(MergePolicy::Replace);
+        base.insert(key, newer);
+        let (root, store) = base.merge(&other).unwrap();
+        assert_eq!(MST::with_store(root, store).get_value(key), Some(older));
+        assert_eq!(
+            base.union(&other).get_value(key),
+            Some(older),
+            "Replace lets the value of other win a union"
+        );
+        assert_eq!(
+            base.intersection(&other).get_value(key),
+            Some(older),
+            "Replace lets the value of other win an intersection"
+        );
+
+        let (mut plain, mut replaced): (MST<TestValue>, MST<TestValue>) = (
+            MST::new(),
+            MST::new().with_merge_policy(MergePolicy::Replace),
+        );
+        for i in 0..300u32 {
+            let key = create_key(&(i % 200).to_be_bytes());
+            let value = TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            };
+            plain.insert(key, value);
+            replaced.insert(key, value);
+        }
+        assert_eq!(plain.root, replaced.root);
+    }
 
     #[test]
-    fn test_large_dataset() {
-        // This test verifies MST performance with a larger dataset
-        // and validates correct behavior when merging trees
-        let mut nums_a: Vec<u32> = (20..=30).collect();
-        let mut nums_b: Vec<u32> = (5..=57).collect();
+    fn test_tombstones_survive_merge() {
+        // This test verifies that a delete written as a tombstone wins over a
+        // replica's older live value in either merge direction, reads as absent,
+        // and still contributes to a converged root
+        let mut replica_a = MST::new();
+        let mut replica_b = MST::new();
+        for i in 0..50u32 {
+            let key = create_key(&i.to_be_bytes());
+            replica_a.insert(key, LwwValue::new(key, 1, false, i as u8));
+            replica_b.insert(key, LwwValue::new(key, 1, false, i as u8));
+        }
 
-        // Shuffle inputs to test random insertion order
-        let mut rng = thread_rng();
-        nums_a.shuffle(&mut rng);
-        nums_b.shuffle(&mut rng);
+        let deleted = create_key(&7u32.to_be_bytes());
+        let mut removed_only = replica_a.clone();
+        removed_only.remove(deleted);
+        replica_a.insert(deleted, LwwValue::new(deleted, 2, true, 0));
 
-        // Create and populate trees
+        assert_eq!(
+            replica_a.get_value(deleted),
+            None,
+            "A tombstone reads as absent"
+        );
+        assert!(!replica_a.contains_key(deleted));
+        assert_eq!(replica_a.get_many(&[deleted]), vec![None]);
+        assert_eq!(replica_a.to_list().len(), 49, "to_list skips tombstones");
+        assert_eq!(replica_a.len(), 50, "The tombstone is still an entry");
+
+        let (root_ab, store_ab) = replica_a.merge(&replica_b).unwrap();
+        let (root_ba, store_ba) = replica_b.merge(&replica_a).unwrap();
+        assert_eq!(root_ab, root_ba, "Replicas should converge");
+        for (root, store) in [(root_ab, store_ab), (root_ba, store_ba)] {
+            let merged = MST::with_store(root, store);
+            assert_eq!(
+                merged.get_value(deleted),
+                None,
+                "The delete should not be undone"
+            );
+            assert_eq!(merged.to_list(), replica_a.to_list());
+        }
+        assert_ne!(
+            root_ab, removed_only.root,
+            "The tombstone is hashed into the root"
+        );
+
+        // Without a tombstone, merging brings the key back
+        let (root, store) = removed_only.merge(&replica_b).unwrap();
+        assert!(MST::with_store(root, store).get_value(deleted).is_some());
+
+        // A later write revives the key
+        replica_a.insert(deleted, LwwValue::new(deleted, 3, false, 70));
+        assert_eq!(
+            replica_a.get_value(deleted).map(|value| value.bytes[9]),
+            Some(70)
+        );
+    }
+
+    #[test]
+    fn test_merging_disjoint_trees() {
+        // This test verifies merging two trees with no overlapping keys
         let mut tree_a = MST::new();
         let mut tree_b = MST::new();
 
-        // Insert shuffled items into trees
-        for &num in &nums_a {
-            let key = create_key(&num.to_be_bytes());
+        // Populate first tree with items 1-5
+        for i in 1u32..=5u32 {
+            let key = create_key(&i.to_be_bytes());
             tree_a.insert(
                 key,
                 TestValue {
                     key,
-                    data: [num as u8, 0, 0, 0],
+                    data: [i as u8, 0, 0, 0],
                 },
             );
         }
 
-        for &num in &nums_b {
-            let key = create_key(&num.to_be_bytes());
+        // Populate second tree with items 6-10
+        for i in 6u32..=10u32 {
+            let key = create_key(&i.to_be_bytes());
             tree_b.insert(
                 key,
                 TestValue {
                     key,
-                    data: [num as u8, 0, 0, 0],
+                    data: [i as u8, 0, 0, 0],
                 },
             );
         }
 
-        // Merge large trees
-        let (merged_root_key, merged_store) = tree_a.merge(&tree_b);
+        // Merge the trees
+        let (merged_root_key, merged_store) = tree_a.merge(&tree_b).unwrap();
         let merged_tree = MST::with_store(merged_root_key, merged_store);
 
-        // Calculate expected size (unique items after merge)
-        let expected_unique_count = 53; // 11 + 53 - 11 (overlap)
-
-        // Verify merged tree contains correct number of items
+        // Verify merged tree contains all items
         assert_eq!(
             merged_tree.to_list().len(),
-            expected_unique_count,
-            "Merged tree should contain all unique items"
+            10,
+            "Merged tree should contain all items from both trees"
         );
 
-        // Verify items are in sorted order
-        let items = merged_tree.to_list();
-        for i in 1..items.len() {
+        // Verify all values are retrievable
+        for i in 1u32..=10u32 {
+            let key = create_key(&i.to_be_bytes());
             assert!(
-                TestValue::compare_keys(&items[i - 1].key, &items[i].key)
-                    != std::cmp::Ordering::Greater,
-                "Items should maintain sorted order in large dataset"
+                merged_tree.get_value(key).is_some(),
+                "Merged tree should contain value for key {}",
+                i
             );
         }
     }
-}
-
-/// # Specialized Tests
-///
-/// These tests target specific edge cases and behaviors in the MST implementation.
-mod specialized_tests {
-    use super::*;
 
     #[test]
-    fn test_tree_consistency() {
-        // This test verifies tree maintains structural consistency
-        // throughout a series of operations
-        let mut mst = MST::new();
+    fn test_merging_overlapping_trees() {
+        // This test verifies merging trees with overlapping keys
+        // (keys present in both trees)
+        let mut tree_a = MST::new();
+        let mut tree_b = MST::new();
 
-        // Insert values and check consistency after each insertion
-        for i in 0..10u8 {
-            let key = create_key(&[i, 0, 0, 0]);
-            let value = TestValue {
+        // Insert overlapping items with different values
+        for i in 1u32..=5u32 {
+            let key = create_key(&i.to_be_bytes());
+
+            // Tree A has simple values
+            tree_a.insert(
                 key,
-                data: [i, 0, 0, 0],
-            };
-            mst.insert(key, value);
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
 
-            // After each insertion:
-            // 1. Verify the list is in sorted order
-            let list = mst.to_list();
-            for j in 1..list.len() {
-                assert!(
-                    TestValue::compare_keys(&list[j - 1].key, &list[j].key)
-                        != std::cmp::Ordering::Greater,
-                    "List is not in sorted order after inserting key {}",
-                    i
-                );
-            }
+            // Tree B has different values for same keys
+            tree_b.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, i as u8, i as u8, i as u8],
+                },
+            );
+        }
 
-            // 2. Verify all previously inserted keys are still retrievable
-            for j in 0..=i {
-                let check_key = create_key(&[j, 0, 0, 0]);
-                assert!(
-                    mst.get_value(check_key).is_some(),
-                    "Key {} not retrievable after inserting key {}",
-                    j,
-                    i
-                );
-            }
+        // Merge the trees
+        let (merged_root_key, merged_store) = tree_a.merge(&tree_b).unwrap();
+        let merged_tree = MST::with_store(merged_root_key, merged_store);
+
+        // Verify merged tree has correct number of items (no duplicates)
+        assert_eq!(
+            merged_tree.to_list().len(),
+            5,
+            "Merged tree should contain one entry per unique key"
+        );
+
+        // Verify values from tree_b took precedence (per our merge implementation)
+        for i in 1u32..=5u32 {
+            let key = create_key(&i.to_be_bytes());
+            let value = merged_tree.get_value(key).unwrap();
+            assert_eq!(
+                value.data,
+                [i as u8, i as u8, i as u8, i as u8],
+                "Merged tree should contain values from tree_b for key {}",
+                i
+            );
         }
     }
 
     #[test]
-    fn test_interleaved_operations() {
-        // This test verifies that the tree handles interleaved insert and lookup
-        // operations correctly
-        const A: u8 = 11; // Define a constant for the pattern generation
-
-        let mut mst = MST::new();
-        let mut keys = Vec::new();
+    fn test_structural_merge_matches_reinsertion() {
+        // This test verifies that the structural merge produces the same root as
+        // inserting every entry of both trees into a fresh tree, for disjoint,
+        // interleaved, overlapping, and identical inputs
+        let value = |i: u32, tag: u8| {
+            let key = create_key(&i.to_be_bytes());
+            (
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, tag, 0, 0],
+                },
+            )
+        };
+        let cases: Vec<(Vec<u32>, Vec<u32>)> = vec![
+            ((0..200).collect(), (200..400).collect()),
+            ((0..400).step_by(2).collect(), (1..400).step_by(2).collect()),
+            ((0..300).collect(), (150..450).collect()),
+            ((0..250).collect(), (0..250).collect()),
+            ((0..100).collect(), Vec::new()),
+            (Vec::new(), (0..100).collect()),
+        ];
 
-        // Insert 50 values with complex patterns
-        for i in 0..50u8 {
-            let key = create_key(&[i, i % A, i % 7, i % 13]); // Use a mix of patterns
-            keys.push(key);
+        for (case, (left_keys, right_keys)) in cases.into_iter().enumerate() {
+            let mut left = MST::new();
+            let mut right = MST::new();
+            let mut expected = MST::new();
+            for &i in &left_keys {
+                let (key, v) = value(i, 1);
+                left.insert(key, v);
+                expected.insert(key, v);
+            }
+            for &i in &right_keys {
+                let (key, v) = value(i, 2);
+                right.insert(key, v);
+                expected.insert(key, v);
+            }
 
-            let value = TestValue {
-                key,
-                data: [i, 0, 0, 0],
-            };
-            mst.insert(key, value);
+            let (merged_root, merged_store) = left.merge(&right).unwrap();
+            assert_eq!(
+                merged_root, expected.root,
+                "Structural merge should match reinsertion in case {}",
+                case
+            );
 
-            // After each insertion, check a previously inserted key
-            if !keys.is_empty() && i > 0 {
-                let idx = (i as usize) % keys.len();
-                let check_key = keys[idx];
-                let retrieved = mst.get_value(check_key);
+            let merged_tree = MST::with_store(merged_root, merged_store);
+            assert_eq!(
+                merged_tree.to_list(),
+                expected.to_list(),
+                "Merged store should hold every page of the merged tree in case {}",
+                case
+            );
+        }
+    }
 
-                assert!(
-                    retrieved.is_some(),
-                    "Failed to retrieve key at index {} after inserting key {}",
-                    idx,
-                    i
-                );
-            }
+    #[test]
+    fn test_merge_with_custom_resolver() {
+        // This test verifies that merge_with resolves only colliding keys with the
+        // given function, passing the value from self first
+        let value = |i: u32, tag: u8| {
+            let key = create_key(&i.to_be_bytes());
+            (
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, tag, 0, 0],
+                },
+            )
+        };
+        let mut left = MST::new();
+        let mut right = MST::new();
+        for i in 0..300u32 {
+            let (key, v) = value(i, 1);
+            left.insert(key, v);
+        }
+        for i in 150..450u32 {
+            let (key, v) = value(i, 2);
+            right.insert(key, v);
         }
 
-        // Finally, verify all inserted keys are retrievable
-        for (i, &key) in keys.iter().enumerate() {
-            let retrieved = mst.get_value(key);
-            assert!(
-                retrieved.is_some(),
-                "Failed to retrieve key at index {} after all insertions",
+        let calls = std::cell::Cell::new(0);
+        let (merged_root, merged_store) = left
+            .merge_with(&right, |mine, theirs| {
+                calls.set(calls.get() + 1);
+                assert_eq!(mine.data[1], 1, "The first argument should come from self");
+                assert_eq!(
+                    theirs.data[1], 2,
+                    "The second argument should come from other"
+                );
+                *mine
+            })
+            .unwrap();
+        assert_eq!(calls.get(), 150, "Only colliding keys should be resolved");
+
+        let merged = MST::with_store(merged_root, merged_store);
+        for i in 0..450u32 {
+            let (key, _) = value(i, 0);
+            let expected_tag = if i < 300 { 1 } else { 2 };
+            assert_eq!(
+                merged.get_value(key).map(|v| v.data[1]),
+                Some(expected_tag),
+                "Key {} should keep the value chosen by the resolver",
                 i
             );
         }
     }
+}
 
-    #[test]
-    fn test_split_operation() {
-        // This test specifically targets the split operation
-        let mut mst = MST::new();
+/// # Remove Operation Tests
+///
+/// These tests verify that removing keys keeps the tree in the same
+/// canonical shape as if the keys had never been inserted.
+mod remove_tests {
+    use super::*;
 
-        // Insert enough values to trigger multiple splits
-        for i in 0..20u8 {
-            let key = create_key(&[i, i, i, i]);
+    #[test]
+    fn test_retain_matches_inserting_kept_keys() {
+        // This test verifies that retain yields the canonical tree of the kept
+        // entries, visits each entry once in key order, and can empty the tree
+        let mut tree = MST::new();
+        let mut kept_tree = MST::new();
+        for i in 0..500u32 {
+            let key = create_key(&i.to_be_bytes());
             let value = TestValue {
                 key,
-                data: [i, 0, 0, 0],
+                data: [i as u8, (i >> 8) as u8, 0, 0],
             };
-            mst.insert(key, value);
-
-            // After each insertion, verify ALL previously inserted keys
-            for j in 0..=i {
-                let check_key = create_key(&[j, j, j, j]);
-                let retrieved = mst.get_value(check_key);
-                assert!(
-                    retrieved.is_some(),
-                    "After inserting key {}, could not retrieve key {}",
-                    i,
-                    j
-                );
-                assert_eq!(
-                    retrieved.unwrap().data[0],
-                    j,
-                    "Retrieved incorrect value for key {}",
-                    j
-                );
+            tree.insert(key, value);
+            if i % 7 != 3 {
+                kept_tree.insert(key, value);
             }
         }
+        let all_keys: Vec<_> = tree.keys().collect();
+
+        let mut visited = Vec::new();
+        tree.retain(|key, value| {
+            visited.push(*key);
+            let i = value.data[0] as u32 | (value.data[1] as u32) << 8;
+            i % 7 != 3
+        });
+        assert_eq!(
+            visited, all_keys,
+            "Each entry should be offered once, in order"
+        );
+        assert_eq!(
+            tree.root, kept_tree.root,
+            "Retained tree should be canonical"
+        );
+        assert_eq!(tree.len(), kept_tree.len());
 
-        // Insert values in reverse order to test different split patterns
-        for i in (20..40u8).rev() {
-            let key = create_key(&[i, i, i, i]);
+        let root = tree.root;
+        tree.retain(|_, _| true);
+        assert_eq!(tree.root, root, "Keeping everything should change nothing");
+
+        tree.retain(|_, _| false);
+        assert!(tree.is_empty(), "Keeping nothing should empty the tree");
+        assert_eq!(tree.root, None);
+    }
+
+    #[test]
+    fn test_remove_restores_canonical_root() {
+        // This test verifies that removing a set of keys produces exactly the
+        // tree that inserting only the remaining keys would have produced
+        let mut full_tree = MST::new();
+        let mut partial_tree = MST::new();
+
+        for i in 0..200u32 {
+            let key = create_key(&i.to_be_bytes());
             let value = TestValue {
                 key,
-                data: [i, 0, 0, 0],
+                data: [i as u8, (i >> 8) as u8, 0, 0],
             };
-            mst.insert(key, value);
+            full_tree.insert(key, value);
+            if i % 3 != 0 {
+                partial_tree.insert(key, value);
+            }
+        }
 
-            // Verify the key we just inserted
-            let retrieved = mst.get_value(key);
-            assert!(
-                retrieved.is_some(),
-                "Failed to retrieve key {} after insertion",
-                i
-            );
+        for i in (0..200u32).step_by(3) {
+            let key = create_key(&i.to_be_bytes());
+            let removed = full_tree.remove(key);
             assert_eq!(
-                retrieved.unwrap().data[0],
-                i,
-                "Retrieved incorrect value for key {}",
+                removed.map(|value| value.data),
+                Some([i as u8, (i >> 8) as u8, 0, 0]),
+                "Remove should return the old value for key {}",
                 i
             );
-        }
-
-        // Now verify ALL keys are retrievable
-        for i in 0..40u8 {
-            let key = create_key(&[i, i, i, i]);
-            let retrieved = mst.get_value(key);
             assert!(
-                retrieved.is_some(),
-                "After all insertions, could not retrieve key {}",
-                i
-            );
-            assert_eq!(
-                retrieved.unwrap().data[0],
-                i,
-                "Retrieved incorrect value for key {}",
+                full_tree.get_value(key).is_none(),
+                "Key {} should not be retrievable after removal",
                 i
             );
         }
+
+        assert_eq!(
+            full_tree.root, partial_tree.root,
+            "Removing keys should yield the same root as never inserting them"
+        );
+        assert_eq!(
+            full_tree.to_list(),
+            partial_tree.to_list(),
+            "Both trees should list the same values"
+        );
+
+        // Removing an absent key is a no-op
+        let root_before = full_tree.root;
+        let absent = create_key(&0u32.to_be_bytes());
+        assert_eq!(
+            full_tree.remove(absent),
+            None,
+            "Absent key should return None"
+        );
+        assert_eq!(full_tree.root, root_before, "Root should be unchanged");
     }
 
     #[test]
-    fn test_progressive_tree_growth() {
-        // This test verifies tree integrity during growth by incrementally
-        // inserting values and checking retrievability
+    fn test_remove_first_entry_in_page() {
+        // This test verifies removing the first entry of a page, which requires
+        // joining the page's low subtree with the entry's next subtree
         let mut mst = MST::new();
-
-        // Track keys we've inserted
-        let mut inserted_keys = HashSet::new();
-        let mut failed_retrievals = Vec::new();
-
-        // Insert keys with incremental verification
-        for i in 0..120u32 {
-            // Use a key pattern with good distribution
-            let key_bytes = [
-                (i % 256) as u8,
-                ((i * 3) % 256) as u8,
-                ((i * 7) % 256) as u8,
-                ((i * 11) % 256) as u8,
-            ];
-            let key = create_key(&key_bytes);
-
-            // Insert the key
-            let value = TestValue {
+        let mut keys = Vec::new();
+        for i in 0..300u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
                 key,
-                data: [i as u8, 0, 0, 0],
-            };
-            mst.insert(key, value);
-            inserted_keys.insert(key);
-
-            // After every 10 insertions, verify ALL previously inserted keys
-            if i % 10 == 0 && i > 0 {
-                for &check_key in &inserted_keys {
-                    if mst.get_value(check_key).is_none() {
-                        failed_retrievals.push((i, check_key));
-                    }
-                }
-            }
+                TestValue {
+                    key,
+                    data: [i as u8, (i >> 8) as u8, 0, 0],
+                },
+            );
+            keys.push(key);
         }
 
-        // Assertion fails if any keys were not retrievable
+        let root_page = mst.get(mst.root.unwrap()).unwrap();
         assert!(
-            failed_retrievals.is_empty(),
-            "{} keys could not be retrieved after insertion",
-            failed_retrievals.len()
+            root_page.low.is_some(),
+            "Root page should have a low subtree for this test"
+        );
+        let first_key = root_page.list[0].key;
+
+        assert!(
+            mst.remove(first_key).is_some(),
+            "First entry should be removed"
+        );
+
+        let mut expected = MST::new();
+        for &key in keys.iter().filter(|&&key| key != first_key) {
+            expected.insert(key, mst.get_value(key).unwrap());
+        }
+        assert_eq!(
+            mst.root, expected.root,
+            "Joining low and next should give the canonical tree"
         );
+        assert_eq!(mst.to_list().len(), 299, "Exactly one entry should be gone");
     }
 
     #[test]
-    fn test_targeted_split_edge_case() {
-        // This test targets specific edge cases in the split algorithm
+    fn test_remove_only_entry_empties_tree() {
+        // This test verifies that removing the only entry resets the tree
         let mut mst = MST::new();
+        let key = create_key(b"only");
+        let value = TestValue {
+            key,
+            data: [1, 2, 3, 4],
+        };
+        mst.insert(key, value);
 
-        // First insert some foundation keys
-        for i in 0..20u8 {
-            let key = create_key(&[i, i + 1, i + 2, i + 3]);
-            let value = TestValue {
+        assert_eq!(mst.remove(key), Some(value), "Should return the only value");
+        assert_eq!(
+            mst.root, None,
+            "Tree should be empty after removing its only entry"
+        );
+        assert!(mst.to_list().is_empty(), "Tree should list no values");
+        assert_eq!(mst.remove(key), None, "Second removal should return None");
+    }
+
+    #[test]
+    fn test_remove_after_replacing_root_keeps_len_in_range() {
+        // This test verifies that removing from a tree whose root was swapped in
+        // behind its entry count neither underflows nor keeps a stale count
+        let mut source = MST::new();
+        for i in 0..100u32 {
+            let key = create_key(&i.to_be_bytes());
+            source.insert(
                 key,
-                data: [i, 0, 0, 0],
-            };
-            mst.insert(key, value);
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
         }
+        let mut tree = MST::<TestValue>::new();
+        for (key, page) in source.store.iter() {
+            tree.store.put(*key, page.clone());
+        }
+        tree.root = source.root;
+        assert_eq!(tree.len(), 0, "The count was not updated with the root");
 
-        // Now insert keys with patterns that might trigger specific split behaviors
-        let challenging_patterns = [
-            [100, 120, 140, 160], // Wide spread values
-            [101, 101, 101, 101], // Identical bytes
-            [255, 254, 253, 252], // High values
-            [128, 128, 128, 128], // Middle values
-            [122, 120, 96, 150],  // Mix of values
-        ];
+        let key = create_key(&7u32.to_be_bytes());
+        assert!(tree.remove(key).is_some(), "Key should be removed");
+        assert_eq!(tree.len(), 99, "The count should follow the new root");
+        assert_eq!(tree.len(), tree.iter().count());
+    }
+}
 
-        for (i, &pattern) in challenging_patterns.iter().enumerate() {
-            let key = create_key(&pattern);
-            let value = TestValue {
-                key,
-                data: [(200 + i) as u8, 0, 0, 0],
-            };
+/// # Performance Tests
+///
+/// These tests verify the MST performs well with larger datasets.
+mod performance_tests {
+    use super::*;
+    use rand::seq::SliceRandom;
+    use rand::thread_rng;
 
-            mst.insert(key, value);
+    #[test]
+    fn test_large_dataset() {
+        // This test verifies MST performance with a larger dataset
+        // and validates correct behavior when merging trees
+        let mut nums_a: Vec<u32> = (20..=30).collect();
+        let mut nums_b: Vec<u32> = (5..=57).collect();
 
-            // Verify the key is retrievable immediately after insertion
-            let retrieved = mst.get_value(key);
-            assert!(
-                retrieved.is_some(),
-                "Could not retrieve key with pattern {:?} after insertion",
-                pattern
+        // Shuffle inputs to test random insertion order
+        let mut rng = thread_rng();
+        nums_a.shuffle(&mut rng);
+        nums_b.shuffle(&mut rng);
+
+        // Create and populate trees
+        let mut tree_a = MST::new();
+        let mut tree_b = MST::new();
+
+        // Insert shuffled items into trees
+        for &num in &nums_a {
+            let key = create_key(&num.to_be_bytes());
+            tree_a.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [num as u8, 0, 0, 0],
+                },
             );
         }
 
-        // Verify ALL keys are still retrievable
-        // (foundation keys and challenge keys)
-        for i in 0..20u8 {
-            let key = create_key(&[i, i + 1, i + 2, i + 3]);
-            assert!(
-                mst.get_value(key).is_some(),
-                "Could not retrieve foundation key [{}]",
-                i
+        for &num in &nums_b {
+            let key = create_key(&num.to_be_bytes());
+            tree_b.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [num as u8, 0, 0, 0],
+                },
             );
         }
 
-        for &pattern in &challenging_patterns {
-            let key = create_key(&pattern);
+        // Merge large trees
+        let (merged_root_key, merged_store) = tree_a.merge(&tree_b).unwrap();
+        let merged_tree = MST::with_store(merged_root_key, merged_store);
+
+        // Calculate expected size (unique items after merge)
+        let expected_unique_count = 53; // 11 + 53 - 11 (overlap)
+
+        // Verify merged tree contains correct number of items
+        assert_eq!(
+            merged_tree.to_list().len(),
+            expected_unique_count,
+            "Merged tree should contain all unique items"
+        );
+
+        // Verify items are in sorted order
+        let items = merged_tree.to_list();
+        for i in 1..items.len() {
             assert!(
-                mst.get_value(key).is_some(),
-                "Could not retrieve challenge key {:?}",
-                pattern
+                TestValue::compare_keys(&items[i - 1].key, &items[i].key)
+                    != std::cmp::Ordering::Greater,
+                "Items should maintain sorted order in large dataset"
             );
         }
     }
 }
+
+/// # Specialized Tests
+///
+/// These tests target specific edge cases and behaviors in the MST implementation.
+mod specialized_tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dot() {
+        // This test verifies that to_dot emits one node per page and one edge per
+        // child pointer, wrapped in a digraph
+        let mut mst = MST::new();
+        for i in 0..200u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        let dot = mst.to_dot();
+        let pages = mst.store.reachable_set(mst.root.unwrap()).len();
+        assert!(
+            dot.starts_with("digraph MST {"),
+            "Output should be a digraph"
+        );
+        assert!(dot.ends_with("}\n"), "The digraph should be closed");
+        assert_eq!(
+            dot.matches("[label=\"level ").count(),
+            pages,
+            "Every page should be one node"
+        );
+        assert_eq!(
+            dot.matches(" -> ").count(),
+            pages - 1,
+            "Every page but the root should have one incoming edge"
+        );
+
+        let empty = MST::<TestValue>::new();
+        assert_eq!(
+            empty.to_dot().matches(" -> ").count(),
+            0,
+            "An empty tree has no edges"
+        );
+    }
+
+    #[test]
+    fn test_display_matches_dump() {
+        // This test verifies that formatting a tree writes the dump output, and
+        // that an empty tree formats as an empty string
+        let mut mst = MST::new();
+        for i in 0..50u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        assert_eq!(mst.to_string(), mst.dump());
+        assert_eq!(
+            format!("{mst}").matches(" => ").count(),
+            50,
+            "Every entry should be formatted"
+        );
+        assert_eq!(MST::<TestValue>::new().to_string(), "");
+    }
+
+    #[test]
+    fn test_tree_consistency() {
+        // This test verifies tree maintains structural consistency
+        // throughout a series of operations
+        let mut mst = MST::new();
+
+        // Insert values and check consistency after each insertion
+        for i in 0..10u8 {
+            let key = create_key(&[i, 0, 0, 0]);
+            let value = TestValue {
+                key,
+                data: [i, 0, 0, 0],
+            };
+            mst.insert(key, value);
+
+            // After each insertion:
+            // 1. Verify the list is in sorted order
+            let list = mst.to_list();
+            for j in 1..list.len() {
+                assert!(
+                    TestValue::compare_keys(&list[j - 1].key, &list[j].key)
+                        != std::cmp::Ordering::Greater,
+                    "List is not in sorted order after inserting key {}",
+                    i
+                );
+            }
+
+            // 2. Verify all previously inserted keys are still retrievable
+            for j in 0..=i {
+                let check_key = create_key(&[j, 0, 0, 0]);
+                assert!(
+                    mst.get_value(check_key).is_some(),
+                    "Key {} not retrievable after inserting key {}",
+                    j,
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_interleaved_operations() {
+        // This test verifies that the tree handles interleaved insert and lookup
+        // operations correctly
+        const A: u8 = 11; // Define a constant for the pattern generation
+
+        let mut mst = MST::new();
+        let mut keys = Vec::new();
+
+        // Insert 50 values with complex patterns
+        for i in 0..50u8 {
+            let key = create_key(&[i, i % A, i % 7, i % 13]); // Use a mix of patterns
+            keys.push(key);
+
+            let value = TestValue {
+                key,
+                data: [i, 0, 0, 0],
+            };
+            mst.insert(key, value);
+
+            // After each insertion, check a previously inserted key
+            if !keys.is_empty() && i > 0 {
+                let idx = (i as usize) % keys.len();
+                let check_key = keys[idx];
+                let retrieved = mst.get_value(check_key);
+
+                assert!(
+                    retrieved.is_some(),
+                    "Failed to retrieve key at index {} after inserting key {}",
+                    idx,
+                    i
+                );
+            }
+        }
+
+        // Finally, verify all inserted keys are retrievable
+        for (i, &key) in keys.iter().enumerate() {
+            let retrieved = mst.get_value(key);
+            assert!(
+                retrieved.is_some(),
+                "Failed to retrieve key at index {} after all insertions",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_operation() {
+        // This test specifically targets the split operation
+        let mut mst = MST::new();
+
+        // Insert enough values to trigger multiple splits
+        for i in 0..20u8 {
+            let key = create_key(&[i, i, i, i]);
+            let value = TestValue {
+                key,
+                data: [i, 0, 0, 0],
+            };
+            mst.insert(key, value);
+
+            // After each insertion, verify ALL previously inserted keys
+            for j in 0..=i {
+                let check_key = create_key(&[j, j, j, j]);
+                let retrieved = mst.get_value(check_key);
+                assert!(
+                    retrieved.is_some(),
+                    "After inserting key {}, could not retrieve key {}",
+                    i,
+                    j
+                );
+                assert_eq!(
+                    retrieved.unwrap().data[0],
+                    j,
+                    "Retrieved incorrect value for key {}",
+                    j
+                );
+            }
+        }
+
+        // Insert values in reverse order to test different split patterns
+        for i in (20..40u8).rev() {
+            let key = create_key(&[i, i, i, i]);
+            let value = TestValue {
+                key,
+                data: [i, 0, 0, 0],
+            };
+            mst.insert(key, value);
+
+            // Verify the key we just inserted
+            let retrieved = mst.get_value(key);
+            assert!(
+                retrieved.is_some(),
+                "Failed to retrieve key {} after insertion",
+                i
+            );
+            assert_eq!(
+                retrieved.unwrap().data[0],
+                i,
+                "Retrieved incorrect value for key {}",
+                i
+            );
+        }
+
+        // Now verify ALL keys are retrievable
+        for i in 0..40u8 {
+            let key = create_key(&[i, i, i, i]);
+            let retrieved = mst.get_value(key);
+            assert!(
+                retrieved.is_some(),
+                "After all insertions, could not retrieve key {}",
+                i
+            );
+            assert_eq!(
+                retrieved.unwrap().data[0],
+                i,
+                "Retrieved incorrect value for key {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_progressive_tree_growth() {
+        // This test verifies tree integrity during growth by incrementally
+        // inserting values and checking retrievability
+        let mut mst = MST::new();
+
+        // Track keys we've inserted
+        let mut inserted_keys = HashSet::new();
+        let mut failed_retrievals = Vec::new();
+
+        // Insert keys with incremental verification
+        for i in 0..120u32 {
+            // Use a key pattern with good distribution
+            let key_bytes = [
+                (i % 256) as u8,
+                ((i * 3) % 256) as u8,
+                ((i * 7) % 256) as u8,
+                ((i * 11) % 256) as u8,
+            ];
+            let key = create_key(&key_bytes);
+
+            // Insert the key
+            let value = TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            };
+            mst.insert(key, value);
+            inserted_keys.insert(key);
+
+            // After every 10 insertions, verify ALL previously inserted keys
+            if i % 10 == 0 && i > 0 {
+                for &check_key in &inserted_keys {
+                    if mst.get_value(check_key).is_none() {
+                        failed_retrievals.push((i, check_key));
+                    }
+                }
+            }
+        }
+
+        // Assertion fails if any keys were not retrievable
+        assert!(
+            failed_retrievals.is_empty(),
+            "{} keys could not be retrieved after insertion",
+            failed_retrievals.len()
+        );
+    }
+
+    #[test]
+    fn test_targeted_split_edge_case() {
+        // This test targets specific edge cases in the split algorithm
+        let mut mst = MST::new();
+
+        // First insert some foundation keys
+        for i in 0..20u8 {
+            let key = create_key(&[i, i + 1, i + 2, i + 3]);
+            let value = TestValue {
+                key,
+                data: [i, 0, 0, 0],
+            };
+            mst.insert(key, value);
+        }
+
+        // Now insert keys with patterns that might trigger specific split behaviors
+        let challenging_patterns = [
+            [100, 120, 140, 160], // Wide spread values
+            [101, 101, 101, 101], // Identical bytes
+            [255, 254, 253, 252], // High values
+            [128, 128, 128, 128], // Middle values
+            [122, 120, 96, 150],  // Mix of values
+        ];
+
+        for (i, &pattern) in challenging_patterns.iter().enumerate() {
+            let key = create_key(&pattern);
+            let value = TestValue {
+                key,
+                data: [(200 + i) as u8, 0, 0, 0],
+            };
+
+            mst.insert(key, value);
+
+            // Verify the key is retrievable immediately after insertion
+            let retrieved = mst.get_value(key);
+            assert!(
+                retrieved.is_some(),
+                "Could not retrieve key with pattern {:?} after insertion",
+                pattern
+            );
+        }
+
+        // Verify ALL keys are still retrievable
+        // (foundation keys and challenge keys)
+        for i in 0..20u8 {
+            let key = create_key(&[i, i + 1, i + 2, i + 3]);
+            assert!(
+                mst.get_value(key).is_some(),
+                "Could not retrieve foundation key [{}]",
+                i
+            );
+        }
+
+        for &pattern in &challenging_patterns {
+            let key = create_key(&pattern);
+            assert!(
+                mst.get_value(key).is_some(),
+                "Could not retrieve challenge key {:?}",
+                pattern
+            );
+        }
+    }
+}
+
+/// # Iterator Tests
+///
+/// These tests verify the lazy, stack-based iterators over tree entries.
+mod iterator_tests {
+    use super::*;
+
+    #[test]
+    fn test_iter_yields_ascending_entries() {
+        // This test verifies that iter() visits every entry exactly once in
+        // strictly ascending key order
+        let mut mst = MST::new();
+        let mut expected_keys = Vec::new();
+
+        for i in 0..500u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, (i >> 8) as u8, 0, 0],
+                },
+            );
+            expected_keys.push(key);
+        }
+        expected_keys.sort_by(TestValue::compare_keys);
+
+        let keys: Vec<_> = mst.iter().map(|entry| entry.key).collect();
+        assert_eq!(keys, expected_keys, "iter() should yield all keys in order");
+
+        for pair in keys.windows(2) {
+            assert_eq!(
+                TestValue::compare_keys(&pair[0], &pair[1]),
+                std::cmp::Ordering::Less,
+                "Keys should be strictly ascending"
+            );
+        }
+
+        // to_list is built on the iterator and must agree with it
+        let values: Vec<_> = mst.iter().map(|entry| entry.value).collect();
+        assert_eq!(mst.to_list(), values, "to_list should match iter()");
+    }
+
+    #[test]
+    fn test_iter_prefix_and_empty_tree() {
+        // This test verifies that a prefix can be streamed without draining
+        // the iterator, and that an empty tree yields nothing
+        let empty: MST<TestValue> = MST::new();
+        assert_eq!(
+            empty.iter().count(),
+            0,
+            "Empty tree should yield no entries"
+        );
+
+        let mut mst = MST::new();
+        for i in 0..100u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        let all = mst.to_list();
+        let mut iter = mst.iter();
+        let prefix: Vec<_> = iter.by_ref().take(10).map(|entry| entry.value).collect();
+        assert_eq!(
+            prefix,
+            all[..10],
+            "Prefix should match the first ten values"
+        );
+
+        // The same iterator resumes where it was paused
+        let rest: Vec<_> = iter.map(|entry| entry.value).collect();
+        assert_eq!(rest, all[10..], "Resumed iterator should yield the rest");
+    }
+
+    #[test]
+    fn test_iter_rev_is_reverse_of_iter() {
+        // This test verifies that iter_rev() yields exactly the reverse of
+        // iter() and is strictly descending
+        let mut mst = MST::new();
+        assert_eq!(mst.iter_rev().count(), 0, "Empty tree should yield nothing");
+
+        for i in 0..500u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, (i >> 8) as u8, 0, 0],
+                },
+            );
+        }
+
+        let mut forward: Vec<_> = mst.iter().map(|entry| entry.key).collect();
+        let backward: Vec<_> = mst.iter_rev().map(|entry| entry.key).collect();
+        for pair in backward.windows(2) {
+            assert_eq!(
+                TestValue::compare_keys(&pair[0], &pair[1]),
+                std::cmp::Ordering::Greater,
+                "Keys should be strictly descending"
+            );
+        }
+
+        forward.reverse();
+        assert_eq!(backward, forward, "iter_rev() should reverse iter()");
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        // This test verifies that range() yields exactly the entries with
+        // start <= key < end, including open and empty ranges
+        let mut mst = MST::new();
+        for i in 0..300u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, (i >> 8) as u8, 0, 0],
+                },
+            );
+        }
+        let keys: Vec<_> = mst.iter().map(|entry| entry.key).collect();
+
+        // Bounds on existing keys: start is inclusive, end is exclusive
+        let found: Vec<_> = mst
+            .range(Some(keys[50]), Some(keys[120]))
+            .map(|entry| entry.key)
+            .collect();
+        assert_eq!(found, keys[50..120], "Range should be [start, end)");
+
+        // A start between two existing keys begins at the next key
+        let mut between = keys[10];
+        between[31] = between[31].wrapping_add(1);
+        assert!(
+            TestValue::compare_keys(&between, &keys[11]) == std::cmp::Ordering::Less,
+            "Test key should fall strictly between two entries"
+        );
+        let first = mst.range(Some(between), None).next().map(|entry| entry.key);
+        assert_eq!(first, Some(keys[11]), "Range should start at the next key");
+
+        // Open bounds on either side
+        assert_eq!(
+            mst.range(None, None).count(),
+            300,
+            "Unbounded range is everything"
+        );
+        assert_eq!(
+            mst.range(None, Some(keys[42])).count(),
+            42,
+            "Open start should begin at the smallest key"
+        );
+        assert_eq!(
+            mst.range(Some(keys[250]), None).count(),
+            50,
+            "Open end should run to the largest key"
+        );
+
+        // An end below every key gives an empty range
+        let smallest = mst::MSTKey::clone_from_slice(&[0; 32]);
+        assert_eq!(
+            mst.range(None, Some(smallest)).count(),
+            0,
+            "End below all keys should yield nothing"
+        );
+    }
+
+    #[test]
+    fn test_keys_and_values() {
+        // This test verifies that keys() and values() match iter(), yield len()
+        // items with strictly ascending keys, and stay lazy under take
+        let mut mst = MST::new();
+        for i in 0..400u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        let keys: Vec<_> = mst.keys().collect();
+        assert_eq!(keys.len(), mst.len(), "keys() should yield len() keys");
+        assert_eq!(
+            mst.values().count(),
+            mst.len(),
+            "values() should yield len() values"
+        );
+        for pair in keys.windows(2) {
+            assert_eq!(
+                TestValue::compare_keys(&pair[0], &pair[1]),
+                std::cmp::Ordering::Less,
+                "Keys should be strictly ascending"
+            );
+        }
+        for ((key, value), entry) in keys.iter().zip(mst.values()).zip(mst.iter()) {
+            assert_eq!((*key, value), (entry.key, &entry.value));
+        }
+        assert_eq!(mst.keys().take(5).collect::<Vec<_>>(), keys[..5]);
+        assert!(MST::<TestValue>::new().keys().next().is_none());
+    }
+
+    #[test]
+    fn test_iter_pairs_yields_tree_keys() {
+        // This test verifies that iter_pairs pairs each value with the key it is
+        // stored under, even when the value embeds a different key
+        let mut mst = MST::new();
+        for i in 0..200u32 {
+            let key = create_key(&i.to_be_bytes());
+            let embedded = create_key(&(i + 1000).to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key: embedded,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        let pairs: Vec<_> = mst.iter_pairs().collect();
+        assert_eq!(pairs.len(), mst.len());
+        for ((key, value), entry) in pairs.iter().zip(mst.iter()) {
+            assert_eq!(*key, entry.key, "The key should be the entry's key");
+            assert_ne!(*key, value.key, "The key should not come from the value");
+            assert!(std::ptr::eq(*value, &entry.value));
+        }
+        assert!(MST::<TestValue>::new().iter_pairs().next().is_none());
+    }
+
+    #[test]
+    fn test_into_iter() {
+        // This test verifies that consuming the tree yields every entry once,
+        // in the same ascending order as iter()
+        let mut mst = MST::new();
+        for i in 0..400u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        let expected: Vec<_> = mst.iter().map(|entry| (entry.key, entry.value)).collect();
+
+        let mut seen = HashSet::new();
+        let mut owned = Vec::new();
+        for (key, value) in mst {
+            assert!(seen.insert(key), "Each key should be yielded once");
+            owned.push((key, value));
+        }
+        assert_eq!(owned, expected, "into_iter() should match iter()");
+        assert_eq!(owned.len(), 400);
+
+        assert!(MST::<TestValue>::new().into_iter().next().is_none());
+    }
+}
+
+/// # Hasher Tests
+///
+/// These tests verify that trees can be built on hash functions other
+/// than the default SHA-256.
+mod hasher_tests {
+    use super::*;
+    use mst::{FixedKeyMST, Merge, Reference, Truncated, hash_page_with, hash_with};
+    use sha2::digest::Output;
+    use sha2::{Sha256, Sha512};
+
+    type WideKey = Output<Sha512>;
+
+    /// A value keyed by 64-byte SHA-512 digests
+    #[derive(Debug, Copy, Clone, PartialEq, Hash)]
+    struct WideValue {
+        key: WideKey,
+        data: [u8; 4],
+    }
+
+    impl AsRef<[u8]> for WideValue {
+        fn as_ref(&self) -> &[u8] {
+            &self.data
+        }
+    }
+
+    impl Reference for WideValue {
+        type Key = WideKey;
+        fn refs(&self) -> Vec<Self::Key> {
+            vec![]
+        }
+    }
+
+    impl Merge for WideValue {
+        fn merge(self, other: Self) -> Self {
+            other
+        }
+    }
+
+    impl KeyComparable for WideValue {
+        type Key = WideKey;
+
+        fn compare_keys(key1: &Self::Key, key2: &Self::Key) -> std::cmp::Ordering {
+            key1.cmp(key2)
+        }
+    }
+
+    thread_local! {
+        static DIGESTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    /// SHA-256, counting the digests computed on the current thread
+    #[derive(Default)]
+    struct CountingSha256(Sha256);
+
+    impl mst::MstHasher for CountingSha256 {
+        type Key = mst::MSTKey;
+
+        fn write_bytes(&mut self, bytes: &[u8]) {
+            sha2::Digest::update(&mut self.0, bytes);
+        }
+
+        fn finish(self) -> Self::Key {
+            DIGESTS.with(|count| count.set(count.get() + 1));
+            sha2::Digest::finalize(self.0)
+        }
+    }
+
+    #[test]
+    fn test_insert_only_hashes_new_pages() {
+        // This test verifies that an insert hashes the key once for its level and
+        // otherwise only the pages of the new tree that the old tree lacked, each
+        // with its values, never a page it left unchanged
+        let mut tree = MST::<TestValue, CountingSha256>::with_hasher();
+        for i in 0..1000u32 {
+            let key = create_key(&i.to_be_bytes());
+            let pages_before = tree
+                .root
+                .map(|root| tree.store.reachable_set(root))
+                .unwrap_or_default();
+
+            DIGESTS.with(|count| count.set(0));
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+            let digests = DIGESTS.with(|count| count.get());
+
+            let new_page_digests: usize = tree
+                .store
+                .reachable_set(tree.root.unwrap())
+                .difference(&pages_before)
+                .map(|page| 1 + tree.store.get(*page).unwrap().list.len())
+                .sum();
+            assert_eq!(
+                digests,
+                new_page_digests + 1,
+                "Insert {} should hash only its key and the pages it adds",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_reuses_shared_pages() {
+        // This test verifies that merging two trees with a common prefix of keys
+        // gives the same tree as reinserting one into the other, while hashing a
+        // small fraction of the pages
+        let build = |keys: &mut dyn Iterator<Item = u32>| {
+            let mut tree = MST::<TestValue, CountingSha256>::with_hasher();
+            for i in keys {
+                let key = create_key(&i.to_be_bytes());
+                tree.insert(
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, 0, 0, 0],
+                    },
+                );
+            }
+            tree
+        };
+        let mut left = build(&mut (0..2000).chain(2000..2050));
+        let right = build(&mut (0..2000).chain(3000..3050));
+
+        DIGESTS.with(|count| count.set(0));
+        let mut reinserted = left.clone();
+        for entry in right.iter() {
+            reinserted.insert(entry.key, entry.value);
+        }
+        let reinsert_digests = DIGESTS.with(|count| count.get());
+
+        DIGESTS.with(|count| count.set(0));
+        let (root, store) = left.merge(&right).unwrap();
+        let merge_digests = DIGESTS.with(|count| count.get());
+
+        assert_eq!(root, reinserted.root, "Merge should match reinsertion");
+        let merged = MST::<TestValue, CountingSha256>::with_store_and_hasher(root, store);
+        assert_eq!(merged.verify_integrity(), Ok(()));
+        assert!(
+            merge_digests * 10 < reinsert_digests,
+            "Merge should reuse the shared pages: {} vs {} digests",
+            merge_digests,
+            reinsert_digests
+        );
+    }
+
+    #[test]
+    fn test_insert_batch_matches_sequential_with_fewer_hashes() {
+        // This test verifies that a batch, including keys already in the tree and
+        // keys repeated within the batch, yields the same tree as inserting it in
+        // order while computing fewer digests
+        let value = |i: u32, tag: u8| {
+            let key = create_key(&i.to_be_bytes());
+            (
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, tag, 0, 0],
+                },
+            )
+        };
+        let mut sequential = MST::<TestValue, CountingSha256>::with_hasher();
+        for i in 0..1000u32 {
+            let (key, v) = value(i, 0);
+            sequential.insert(key, v);
+        }
+        let mut batched = sequential.clone();
+
+        let mut items: Vec<_> = (900..1900u32).map(|i| value(i, 1)).collect();
+        items.extend((1800..1850u32).map(|i| value(i, 2)));
+
+        DIGESTS.with(|count| count.set(0));
+        for (key, v) in items.clone() {
+            sequential.insert(key, v);
+        }
+        let sequential_digests = DIGESTS.with(|count| count.get());
+
+        DIGESTS.with(|count| count.set(0));
+        let root = batched.insert_batch(items);
+        let batch_digests = DIGESTS.with(|count| count.get());
+
+        assert_eq!(
+            root, sequential.root,
+            "Batch should match sequential inserts"
+        );
+        assert_eq!(batched.root, sequential.root);
+        assert_eq!(batched.len(), 1900, "Batch should count new keys only");
+        assert_eq!(batched.get_value(value(1820, 0).0), Some(value(1820, 2).1));
+        assert!(
+            batch_digests < sequential_digests,
+            "Batch should hash less: {} vs {}",
+            batch_digests,
+            sequential_digests
+        );
+    }
+
+    #[test]
+    fn test_set_operations_only_rebuild_differing_paths() {
+        // This test verifies that on two mostly overlapping trees, the set
+        // operations hash a small fraction of the pages a rebuild would
+        let build = |keys: &mut dyn Iterator<Item = u32>| {
+            let mut tree = MST::<TestValue, CountingSha256>::with_hasher();
+            for i in keys {
+                let key = create_key(&i.to_be_bytes());
+                tree.insert(
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, 0, 0, 0],
+                    },
+                );
+            }
+            tree
+        };
+        let left = build(&mut (0..3000));
+        let right = build(&mut (0..3000).filter(|i| i % 300 != 7));
+        let pages = left.stats().pages;
+
+        for (name, operation) in [
+            ("union", MST::union as fn(&_, &_) -> _),
+            ("intersection", MST::intersection),
+            ("difference", MST::difference),
+        ] {
+            DIGESTS.with(|count| count.set(0));
+            let result = operation(&left, &right);
+            let digests = DIGESTS.with(|count| count.get());
+            assert!(result.verify_integrity().is_ok());
+            assert!(
+                digests * 4 < pages,
+                "{} should only rebuild the differing paths: {} digests for {} pages",
+                name,
+                digests,
+                pages
+            );
+        }
+    }
+
+    /// SHA-256, except that anything longer than a key (i.e. a page) hashes to
+    /// all zeros
+    #[derive(Default)]
+    struct ZeroPageSha256 {
+        digest: Sha256,
+        written: usize,
+    }
+
+    impl mst::MstHasher for ZeroPageSha256 {
+        type Key = mst::MSTKey;
+
+        fn write_bytes(&mut self, bytes: &[u8]) {
+            self.written += bytes.len();
+            sha2::Digest::update(&mut self.digest, bytes);
+        }
+
+        fn finish(self) -> Self::Key {
+            if self.written > 32 {
+                mst::MSTKey::default()
+            } else {
+                sha2::Digest::finalize(self.digest)
+            }
+        }
+    }
+
+    /// SHA-256, except that a key (32 bytes) hashes to all zeros, placing every
+    /// key at the highest possible level
+    #[derive(Default)]
+    struct ZeroKeySha256 {
+        digest: Sha256,
+        written: usize,
+    }
+
+    impl mst::MstHasher for ZeroKeySha256 {
+        type Key = mst::MSTKey;
+
+        fn write_bytes(&mut self, bytes: &[u8]) {
+            self.written += bytes.len();
+            sha2::Digest::update(&mut self.digest, bytes);
+        }
+
+        fn finish(self) -> Self::Key {
+            if self.written == 32 {
+                mst::MSTKey::default()
+            } else {
+                sha2::Digest::finalize(self.digest)
+            }
+        }
+    }
+
+    #[test]
+    fn test_all_zero_key_hashes_are_capped() {
+        // This test verifies that an all-zero hash gets the capped level at any
+        // base, and that a tree of such keys is one flat page rather than a spine
+        let key = create_key(b"anything");
+        assert_eq!(
+            mst::calc_level_with::<ZeroKeySha256, _>(key),
+            mst::MAX_LEVEL
+        );
+        for base in [2, 16, 256] {
+            assert!(mst::calc_level_base_with::<ZeroKeySha256, _>(key, base) <= mst::MAX_LEVEL);
+        }
+
+        let mut tree = MST::<TestValue, ZeroKeySha256>::with_hasher();
+        for i in 0..20u32 {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        let root = tree.store.get(tree.root.unwrap()).unwrap();
+        assert_eq!(root.level, mst::MAX_LEVEL);
+        assert_eq!(root.list.len(), 20, "Every key shares the top page");
+        assert_eq!(tree.height(), 1);
+    }
+
+    #[test]
+    fn test_all_zero_hashes_are_ordinary() {
+        // This test verifies that a page hashing to all zeros and an all-zero user
+        // key are stored and found like any other, rather than read as empty
+        let mut tree = MST::<TestValue, ZeroPageSha256>::with_hasher();
+        let key = create_key(b"zero page");
+        let value = TestValue {
+            key,
+            data: [1, 0, 0, 0],
+        };
+        tree.insert(key, value);
+        assert_eq!(
+            tree.root,
+            Some(mst::MSTKey::default()),
+            "The only page should hash to all zeros"
+        );
+        assert_eq!(tree.get_value(key), Some(value), "Entry should be found");
+        assert_eq!(tree.iter().count(), 1, "Entry should be iterated");
+        assert_eq!(tree.verify_integrity(), Ok(()));
+        assert_eq!(tree.remove(key), Some(value), "Entry should be removable");
+        assert_eq!(tree.root, None, "Tree should then be empty");
+
+        let mut tree = MST::new();
+        for i in 0..50u32 {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        let zero = mst::MSTKey::default();
+        let value = TestValue {
+            key: zero,
+            data: [2, 0, 0, 0],
+        };
+        tree.insert(zero, value);
+        assert_eq!(
+            tree.get_value(zero),
+            Some(value),
+            "All-zero key should be found"
+        );
+        assert_eq!(tree.first().map(|entry| entry.key), Some(zero));
+        assert_eq!(tree.len(), 51);
+    }
+
+    #[test]
+    fn test_root_page() {
+        // This test verifies that root_page is the page under the root, and None
+        // for an empty tree even when a page is stored under the all-zero key
+        let mut tree = MST::<TestValue, ZeroPageSha256>::with_hasher();
+        assert!(tree.root_page().is_none());
+
+        let key = create_key(b"zero page");
+        tree.insert(
+            key,
+            TestValue {
+                key,
+                data: [1, 0, 0, 0],
+            },
+        );
+        let zero = mst::MSTKey::default();
+        assert_eq!(tree.root, Some(zero));
+        assert_eq!(tree.root_page().map(|page| page.list.len()), Some(1));
+
+        tree.remove(key);
+        assert!(tree.get(zero).is_some(), "The old page is still stored");
+        assert!(tree.root_page().is_none(), "An empty tree has no root page");
+    }
+
+    #[test]
+    fn test_default_hasher_is_sha256() {
+        // This test verifies that MST::new() and an explicit SHA-256 tree
+        // produce identical roots
+        let mut default_tree = MST::new();
+        let mut explicit_tree = MST::<TestValue, Sha256>::with_hasher();
+
+        for i in 0..100u32 {
+            let key = create_key(&i.to_be_bytes());
+            let value = TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            };
+            default_tree.insert(key, value);
+            explicit_tree.insert(key, value);
+        }
+
+        assert_eq!(
+            default_tree.root, explicit_tree.root,
+            "The default hasher should be SHA-256"
+        );
+    }
+
+    #[test]
+    fn test_sha512_tree() {
+        // This test verifies a tree addressed by SHA-512 stores, retrieves,
+        // and orders its 64-byte keys
+        let mut mst = MST::<WideValue, Sha512>::with_hasher();
+
+        for i in 0..200u32 {
+            let key = hash_with::<Sha512, _>(i.to_be_bytes());
+            mst.insert(
+                key,
+                WideValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        assert_eq!(mst.len(), 200, "All keys should be present");
+        assert_eq!(
+            mst.root.unwrap().len(),
+            64,
+            "Page keys should be SHA-512 digests"
+        );
+
+        for i in 0..200u32 {
+            let key = hash_with::<Sha512, _>(i.to_be_bytes());
+            assert_eq!(
+                mst.get_value(key).map(|value| value.data[0]),
+                Some(i as u8),
+                "Key {} should be retrievable",
+                i
+            );
+        }
+
+        let keys: Vec<_> = mst.iter().map(|entry| entry.key).collect();
+        assert!(
+            keys.windows(2).all(|pair| pair[0] < pair[1]),
+            "Keys should iterate in ascending order"
+        );
+
+        // The root is the SHA-512 hash of the root page
+        let root_page = mst.get(mst.root.unwrap()).unwrap();
+        assert_eq!(
+            Some(hash_page_with::<Sha512, _, _>(root_page)),
+            mst.root,
+            "Root should be addressed by the SHA-512 page hash"
+        );
+    }
+
+    /// A value keyed by fixed-length byte arrays
+    #[derive(Debug, Copy, Clone, PartialEq, Hash)]
+    struct ArrayValue<const N: usize> {
+        key: [u8; N],
+        data: [u8; 4],
+    }
+
+    impl<const N: usize> AsRef<[u8]> for ArrayValue<N> {
+        fn as_ref(&self) -> &[u8] {
+            &self.data
+        }
+    }
+
+    impl<const N: usize> Reference for ArrayValue<N> {
+        type Key = [u8; N];
+        fn refs(&self) -> Vec<Self::Key> {
+            vec![]
+        }
+    }
+
+    impl<const N: usize> Merge for ArrayValue<N> {
+        fn merge(self, other: Self) -> Self {
+            other
+        }
+    }
+
+    impl<const N: usize> KeyComparable for ArrayValue<N> {
+        type Key = [u8; N];
+
+        fn compare_keys(key1: &Self::Key, key2: &Self::Key) -> std::cmp::Ordering {
+            key1.cmp(key2)
+        }
+    }
+
+    #[test]
+    fn test_twenty_byte_keys() {
+        // This test verifies a tree over 20-byte keys stores and orders them,
+        // starting from an empty root
+        let mut mst = FixedKeyMST::<ArrayValue<20>, 20>::with_hasher();
+        assert_eq!(mst.root, None, "Empty tree should have no root");
+
+        for i in 0..200u32 {
+            let key = hash_with::<Truncated<Sha256, 20>, _>(i.to_be_bytes());
+            mst.insert(
+                key,
+                ArrayValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+
+        assert_eq!(mst.len(), 200, "All keys should be present");
+        for i in 0..200u32 {
+            let key = hash_with::<Truncated<Sha256, 20>, _>(i.to_be_bytes());
+            assert!(mst.contains_key(key), "Key {} should be present", i);
+        }
+
+        let keys: Vec<_> = mst.iter().map(|entry| entry.key).collect();
+        assert!(
+            keys.windows(2).all(|pair| pair[0] < pair[1]),
+            "Keys should iterate in lexicographic order"
+        );
+    }
+
+    #[test]
+    fn test_full_length_fixed_keys_match_default_tree() {
+        // This test verifies that 32-byte fixed keys are byte-for-byte the
+        // same tree as the default MSTKey tree
+        let mut array_tree = FixedKeyMST::<ArrayValue<32>, 32>::with_hasher();
+        let mut default_tree = MST::new();
+
+        for i in 0..100u32 {
+            let key = create_key(&i.to_be_bytes());
+            let data = [i as u8, 0, 0, 0];
+            default_tree.insert(key, TestValue { key, data });
+            let array_key: [u8; 32] = key.into();
+            array_tree.insert(
+                array_key,
+                ArrayValue {
+                    key: array_key,
+                    data,
+                },
+            );
+        }
+
+        assert_eq!(
+            array_tree.root.unwrap()[..],
+            default_tree.root.unwrap()[..],
+            "Both trees should hash to the same root bytes"
+        );
+    }
+}
+
+/// # Error Tests
+///
+/// These tests verify that fallible operations report errors instead of
+/// panicking when their preconditions are violated.
+mod error_tests {
+    use super::*;
+    use mst::{MSTKey, Merge, MstError, Reference};
+
+    /// A value whose comparator claims every key is greater than every other key
+    #[derive(Debug, Copy, Clone, PartialEq, Hash)]
+    struct BrokenValue {
+        key: MSTKey,
+    }
+
+    impl AsRef<[u8]> for BrokenValue {
+        fn as_ref(&self) -> &[u8] {
+            &self.key
+        }
+    }
+
+    impl Reference for BrokenValue {
+        type Key = MSTKey;
+        fn refs(&self) -> Vec<Self::Key> {
+            vec![]
+        }
+    }
+
+    impl Merge for BrokenValue {
+        fn merge(self, other: Self) -> Self {
+            other
+        }
+    }
+
+    impl KeyComparable for BrokenValue {
+        type Key = MSTKey;
+
+        fn compare_keys(_key1: &Self::Key, _key2: &Self::Key) -> std::cmp::Ordering {
+            std::cmp::Ordering::Greater
+        }
+    }
+
+    #[test]
+    fn test_try_insert_reports_inconsistent_ordering() {
+        // This test verifies that a comparator which is not a total order yields
+        // MstError::InconsistentOrdering from try_insert rather than a panic
+        let first = create_key(&[0, 0, 0, 0]);
+        let second = (1..1000u32)
+            .map(|i| create_key(&i.to_be_bytes()))
+            .find(|key| calc_level(*key) == calc_level(first))
+            .expect("Should find a key on the same level as the first");
+
+        let mut tree: MST<BrokenValue> = MST::new();
+        tree.try_insert(first, BrokenValue { key: first })
+            .expect("Inserting into an empty tree never compares keys");
+        let root_before = tree.root;
+
+        let result = tree.try_insert(second, BrokenValue { key: second });
+        assert_eq!(
+            result,
+            Err(MstError::InconsistentOrdering),
+            "Broken comparator should be reported as an error"
+        );
+        assert_eq!(tree.root, root_before, "Root should be unchanged on error");
+        assert_eq!(tree.len(), 1, "Length should be unchanged on error");
+    }
+}
+
+/// # Query Tests
+///
+/// These tests verify ordered lookups that locate entries relative to a key
+/// rather than by exact match.
+mod query_tests {
+    use super::*;
+    use mst::MSTKey;
+
+    /// Builds a tree from the given integer keys, returning it with its sorted keys
+    fn build_tree(keys: impl IntoIterator<Item = u32>) -> (MST<TestValue>, Vec<MSTKey>) {
+        let mut tree = MST::new();
+        for i in keys {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        let keys = tree.iter().map(|entry| entry.key).collect();
+        (tree, keys)
+    }
+
+    #[test]
+    fn test_locate_points_at_the_entry() {
+        // This test verifies that locate reports a page holding the key at the
+        // reported index, on the key's search path, and None for absent keys
+        let (tree, _) = build_tree((0..400).filter(|i| i % 2 == 0));
+        for i in 0..400u32 {
+            let key = create_key(&i.to_be_bytes());
+            match tree.locate(key) {
+                Some((page_key, index)) => {
+                    assert_eq!(i % 2, 0, "Only present keys are located");
+                    let page = tree.get(page_key).unwrap();
+                    assert_eq!(page.list[index].key, key, "Index should point at the key");
+                    assert_eq!(tree.get_path(key).last().map(|(k, _)| *k), Some(page_key));
+                }
+                None => assert_eq!(i % 2, 1, "Key {} should be located", i),
+            }
+        }
+        assert_eq!(MST::<TestValue>::new().locate(create_key(b"key")), None);
+    }
+
+    #[test]
+    fn test_try_get_value_reports_missing_pages() {
+        // This test verifies that try_get_value answers like get_value while the
+        // path is intact, and names the missing page for keys, present or absent,
+        // whose path leads through it
+        let (mut tree, _) = build_tree((0..400).filter(|i| i % 2 == 0));
+        let intact = tree.clone();
+        let root = tree.get(tree.root.unwrap()).unwrap();
+        let missing = root.list[0].next.or(root.low).unwrap();
+        tree.store.remove(missing);
+
+        let mut misses = 0;
+        for i in 0..400u32 {
+            let key = create_key(&i.to_be_bytes());
+            let through_missing = intact.get_path(key).iter().any(|(k, _)| *k == missing);
+            match tree.try_get_value(key) {
+                Ok(value) => {
+                    assert!(!through_missing, "Key {} should hit the missing page", i);
+                    assert_eq!(value, intact.get_value(key));
+                }
+                Err(err) => {
+                    assert!(through_missing, "Key {} should not need the page", i);
+                    assert_eq!(err, mst::IntegrityError::MissingPage(missing));
+                    assert_eq!(tree.get_value(key), None, "get_value reads it as absent");
+                    misses += 1;
+                }
+            }
+        }
+        assert!(misses > 0, "Some keys should lie below the missing page");
+
+        let mut empty = MST::<TestValue>::new();
+        assert_eq!(empty.try_get_value(create_key(b"key")), Ok(None));
+        empty.root = Some(missing);
+        assert_eq!(
+            empty.try_get_value(create_key(b"key")),
+            Err(mst::IntegrityError::MissingPage(missing)),
+            "A missing root should be reported too"
+        );
+    }
+
+    #[test]
+    fn test_get_many_matches_get_value() {
+        // This test verifies that get_many answers every key like get_value, in
+        // the caller's order, including absent and repeated keys
+        let (tree, _) = build_tree((0..600).filter(|i| i % 2 == 0));
+        let mut requested: Vec<MSTKey> =
+            (0..600u32).map(|i| create_key(&i.to_be_bytes())).collect();
+        requested.extend_from_within(..50);
+        requested.shuffle(&mut thread_rng());
+
+        let results = tree.get_many(&requested);
+        assert_eq!(results.len(), requested.len());
+        for (key, result) in requested.iter().zip(&results) {
+            assert_eq!(
+                *result,
+                tree.get_value(*key),
+                "Results should keep key order"
+            );
+        }
+        assert_eq!(results.iter().filter(|r| r.is_some()).count(), 300 + 25);
+
+        assert!(tree.get_many(&[]).is_empty());
+        assert_eq!(
+            MST::<TestValue>::new().get_many(&requested[..3]),
+            vec![None; 3]
+        );
+    }
+
+    #[test]
+    fn test_floor_and_ceiling() {
+        // This test verifies that floor and ceiling find the nearest entries on
+        // either side of absent keys, and the entry itself for present keys
+        let (tree, keys) = build_tree((0..300).filter(|i| i % 3 == 0));
+
+        for i in 0..300u32 {
+            let key = create_key(&i.to_be_bytes());
+            let position = keys.partition_point(|k| *k < key);
+            let present = keys.get(position) == Some(&key);
+
+            let expected_floor = if present {
+                Some(key)
+            } else {
+                position.checked_sub(1).map(|p| keys[p])
+            };
+            assert_eq!(
+                tree.floor(key).map(|entry| entry.key),
+                expected_floor,
+                "Floor of key {} should be the largest key not above it",
+                i
+            );
+            assert_eq!(
+                tree.ceiling(key).map(|entry| entry.key),
+                keys.get(position).copied(),
+                "Ceiling of key {} should be the smallest key not below it",
+                i
+            );
+        }
+
+        let below_all = MSTKey::clone_from_slice(&[0; 32]);
+        let above_all = MSTKey::clone_from_slice(&[0xff; 32]);
+        assert!(
+            tree.floor(below_all).is_none(),
+            "Nothing is below the smallest key"
+        );
+        assert!(
+            tree.ceiling(above_all).is_none(),
+            "Nothing is above the largest key"
+        );
+        assert_eq!(
+            tree.ceiling(below_all).map(|e| e.key),
+            keys.first().copied()
+        );
+        assert_eq!(tree.floor(above_all).map(|e| e.key), keys.last().copied());
+    }
+
+    #[test]
+    fn test_first_and_last() {
+        // This test verifies that first and last return the smallest and largest
+        // entries, and None for an empty tree
+        let (tree, keys) = build_tree(0..500);
+        assert_eq!(
+            tree.first().map(|e| e.key),
+            keys.first().copied(),
+            "First should be the minimum"
+        );
+        assert_eq!(
+            tree.last().map(|e| e.key),
+            keys.last().copied(),
+            "Last should be the maximum"
+        );
+
+        let (single, keys) = build_tree([7]);
+        assert_eq!(
+            single.first().map(|e| e.key),
+            Some(keys[0]),
+            "Single entry is first"
+        );
+        assert_eq!(
+            single.last().map(|e| e.key),
+            Some(keys[0]),
+            "Single entry is last"
+        );
+
+        let empty = MST::<TestValue>::new();
+        assert!(empty.first().is_none(), "Empty tree has no first entry");
+        assert!(empty.last().is_none(), "Empty tree has no last entry");
+    }
+
+    #[test]
+    fn test_min_and_max_key() {
+        // This test verifies that min_key and max_key bound the keys of the tree,
+        // follow its changes, and are None for an empty tree
+        let (mut tree, keys) = build_tree(0..500);
+        assert_eq!(tree.min_key(), keys.first().copied());
+        assert_eq!(tree.max_key(), keys.last().copied());
+
+        tree.remove(keys[0]);
+        tree.remove(keys[499]);
+        assert_eq!(tree.min_key(), Some(keys[1]), "Removing the minimum");
+        assert_eq!(tree.max_key(), Some(keys[498]), "Removing the maximum");
+
+        let empty = MST::<TestValue>::new();
+        assert_eq!((empty.min_key(), empty.max_key()), (None, None));
+    }
+
+    #[test]
+    fn test_compare_keys_matches_tree_order() {
+        // This test verifies that compare_keys orders keys as the tree stores
+        // them and agrees with where split_at puts a boundary
+        let (tree, keys) = build_tree(0..300);
+        for pair in keys.windows(2) {
+            assert_eq!(
+                MST::<TestValue>::compare_keys(&pair[0], &pair[1]),
+                std::cmp::Ordering::Less
+            );
+            assert_eq!(
+                MST::<TestValue>::compare_keys(&pair[1], &pair[0]),
+                std::cmp::Ordering::Greater
+            );
+        }
+        assert_eq!(
+            MST::<TestValue>::compare_keys(&keys[7], &keys[7]),
+            std::cmp::Ordering::Equal
+        );
+
+        let boundary = keys[150];
+        let (low, high) = tree.split_at(boundary);
+        assert!(
+            low.keys()
+                .all(|key| MST::<TestValue>::compare_keys(&key, &boundary).is_lt())
+        );
+        assert!(
+            high.keys()
+                .all(|key| MST::<TestValue>::compare_keys(&key, &boundary).is_ge())
+        );
+    }
+
+    #[test]
+    fn test_count_range_matches_range() {
+        // This test verifies that count_range agrees with counting the range
+        // iterator for present, absent, reversed, and empty bounds
+        let (tree, keys) = build_tree((0..300).filter(|i| i % 3 != 0));
+        let bounds: Vec<MSTKey> = (0..300u32)
+            .step_by(7)
+            .map(|i| create_key(&i.to_be_bytes()))
+            .chain([keys[0], keys[keys.len() - 1]])
+            .collect();
+
+        for start in &bounds {
+            for end in &bounds {
+                assert_eq!(
+                    tree.count_range(*start, *end),
+                    tree.range(Some(*start), Some(*end)).count(),
+                    "count_range should match the range iterator"
+                );
+            }
+        }
+
+        let empty = MST::<TestValue>::new();
+        assert_eq!(empty.count_range(bounds[0], bounds[1]), 0);
+    }
+
+    #[test]
+    fn test_height_and_depth_of() {
+        // This test verifies that depth_of matches the length of a key's search
+        // path, that height is the deepest such path, and that an empty tree has
+        // height 0 and no depths
+        let (tree, keys) = build_tree(0..500);
+        let depths: Vec<usize> = keys
+            .iter()
+            .map(|key| {
+                tree.depth_of(*key)
+                    .expect("Present key should have a depth")
+            })
+            .collect();
+        for (key, depth) in keys.iter().zip(&depths) {
+            assert_eq!(
+                *depth,
+                tree.get_path(*key).len(),
+                "Depth is the path length"
+            );
+        }
+        assert_eq!(
+            tree.height() as usize,
+            *depths.iter().max().unwrap(),
+            "Height should be the deepest key's depth"
+        );
+        assert_eq!(tree.depth_of(create_key(b"absent")), None);
+
+        let empty = MST::<TestValue>::new();
+        assert_eq!(empty.height(), 0, "Empty tree has height 0");
+        assert_eq!(empty.depth_of(keys[0]), None, "Empty tree has no depths");
+    }
+
+    #[test]
+    fn test_select_and_rank() {
+        // This test verifies that select and rank agree with the sorted key order,
+        // including after removals, and that rank counts smaller keys for absent keys
+        let (mut tree, mut keys) = build_tree((0..400).filter(|i| i % 2 == 0));
+        for i in (0..400u32).filter(|i| i % 10 == 0) {
+            let key = create_key(&i.to_be_bytes());
+            tree.remove(key);
+            keys.retain(|k| *k != key);
+        }
+
+        assert_eq!(
+            tree.get(tree.root.unwrap()).map(|page| page.subtree_size),
+            Some(keys.len() as u64),
+            "The root should count every entry"
+        );
+        for (position, key) in keys.iter().enumerate() {
+            assert_eq!(
+                tree.select(position).map(|entry| entry.key),
+                Some(*key),
+                "Select {} should return the key at that position",
+                position
+            );
+            assert_eq!(tree.rank(*key), position, "Rank should invert select");
+        }
+        assert!(
+            tree.select(keys.len()).is_none(),
+            "Select past the end should return None"
+        );
+
+        for i in (0..400u32).filter(|i| i % 2 == 1) {
+            let key = create_key(&i.to_be_bytes());
+            assert_eq!(
+                tree.rank(key),
+                keys.partition_point(|k| *k < key),
+                "Rank of absent key {} should count the smaller keys",
+                i
+            );
+        }
+
+        let empty = MST::<TestValue>::new();
+        assert!(empty.select(0).is_none(), "Empty tree has no entries");
+        assert_eq!(empty.rank(create_key(b"any")), 0, "Empty tree ranks 0");
+    }
+}
+
+/// # Key Ordering Tests
+///
+/// These tests verify that the tree follows the value type's `compare_keys`
+/// rather than the byte order of the keys, while levels still come from the hash.
+mod ordering_tests {
+    use super::*;
+    use mst::{MSTKey, Merge, Reference};
+    use std::cmp::Ordering;
+
+    /// A value whose keys are ordered from the largest bytes to the smallest
+    #[derive(Debug, Copy, Clone, PartialEq, Hash)]
+    struct ReversedValue {
+        key: MSTKey,
+    }
+
+    impl AsRef<[u8]> for ReversedValue {
+        fn as_ref(&self) -> &[u8] {
+            &self.key
+        }
+    }
+
+    impl Reference for ReversedValue {
+        type Key = MSTKey;
+        fn refs(&self) -> Vec<Self::Key> {
+            vec![]
+        }
+    }
+
+    impl Merge for ReversedValue {
+        fn merge(self, other: Self) -> Self {
+            other
+        }
+    }
+
+    impl KeyComparable for ReversedValue {
+        type Key = MSTKey;
+
+        fn compare_keys(key1: &Self::Key, key2: &Self::Key) -> Ordering {
+            key2.cmp(key1)
+        }
+    }
+
+    #[test]
+    fn test_tree_follows_custom_ordering() {
+        // This test verifies that iteration, lookups, neighbours, and bulk loading
+        // all use the value's comparator, and that the tree stays canonical
+        let keys: Vec<MSTKey> = (0..300u32).map(|i| create_key(&i.to_be_bytes())).collect();
+        let mut tree: MST<ReversedValue> = MST::new();
+        for key in &keys {
+            tree.insert(*key, ReversedValue { key: *key });
+        }
+
+        let mut descending = keys.clone();
+        descending.sort_by(|a, b| b.cmp(a));
+        let iterated: Vec<MSTKey> = tree.iter().map(|entry| entry.key).collect();
+        assert_eq!(iterated, descending, "Iteration should follow compare_keys");
+        assert_eq!(tree.first().map(|e| e.key), descending.first().copied());
+        assert_eq!(tree.select(10).map(|e| e.key), Some(descending[10]));
+        for key in &keys {
+            assert_eq!(
+                tree.get_value(*key),
+                Some(ReversedValue { key: *key }),
+                "Every key should be found under the custom order"
+            );
+        }
+
+        let bulk: MST<ReversedValue> = MST::from_sorted(
+            descending
+                .iter()
+                .map(|key| (*key, ReversedValue { key: *key })),
+        )
+        .unwrap();
+        assert_eq!(bulk.root, tree.root, "Bulk loading should match insertion");
+
+        let mut shuffled = keys.clone();
+        shuffled.shuffle(&mut thread_rng());
+        let mut reinserted: MST<ReversedValue> = MST::new();
+        for key in &shuffled {
+            reinserted.insert(*key, ReversedValue { key: *key });
+        }
+        assert_eq!(
+            reinserted.root, tree.root,
+            "The tree should be canonical under the custom order"
+        );
+    }
+
+    #[test]
+    fn test_levels_ignore_custom_ordering() {
+        // This test verifies that pages keep the hash-derived level of their keys
+        // regardless of the comparator
+        let mut tree: MST<ReversedValue> = MST::new();
+        for i in 0..200u32 {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(key, ReversedValue { key });
+        }
+
+        for (_, page) in tree.store.iter() {
+            for entry in &page.list {
+                assert_eq!(
+                    page.level,
+                    calc_level(entry.key),
+                    "A key's page level should come from its hash"
+                );
+            }
+        }
+    }
+}
+
+/// # Raw Key Tests
+///
+/// These tests verify that keys built from the original bytes round-trip and
+/// order the tree lexicographically.
+mod raw_key_tests {
+    use super::*;
+    use mst::{MAX_RAW_KEY_LEN, MSTKey, raw_key, raw_key_bytes};
+
+    #[test]
+    fn test_raw_key_round_trip() {
+        // This test verifies that raw keys decode to their bytes, reject overlong
+        // input, never produce the empty-tree sentinel, and reject hashed keys
+        for bytes in [&b""[..], b"alice", b"a\0", &[0xff; MAX_RAW_KEY_LEN]] {
+            let key = raw_key(bytes).expect("Short keys should be encodable");
+            assert_eq!(raw_key_bytes(&key), Some(bytes), "Bytes should round-trip");
+            assert_ne!(key, MSTKey::default(), "No raw key is the sentinel");
+        }
+        assert!(
+            raw_key(&[1; MAX_RAW_KEY_LEN + 1]).is_none(),
+            "Overlong keys should be rejected"
+        );
+        assert!(
+            raw_key_bytes(&create_key(b"alice")).is_none(),
+            "A hashed key should not decode as a raw key"
+        );
+    }
+
+    #[test]
+    fn test_raw_keys_order_tree_lexicographically() {
+        // This test verifies that a tree keyed by raw keys iterates in the
+        // lexicographic order of the original bytes, prefixes first
+        let mut names: Vec<&[u8]> = vec![
+            b"carol", b"alice", b"bob", b"al", b"al\0", b"alicia", b"", b"zed", b"b",
+        ];
+        let mut tree = MST::new();
+        for name in &names {
+            let key = raw_key(name).unwrap();
+            tree.insert(key, TestValue { key, data: [0; 4] });
+        }
+
+        names.sort();
+        let iterated: Vec<&[u8]> = tree
+            .iter()
+            .map(|entry| raw_key_bytes(&entry.key).unwrap())
+            .collect();
+        assert_eq!(iterated, names, "Entries should be in lexicographic order");
+    }
+
+    #[test]
+    fn test_scan_prefix() {
+        // This test verifies that scan_prefix yields exactly the entries sharing
+        // the prefix in order, everything for an empty prefix, and nothing for an
+        // unmatched or overlong prefix
+        let names: Vec<Vec<u8>> = (0..300u32)
+            .map(|i| format!("user{}", i).into_bytes())
+            .chain([b"usa".to_vec(), b"use".to_vec(), b"zulu".to_vec()])
+            .collect();
+        let mut tree = MST::new();
+        for name in &names {
+            let key = raw_key(name).unwrap();
+            tree.insert(key, TestValue { key, data: [0; 4] });
+        }
+
+        let scan = |prefix: &[u8]| -> Vec<Vec<u8>> {
+            tree.scan_prefix(prefix)
+                .map(|entry| raw_key_bytes(&entry.key).unwrap().to_vec())
+                .collect()
+        };
+        let expected = |prefix: &[u8]| -> Vec<Vec<u8>> {
+            let mut matching: Vec<Vec<u8>> = names
+                .iter()
+                .filter(|name| name.starts_with(prefix))
+                .cloned()
+                .collect();
+            matching.sort();
+            matching
+        };
+
+        for prefix in [&b"user1"[..], b"user", b"us", b"user299", b"zu"] {
+            assert_eq!(
+                scan(prefix),
+                expected(prefix),
+                "Prefix {:?} should match its entries in order",
+                prefix
+            );
+        }
+        assert_eq!(
+            scan(b"").len(),
+            names.len(),
+            "Empty prefix matches everything"
+        );
+        assert!(
+            scan(b"nobody").is_empty(),
+            "Unmatched prefix yields nothing"
+        );
+        assert!(
+            scan(&[b'u'; MAX_RAW_KEY_LEN + 1]).is_empty(),
+            "Overlong prefix yields nothing"
+        );
+    }
+}
+
+/// # Traversal Tests
+///
+/// These tests verify custom visitors driven by `traverse`, and how the
+/// `TraversalControl` they return steers the walk.
+mod traversal_tests {
+    use super::*;
+    use mst::{TraversalControl, TraversalEvent, TraversalStrategy};
+
+    fn build_tree() -> MST<TestValue> {
+        let mut mst = MST::new();
+        for i in 0..300u32 {
+            let key = create_key(&i.to_be_bytes());
+            mst.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        mst
+    }
+
+    #[test]
+    fn test_traverse_visits_every_page_and_entry() {
+        // This test verifies that both strategies reach every reachable page and
+        // entry, that MSTOrder yields entries in key order, and that every
+        // visited page is exited
+        let mst = build_tree();
+        let page_count = mst.store.reachable_set(mst.root.unwrap()).len();
+        let expected: Vec<_> = mst.iter().map(|entry| entry.key).collect();
+
+        for strategy in [TraversalStrategy::DepthFirst, TraversalStrategy::MSTOrder] {
+            let (mut visited, mut exited, mut keys) = (0, 0, Vec::new());
+            let result: Option<()> = mst.traverse(strategy, |event| {
+                match event {
+                    TraversalEvent::VisitNode(..) => visited += 1,
+                    TraversalEvent::ExitNode(_) => exited += 1,
+                    TraversalEvent::VisitEntry(_, entry) => keys.push(entry.key),
+                }
+                TraversalControl::Continue
+            });
+            assert_eq!(result, None, "A full traversal returns nothing");
+            assert_eq!(
+                visited, page_count,
+                "{:?} should visit every page",
+                strategy
+            );
+            assert_eq!(exited, visited, "{:?} should exit every page", strategy);
+            if strategy == TraversalStrategy::MSTOrder {
+                assert_eq!(keys, expected, "MSTOrder should yield keys in order");
+            } else {
+                keys.sort_by(TestValue::compare_keys);
+                assert_eq!(keys, expected, "DepthFirst should yield every key");
+            }
+        }
+    }
+
+    #[test]
+    fn test_traverse_skip_and_return() {
+        // This test verifies that Skip on the root page leaves out all of its
+        // entries and children, and that Return ends the walk with its value
+        let mst = build_tree();
+
+        for strategy in [TraversalStrategy::DepthFirst, TraversalStrategy::MSTOrder] {
+            let root = mst.root.unwrap();
+            let mut entries_seen = 0;
+            mst.traverse::<_, ()>(strategy, |event| match event {
+                TraversalEvent::VisitNode(key, _) if key == root => TraversalControl::Skip,
+                TraversalEvent::VisitEntry(page, _) => {
+                    assert_ne!(page, root, "Skipped page's entries should not be visited");
+                    entries_seen += 1;
+                    TraversalControl::Continue
+                }
+                _ => TraversalControl::Continue,
+            });
+            if strategy == TraversalStrategy::DepthFirst {
+                assert_eq!(entries_seen, 0, "Skipping the root skips everything");
+            }
+
+            let target = mst.select(150).unwrap().key;
+            let mut entries_seen = 0;
+            let found = mst.traverse(strategy, |event| match event {
+                TraversalEvent::VisitEntry(_, entry) => {
+                    entries_seen += 1;
+                    if entry.key == target {
+                        TraversalControl::Return(entry.value)
+                    } else {
+                        TraversalControl::Continue
+                    }
+                }
+                _ => TraversalControl::Continue,
+            });
+            assert_eq!(
+                found,
+                mst.get_value(target),
+                "Return should yield its value"
+            );
+            if strategy == TraversalStrategy::MSTOrder {
+                assert_eq!(
+                    entries_seen, 151,
+                    "Nothing after the match should be visited"
+                );
+            }
+        }
+
+        let empty = MST::<TestValue>::new();
+        let calls = empty.traverse::<_, ()>(TraversalStrategy::MSTOrder, |_| {
+            panic!("An empty tree has nothing to visit")
+        });
+        assert_eq!(calls, None);
+    }
+}
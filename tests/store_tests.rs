@@ -1,6 +1,8 @@
+use mst::store::PageStore;
 use mst::store::{Page, PageData};
 use mst::test_utils::{TestValue, create_key};
-use mst::{MSTKey, Store};
+use mst::{MST, MSTKey, Store};
+use sha2::Sha256;
 
 /// # Store Tests
 ///
@@ -29,6 +31,7 @@ fn test_store_basic_operations() {
     // Create a simple empty page
     let page = Page {
         level: 1,
+        subtree_size: 0,
         low: None,
         list: vec![],
     };
@@ -56,6 +59,32 @@ fn test_store_basic_operations() {
     );
 }
 
+#[test]
+fn test_store_len() {
+    // This test verifies that the store counts every page it holds, and that
+    // gc shrinks the count to the pages reachable from the root
+    let store = Store::<MSTKey, Page<MSTKey, TestValue>>::new();
+    assert_eq!(store.len(), 0);
+    assert!(store.is_empty());
+
+    let mut tree = build_tree(0..300);
+    let total = tree.store.len();
+    assert_eq!(total, tree.store.iter().count());
+    let reachable = tree.store.reachable_set(tree.root.unwrap()).len();
+    assert!(
+        total > reachable,
+        "Inserts should leave replaced pages behind"
+    );
+
+    tree.gc(&[]);
+    assert_eq!(
+        tree.store.len(),
+        reachable,
+        "gc should free unreachable pages"
+    );
+    assert!(!tree.store.is_empty());
+}
+
 #[test]
 fn test_page_references() {
     // This test verifies the reference structure of pages in the store,
@@ -73,6 +102,7 @@ fn test_page_references() {
     // Create a grandchild page (leaf node)
     let grandchild_page = Page::<MSTKey, TestValue> {
         level: 3,
+        subtree_size: 0,
         low: None, // Leaf node has no children
         list: vec![],
     };
@@ -83,6 +113,7 @@ fn test_page_references() {
     // Create a child page that references the grandchild
     let child1_page = Page {
         level: 2,
+        subtree_size: 1,
         low: None,
         list: vec![PageData {
             key: grandchild_key,
@@ -100,6 +131,7 @@ fn test_page_references() {
     // Create a second child page (sibling)
     let child2_page = Page {
         level: 2,
+        subtree_size: 0,
         low: None,
         list: vec![],
     };
@@ -110,6 +142,7 @@ fn test_page_references() {
     // Create a root page that references both children
     let root_page = Page {
         level: 1,
+        subtree_size: 2,
         low: Some(child1_key), // Low child points to first child
         list: vec![PageData {
             key: child2_key,
@@ -164,6 +197,7 @@ fn test_store_content_addressing() {
 
     let page1 = Page {
         level: 1,
+        subtree_size: 1,
         low: None,
         list: vec![PageData {
             key: data_key,
@@ -174,6 +208,7 @@ fn test_store_content_addressing() {
 
     let page2 = Page {
         level: 1,
+        subtree_size: 1,
         low: None,
         list: vec![PageData {
             key: data_key,
@@ -201,6 +236,7 @@ fn test_store_content_addressing() {
     // Change the content slightly
     let page3 = Page {
         level: 1,
+        subtree_size: 1,
         low: None,
         list: vec![PageData {
             key: data_key,
@@ -224,3 +260,820 @@ fn test_store_content_addressing() {
         "Store should not recognize key3 as existing"
     );
 }
+
+#[test]
+fn test_reachable_set() {
+    // This test verifies that reachable_set collects exactly the present pages
+    // reachable from a root, skipping missing ones, and terminates on cycles
+
+    let mut store = Store::<MSTKey, Page<MSTKey, TestValue>>::new();
+    let root_key = create_key(b"root");
+    let child_key = create_key(b"child");
+    let missing_key = create_key(b"missing");
+    let unrelated_key = create_key(b"unrelated");
+
+    // The child points back at the root, forming a cycle
+    store.put(
+        child_key,
+        Page {
+            level: 1,
+            subtree_size: 0,
+            low: Some(root_key),
+            list: vec![],
+        },
+    );
+    store.put(
+        root_key,
+        Page {
+            level: 2,
+            subtree_size: 1,
+            low: Some(child_key),
+            list: vec![PageData {
+                key: missing_key,
+                value: TestValue {
+                    key: missing_key,
+                    data: [0; 4],
+                },
+                next: Some(missing_key),
+            }],
+        },
+    );
+    store.put(
+        unrelated_key,
+        Page {
+            level: 1,
+            subtree_size: 0,
+            low: Some(unrelated_key), // Refers to itself
+            list: vec![],
+        },
+    );
+
+    let reachable = store.reachable_set(root_key);
+    assert_eq!(
+        reachable,
+        [root_key, child_key].into_iter().collect(),
+        "Only present pages reachable from the root should be collected"
+    );
+    assert_eq!(
+        store.missing_set(root_key),
+        [missing_key].into_iter().collect(),
+        "missing_set should report the rest of the references"
+    );
+    assert_eq!(
+        store.reachable_set(unrelated_key),
+        [unrelated_key].into_iter().collect(),
+        "A self-referencing page should be collected once"
+    );
+    assert!(
+        store.reachable_set(missing_key).is_empty(),
+        "A missing root has nothing reachable"
+    );
+}
+
+/// A `PageStore` backend wrapping the in-memory store and counting writes
+#[derive(Default)]
+struct CountingStore {
+    inner: Store<MSTKey, Page<MSTKey, TestValue>>,
+    puts: usize,
+}
+
+impl PageStore<MSTKey, Page<MSTKey, TestValue>> for CountingStore {
+    type Iter<'a> = <Store<MSTKey, Page<MSTKey, TestValue>> as PageStore<
+        MSTKey,
+        Page<MSTKey, TestValue>,
+    >>::Iter<'a>;
+
+    fn put(&mut self, key: MSTKey, value: Page<MSTKey, TestValue>) -> MSTKey {
+        self.puts += 1;
+        self.inner.put(key, value)
+    }
+
+    fn get(&self, key: MSTKey) -> Option<&Page<MSTKey, TestValue>> {
+        self.inner.get(key)
+    }
+
+    fn remove(&mut self, key: MSTKey) -> Option<Page<MSTKey, TestValue>> {
+        self.inner.remove(key)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.inner.iter()
+    }
+}
+
+#[test]
+fn test_custom_page_store_backend() {
+    // This test verifies that an MST over a custom PageStore builds the same
+    // tree as the default in-memory store, with all writes going to the backend
+    let mut default_tree: MST<TestValue> = MST::new();
+    let mut custom_tree = MST::<TestValue, Sha256, CountingStore>::with_hasher();
+
+    for i in 0..200u32 {
+        let key = create_key(&i.to_be_bytes());
+        let value = TestValue {
+            key,
+            data: [i as u8, 0, 0, 0],
+        };
+        default_tree.insert(key, value);
+        custom_tree.insert(key, value);
+    }
+
+    assert_eq!(
+        custom_tree.root, default_tree.root,
+        "Backend should not affect the tree's root"
+    );
+    assert_eq!(
+        custom_tree.to_list(),
+        default_tree.to_list(),
+        "Backend should serve the same entries"
+    );
+    assert!(
+        custom_tree.store.puts > 0,
+        "Writes should reach the backend"
+    );
+    assert!(
+        custom_tree
+            .store
+            .missing_set(custom_tree.root.unwrap())
+            .is_empty(),
+        "Default missing_set should find every page in the backend"
+    );
+}
+
+#[test]
+fn test_collect_pages_syncs_a_replica() {
+    // This test verifies that serving a replica's missing_set with
+    // collect_pages brings it fully up to date in one round trip
+
+    let mut source: MST<TestValue> = MST::new();
+    for i in 0..150u32 {
+        let key = create_key(&i.to_be_bytes());
+        source.insert(
+            key,
+            TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            },
+        );
+    }
+
+    // The replica starts with an older copy of the store
+    let mut replica_store = source.store.clone();
+    for i in 150..300u32 {
+        let key = create_key(&i.to_be_bytes());
+        source.insert(
+            key,
+            TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            },
+        );
+    }
+
+    let mut wanted = replica_store.missing_set(source.root.unwrap());
+    assert!(!wanted.is_empty(), "Replica should be missing new pages");
+    wanted.insert(create_key(b"unknown page"));
+
+    let pages = source.store.collect_pages(&wanted);
+    let unique: std::collections::HashSet<MSTKey> = pages.iter().map(|(key, _)| *key).collect();
+    assert_eq!(unique.len(), pages.len(), "Each page should be sent once");
+
+    for (key, page) in pages {
+        replica_store.put(key, page);
+    }
+    assert!(
+        replica_store.missing_set(source.root.unwrap()).is_empty(),
+        "Replica should have every page after one exchange"
+    );
+
+    let replica = MST::with_store(source.root, replica_store);
+    assert_eq!(
+        replica.to_list(),
+        source.to_list(),
+        "Replica should match the source"
+    );
+
+    let root_only: std::collections::HashSet<MSTKey> = source.root.into_iter().collect();
+    let root_page = source.get(source.root.unwrap()).unwrap();
+    let have: std::collections::HashSet<MSTKey> = root_page.low.into_iter().collect();
+    let without_low = source.store.collect_pages_except(&root_only, &have);
+    assert!(
+        without_low.iter().all(|(key, _)| !have.contains(key)),
+        "Pages the peer already has should not be sent"
+    );
+}
+
+#[test]
+fn test_put_many_imports_a_batch() {
+    // This test verifies that a batch of pages from collect_pages can be stored
+    // in one call, with repeated keys stored once, and that extend does the same
+    let source = build_tree(0..300);
+    let root = source.root.unwrap();
+    let mut pages = source
+        .store
+        .collect_pages(&source.store.reachable_set(root));
+    let count = pages.len();
+    pages.extend(pages.clone());
+
+    let mut replica: Store<MSTKey, Page<MSTKey, TestValue>> = Store::new();
+    replica.put_many(pages.clone());
+    assert_eq!(replica.len(), count, "Repeated keys should be stored once");
+    assert!(replica.missing_set(root).is_empty());
+    assert_eq!(
+        MST::with_store(source.root, replica).to_list(),
+        source.to_list()
+    );
+
+    let mut extended: Store<MSTKey, Page<MSTKey, TestValue>> = Store::new();
+    extended.extend(pages.clone());
+    assert_eq!(extended.len(), count);
+
+    // A custom backend gets the pages one at a time through the default
+    let mut custom = CountingStore::default();
+    PageStore::put_many(&mut custom, pages);
+    assert_eq!(custom.puts, 2 * count);
+    assert!(custom.missing_set(root).is_empty());
+}
+
+/// Builds a tree holding the values for the given integer keys
+fn build_tree(keys: impl IntoIterator<Item = u32>) -> MST<TestValue> {
+    let mut tree = MST::new();
+    for i in keys {
+        let key = create_key(&i.to_be_bytes());
+        tree.insert(
+            key,
+            TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            },
+        );
+    }
+    tree
+}
+
+#[test]
+fn test_tree_is_shared_across_threads() {
+    // This test verifies that a tree over the plain store can be read from
+    // several threads at once through an Arc, without any locking
+    let tree = std::sync::Arc::new(build_tree(0..500));
+
+    std::thread::scope(|scope| {
+        for offset in 0..4u32 {
+            let tree = std::sync::Arc::clone(&tree);
+            scope.spawn(move || {
+                for i in (offset..500).step_by(4) {
+                    let key = create_key(&i.to_be_bytes());
+                    assert_eq!(
+                        tree.get_value(key).map(|value| value.data[0]),
+                        Some(i as u8),
+                        "Every thread should read every value"
+                    );
+                }
+            });
+        }
+    });
+}
+
+/// A tree whose pages go through a copy-on-write layer over a plain store
+type Transaction = MST<TestValue, Sha256, mst::OverlayStore<MSTKey, Page<MSTKey, TestValue>>>;
+
+/// Every page of a store with its encoding, in key order
+fn store_bytes<S: PageStore<MSTKey, Page<MSTKey, TestValue>>>(store: &S) -> Vec<(MSTKey, Vec<u8>)> {
+    let mut pages: Vec<_> = store
+        .iter()
+        .map(|(key, page)| (*key, mst::encode_page(page)))
+        .collect();
+    pages.sort();
+    pages
+}
+
+/// Inserts and removes some keys and collects the garbage, so the transaction
+/// both writes pages and removes pages of the base
+fn run_transaction(tx: &mut Transaction) {
+    for i in 300..400u32 {
+        let key = create_key(&i.to_be_bytes());
+        tx.insert(
+            key,
+            TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            },
+        );
+    }
+    for i in 0..50u32 {
+        tx.remove(create_key(&i.to_be_bytes()));
+    }
+    tx.gc(&[]);
+}
+
+#[test]
+fn test_overlay_store_discard() {
+    // This test verifies that a transaction sees its own changes while the base
+    // store does not, and that discarding it leaves the base byte-identical
+    let tree = build_tree(0..300);
+    let root = tree.root;
+    let before = store_bytes(&tree.store);
+
+    let mut tx = Transaction::with_store_and_hasher(root, mst::OverlayStore::new(tree.store));
+    run_transaction(&mut tx);
+    assert_eq!(tx.len(), 350);
+    assert_eq!(tx.to_list(), build_tree(50..400).to_list());
+    assert_eq!(tx.verify_integrity(), Ok(()));
+    assert!(
+        tx.store.iter().count() < before.len() + 100,
+        "gc should hide base pages"
+    );
+    assert_eq!(
+        store_bytes(tx.store.base()),
+        before,
+        "The base is untouched"
+    );
+
+    tx.store.discard();
+    assert_eq!(
+        store_bytes(&tx.store),
+        before,
+        "Nothing should be left in the layer"
+    );
+    let store = tx.store.into_base();
+    assert_eq!(store_bytes(&store), before, "Discarding restores the base");
+    assert_eq!(
+        MST::with_store(root, store).to_list(),
+        build_tree(0..300).to_list()
+    );
+}
+
+#[test]
+fn test_overlay_store_commit() {
+    // This test verifies that committing a transaction leaves the base holding
+    // exactly what the transaction saw
+    let tree = build_tree(0..300);
+    let mut tx = Transaction::with_store_and_hasher(tree.root, mst::OverlayStore::new(tree.store));
+    run_transaction(&mut tx);
+    let seen = store_bytes(&tx.store);
+
+    tx.store.commit();
+    assert_eq!(
+        store_bytes(tx.store.base()),
+        seen,
+        "The base should hold the changes"
+    );
+    let committed = MST::with_store(tx.root, tx.store.into_base());
+    assert_eq!(committed.root, build_tree(50..400).root);
+    assert_eq!(committed.verify_integrity(), Ok(()));
+}
+
+#[test]
+fn test_store_capacity() {
+    // This test verifies that a preallocated store holds its pages without
+    // growing and builds the same tree, and that shrinking keeps every page
+    let expected = build_tree(0..500);
+    let writes = expected.store.iter().count();
+
+    let mut tree = MST::with_capacity(writes);
+    let capacity = tree.store.capacity();
+    assert!(capacity >= writes, "Capacity should be reserved up front");
+    for i in 0..500u32 {
+        let key = create_key(&i.to_be_bytes());
+        tree.insert(
+            key,
+            TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            },
+        );
+    }
+    assert_eq!(
+        tree.store.capacity(),
+        capacity,
+        "The store should not have grown"
+    );
+    assert_eq!(tree.root, expected.root);
+
+    tree.gc(&[]);
+    tree.store.shrink_to_fit();
+    assert!(
+        tree.store.capacity() < capacity,
+        "Shrinking should release room"
+    );
+    assert_eq!(tree.to_list(), expected.to_list());
+
+    let store: Store<MSTKey, Page<MSTKey, TestValue>> = Store::with_capacity(0);
+    assert_eq!(store.iter().count(), 0);
+}
+
+#[cfg(feature = "serde")]
+mod serde_tests {
+    use super::*;
+
+    #[test]
+    fn test_store_round_trips_through_serde() {
+        // This test verifies that a serialized store reads back with every page
+        // unchanged, so the tree keeps its root and contents
+        let tree = build_tree(0..300);
+        let json = serde_json::to_string(&tree.store).unwrap();
+        let store: Store<MSTKey, Page<MSTKey, TestValue>> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(store.len(), tree.store.len());
+        let restored = MST::with_store(tree.root, store);
+        assert!(
+            restored.verify_integrity().is_ok(),
+            "Every hash should match"
+        );
+        assert_eq!(restored.to_list(), tree.to_list());
+    }
+
+    #[test]
+    fn test_page_round_trips_through_serde() {
+        // This test verifies that a single page serializes with all its fields
+        let tree = build_tree(0..300);
+        let page = tree.root_page().unwrap();
+        let json = serde_json::to_string(page).unwrap();
+        let decoded: Page<MSTKey, TestValue> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded.level, page.level);
+        assert_eq!(decoded.subtree_size, page.subtree_size);
+        assert_eq!(decoded.low, page.low);
+        assert_eq!(decoded.list.len(), page.list.len());
+        assert!(
+            decoded
+                .list
+                .iter()
+                .zip(&page.list)
+                .all(|(a, b)| { a.key == b.key && a.value == b.value && a.next == b.next })
+        );
+    }
+}
+
+#[cfg(feature = "concurrent")]
+mod concurrent_store_tests {
+    use super::*;
+    use mst::{ConcurrentStore, ConcurrentStoreHandle};
+    use std::time::Instant;
+
+    #[test]
+    fn test_concurrent_store_reads_during_writes() {
+        // This test verifies that readers see complete pages while a writer
+        // fills a shared store, and that the result can back a tree
+        let source = build_tree(0..500);
+        let root = source.root.unwrap();
+        let shared = ConcurrentStore::new();
+        let pages: Vec<_> = source
+            .store
+            .iter()
+            .map(|(key, page)| (*key, page.clone()))
+            .collect();
+
+        std::thread::scope(|scope| {
+            let writer = shared.clone();
+            scope.spawn(move || {
+                for (key, page) in pages {
+                    writer.put(key, page);
+                }
+            });
+            for _ in 0..3 {
+                let reader = shared.clone();
+                let source = &source;
+                scope.spawn(move || {
+                    while !reader.has(root) {
+                        std::thread::yield_now();
+                    }
+                    let page = reader
+                        .get(source.root.unwrap())
+                        .expect("Page should stay present");
+                    assert_eq!(
+                        page.list.len(),
+                        source.get(source.root.unwrap()).unwrap().list.len(),
+                        "A read page should be complete"
+                    );
+                });
+            }
+        });
+
+        assert!(
+            shared.missing_set(source.root.unwrap()).is_empty(),
+            "Every page should be readable after the writes"
+        );
+        let tree = MST::with_store(source.root, shared.snapshot());
+        assert_eq!(
+            tree.to_list(),
+            source.to_list(),
+            "Snapshot should back a tree"
+        );
+
+        assert!(shared.remove(root).is_some(), "Removal returns the page");
+        assert!(!shared.has(root), "Removed page should be gone");
+    }
+
+    #[test]
+    fn test_tree_over_concurrent_store_handle() {
+        // This test verifies that a handle backs a tree writing through to the
+        // shared store, that other threads can read the tree from their own
+        // handles, that a handle catches up with later writes on refresh, and
+        // that only deletes through shared reach the shared store
+        let shared = ConcurrentStore::new();
+        let mut reader = shared.handle();
+        let mut tree: MST<TestValue, Sha256, ConcurrentStoreHandle<MSTKey, _>> =
+            MST::with_store_and_hasher(None, shared.handle());
+        for i in 0..300u32 {
+            let key = create_key(&i.to_be_bytes());
+            tree.insert(
+                key,
+                TestValue {
+                    key,
+                    data: [i as u8, 0, 0, 0],
+                },
+            );
+        }
+        let expected = build_tree(0..300);
+        assert_eq!(tree.root, expected.root);
+        assert_eq!(tree.to_list(), expected.to_list());
+        assert!(
+            shared.missing_set(tree.root.unwrap()).is_empty(),
+            "Every page should be in the shared store"
+        );
+
+        assert!(
+            !reader.has(tree.root.unwrap()),
+            "A handle does not see later writes"
+        );
+        reader.refresh();
+        std::thread::scope(|scope| {
+            for _ in 0..3 {
+                let view: MST<TestValue, Sha256, _> =
+                    MST::with_store_and_hasher(tree.root, reader.clone());
+                let expected = &expected;
+                scope.spawn(move || {
+                    assert_eq!(view.to_list(), expected.to_list());
+                });
+            }
+        });
+
+        let root = tree.root.unwrap();
+        assert!(tree.store.remove(root).is_some());
+        assert!(
+            !tree.store.has(root),
+            "Removal drops the page from the handle"
+        );
+        assert!(shared.has(root), "The shared store keeps it");
+        assert!(tree.store.shared().remove(root).is_some());
+        assert!(
+            !shared.has(root),
+            "Deleting through shared reaches every handle"
+        );
+        assert!(reader.has(root), "Until refresh, the reader keeps its page");
+        reader.refresh();
+        assert!(!reader.has(root));
+    }
+
+    #[test]
+    fn test_trees_sharing_a_concurrent_store_keep_their_pages() {
+        // This test verifies that gc and into_iter on one tree over a shared
+        // store leave the pages of another tree over the same store in place
+        let shared = ConcurrentStore::new();
+        let build = |range: std::ops::Range<u32>| {
+            let mut tree: MST<TestValue, Sha256, ConcurrentStoreHandle<MSTKey, _>> =
+                MST::with_store_and_hasher(None, shared.handle());
+            for i in range {
+                let key = create_key(&i.to_be_bytes());
+                tree.insert(
+                    key,
+                    TestValue {
+                        key,
+                        data: [i as u8, 0, 0, 0],
+                    },
+                );
+            }
+            tree
+        };
+        let first = build(0..200);
+        let mut second = build(200..400);
+        let first_root = first.root.unwrap();
+
+        assert!(
+            second.gc(&[]) > 0,
+            "The pages of first are unreachable here"
+        );
+        assert!(
+            shared.missing_set(first_root).is_empty(),
+            "gc on second should not delete the pages of first"
+        );
+        assert_eq!(second.into_iter().count(), 200);
+        assert!(shared.missing_set(first_root).is_empty());
+
+        let mut reopened: MST<TestValue, Sha256, _> =
+            MST::with_store_and_hasher(Some(first_root), shared.handle());
+        assert_eq!(reopened.to_list(), build_tree(0..200).to_list());
+        reopened.gc(&[]);
+        assert_eq!(reopened.len(), 200);
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with --ignored --nocapture"]
+    fn bench_concurrent_reads() {
+        // Compares get_value from 4 threads on the same tree over the plain
+        // store, shared through an Arc, and over handles on a ConcurrentStore,
+        // one per thread
+        let threads = 4;
+        let rounds = 50;
+        let tree = std::sync::Arc::new(build_tree(0..10_000));
+        let shared = ConcurrentStore::new();
+        for (key, page) in tree.store.iter() {
+            shared.put(*key, page.clone());
+        }
+        let keys: Vec<MSTKey> = (0..10_000u32)
+            .map(|i| create_key(&i.to_be_bytes()))
+            .collect();
+        let lookups = threads * rounds * keys.len();
+
+        let start = Instant::now();
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                let (tree, keys) = (&tree, &keys);
+                scope.spawn(move || {
+                    for _ in 0..rounds {
+                        for key in keys {
+                            assert!(tree.get_value(*key).is_some());
+                        }
+                    }
+                });
+            }
+        });
+        println!(
+            "MST over Store get_value: {:.0} lookups/s",
+            lookups as f64 / start.elapsed().as_secs_f64()
+        );
+
+        type HandleTree =
+            MST<TestValue, Sha256, ConcurrentStoreHandle<MSTKey, Page<MSTKey, TestValue>>>;
+        let handle_trees: Vec<HandleTree> = (0..threads)
+            .map(|_| MST::with_store_and_hasher(tree.root, shared.handle()))
+            .collect();
+        let start = Instant::now();
+        std::thread::scope(|scope| {
+            for handle_tree in &handle_trees {
+                let keys = &keys;
+                scope.spawn(move || {
+                    for _ in 0..rounds {
+                        for key in keys {
+                            assert!(handle_tree.get_value(*key).is_some());
+                        }
+                    }
+                });
+            }
+        });
+        println!(
+            "MST over ConcurrentStoreHandle get_value: {:.0} lookups/s",
+            lookups as f64 / start.elapsed().as_secs_f64()
+        );
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_store_tests {
+    use super::*;
+    use mst::{AsyncPageStore, MstAsync};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Waker};
+
+    /// Drives a future to completion on the current thread
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    /// A future that is pending once before completing, like a network round trip
+    struct RoundTrip(bool);
+
+    impl Future for RoundTrip {
+        type Output = ();
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+            if std::mem::replace(&mut self.0, true) {
+                Poll::Ready(())
+            } else {
+                context.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    /// A store that completes every request after a round trip, counting fetches
+    #[derive(Default)]
+    struct RemoteStore {
+        pages: Store<MSTKey, Page<MSTKey, TestValue>>,
+        fetches: AtomicUsize,
+    }
+
+    impl AsyncPageStore<MSTKey, Page<MSTKey, TestValue>> for RemoteStore {
+        fn get(&self, key: MSTKey) -> impl Future<Output = Option<Page<MSTKey, TestValue>>> + Send {
+            self.fetches.fetch_add(1, Ordering::Relaxed);
+            let page = self.pages.get(key).cloned();
+            async move {
+                RoundTrip(false).await;
+                page
+            }
+        }
+
+        fn put(
+            &mut self,
+            key: MSTKey,
+            value: Page<MSTKey, TestValue>,
+        ) -> impl Future<Output = MSTKey> + Send {
+            self.pages.put(key, value);
+            async move {
+                RoundTrip(false).await;
+                key
+            }
+        }
+
+        fn has(&self, key: MSTKey) -> impl Future<Output = bool> + Send {
+            let present = self.pages.has(key);
+            async move {
+                RoundTrip(false).await;
+                present
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_tree_matches_sync_tree() {
+        // This test verifies that inserts through a remote store build the same
+        // tree as the synchronous one, and that a lookup fetches one page per level
+        let expected = build_tree(0..300);
+        let mut tree =
+            MstAsync::<TestValue, Sha256, RemoteStore>::with_store(None, RemoteStore::default());
+        for i in 0..300u32 {
+            let key = create_key(&i.to_be_bytes());
+            let value = TestValue {
+                key,
+                data: [i as u8, 0, 0, 0],
+            };
+            block_on(tree.insert(key, value));
+        }
+        assert_eq!(tree.root, expected.root, "Both trees should be canonical");
+        assert!(
+            tree.store.pages.missing_set(tree.root.unwrap()).is_empty(),
+            "Every page of the tree should have been written"
+        );
+
+        for i in [0u32, 17, 299, 1000] {
+            let key = create_key(&i.to_be_bytes());
+            tree.store.fetches.store(0, Ordering::Relaxed);
+            assert_eq!(block_on(tree.get_value(key)), expected.get_value(key));
+            let path = expected.get_path(key).len();
+            assert_eq!(
+                tree.store.fetches.load(Ordering::Relaxed),
+                path,
+                "A lookup should fetch each page on its path once"
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_memory_store_is_async() {
+        // This test verifies that the plain store serves an async tree
+        let source = build_tree(0..100);
+        let key = create_key(&42u32.to_be_bytes());
+        let mut tree: MstAsync<TestValue> = MstAsync::with_store(source.root, source.store);
+        assert!(block_on(AsyncPageStore::has(
+            &tree.store,
+            tree.root.unwrap()
+        )));
+        assert_eq!(
+            block_on(tree.get_value(key)).map(|value| value.data[0]),
+            Some(42)
+        );
+
+        let new_key = create_key(&100u32.to_be_bytes());
+        let value = TestValue {
+            key: new_key,
+            data: [100, 0, 0, 0],
+        };
+        block_on(tree.insert(new_key, value));
+        assert_eq!(tree.root, build_tree(0..101).root);
+        assert_eq!(block_on(tree.get_value(new_key)), Some(value));
+    }
+
+    #[test]
+    fn test_async_lookup_stops_at_cycles() {
+        // This test verifies that an async lookup gives up at a page that is its
+        // own child instead of looping
+        let mut source = build_tree(0..100);
+        let root = source.root.unwrap();
+        let smallest = source.min_key().unwrap();
+        let mut page = source.store.get(root).cloned().unwrap();
+        page.low = Some(root);
+        source.store.put(root, page);
+
+        let tree: MstAsync<TestValue> = MstAsync::with_store(source.root, source.store);
+        assert_eq!(block_on(tree.get_value(smallest)), None);
+    }
+}